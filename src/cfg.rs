@@ -1,12 +1,88 @@
 //! # Configuration module
 //!
 //! This module provide utilities to parse configuration
-use std::{convert::TryFrom, env, error::Error, path::PathBuf};
+use std::{
+    collections::BTreeMap,
+    convert::TryFrom,
+    env,
+    error::Error,
+    fmt, fs,
+    path::{Path, PathBuf},
+};
 
 use config::{Config, Environment, File};
 use serde::Deserialize;
 
+/// Value written in place of secrets in debug output, secrets are redacted so
+/// that verbose logs could be shared
+pub const REDACTED: &str = "***";
+
+/// Endpoint used when none is configured
+pub const DEFAULT_ENDPOINT: &str = "https://eu.api.ovh.com/1.0";
+
+/// Statuses on which requests are retried when none are configured, '503' is
+/// left out as the api answers it during maintenance windows, which last longer
+/// than the retries
+pub const RETRY_ON: [u16; 3] = [429, 502, 504];
+
 #[derive(Deserialize, Clone, Debug)]
+pub struct Retry {
+    /// Number of times a request is retried, zero disables the retries
+    #[serde(rename = "attempts", default = "Retry::default_attempts")]
+    pub attempts: u32,
+    /// Statuses of the responses on which a request is retried
+    #[serde(rename = "on", default = "Retry::default_on")]
+    pub on: Vec<u16>,
+    /// Retry creations, which carry an idempotency key, only for endpoints known
+    /// to deduplicate them
+    #[serde(rename = "creations", default)]
+    pub creations: bool,
+}
+
+impl Default for Retry {
+    #[tracing::instrument]
+    fn default() -> Self {
+        Self {
+            attempts: Self::default_attempts(),
+            on: Self::default_on(),
+            creations: false,
+        }
+    }
+}
+
+impl Retry {
+    #[tracing::instrument]
+    fn default_attempts() -> u32 {
+        2
+    }
+
+    #[tracing::instrument]
+    fn default_on() -> Vec<u16> {
+        RETRY_ON.to_vec()
+    }
+
+    /// Check that the statuses are plausible http statuses
+    #[tracing::instrument]
+    pub fn validate(&self) -> Result<(), Box<dyn Error + Send + Sync>> {
+        for status in &self.on {
+            validate_status(*status)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Check that the value is a plausible http status, between 100 and 599
+#[tracing::instrument]
+pub fn validate_status(status: u16) -> Result<u16, Box<dyn Error + Send + Sync>> {
+    if !(100..=599).contains(&status) {
+        return Err(format!("'{}' is not an http status, expect 100 to 599", status).into());
+    }
+
+    Ok(status)
+}
+
+#[derive(Deserialize, Clone)]
 pub struct Ovh {
     #[serde(rename = "endpoint")]
     pub endpoint: String,
@@ -16,50 +92,501 @@ pub struct Ovh {
     pub application_secret: String,
     #[serde(rename = "consumer-key")]
     pub consumer_key: Option<String>,
+    /// Maximum number of idle connections kept open to the api
+    #[serde(rename = "pool-max-idle")]
+    pub pool_max_idle: usize,
+    /// Number of seconds after which an idle connection is closed. The ovh api
+    /// front-ends close keep-alive connections on their side after a while, so
+    /// keep it short to avoid reusing a connection that the remote has dropped.
+    #[serde(rename = "pool-idle-timeout")]
+    pub pool_idle_timeout: u64,
+    /// Send an idempotency key with creation requests
+    #[serde(rename = "idempotency-keys")]
+    pub idempotency_keys: bool,
+    /// Tenant used by cloud commands when they are given none
+    #[serde(rename = "default-tenant")]
+    pub default_tenant: Option<String>,
+    /// Skip resources which could not be retrieved when listing instead of failing
+    #[serde(rename = "best-effort")]
+    pub best_effort: bool,
+    /// Number of seconds during which listed identifiers are kept in memory,
+    /// zero disables the cache
+    #[serde(rename = "id-cache-ttl")]
+    pub id_cache_ttl: u64,
+    /// Retries of requests which failed with a transient status
+    #[serde(rename = "retry", default)]
+    pub retry: Retry,
+    /// Name of the header in which the delegated identity is sent
+    #[serde(rename = "delegate-header", default)]
+    pub delegate_header: Option<String>,
+    /// Identity to which requests are scoped, e.g. a client account managed by
+    /// an agency
+    #[serde(rename = "delegate", default)]
+    pub delegate: Option<String>,
+    /// Print the curl command line equivalent to each request, only set from the
+    /// command line
+    #[serde(skip)]
+    pub print_curl: bool,
+    /// Accept payloads in which non-critical fields are missing, only set from
+    /// the command line
+    #[serde(skip)]
+    pub deserialize_lenient: bool,
+    /// File in which a json line is appended for each request, only set from
+    /// the command line
+    #[serde(skip)]
+    pub api_call_log: Option<PathBuf>,
+}
+
+impl fmt::Debug for Ovh {
+    /// Hides the application secret and the consumer key
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Ovh")
+            .field("endpoint", &self.endpoint)
+            .field("application_key", &self.application_key)
+            .field("application_secret", &REDACTED)
+            .field(
+                "consumer_key",
+                &self.consumer_key.as_ref().map(|_| REDACTED),
+            )
+            .field("pool_max_idle", &self.pool_max_idle)
+            .field("pool_idle_timeout", &self.pool_idle_timeout)
+            .field("idempotency_keys", &self.idempotency_keys)
+            .field("default_tenant", &self.default_tenant)
+            .field("best_effort", &self.best_effort)
+            .field("id_cache_ttl", &self.id_cache_ttl)
+            .field("retry", &self.retry)
+            .field("delegate_header", &self.delegate_header)
+            .field("delegate", &self.delegate)
+            .field("print_curl", &self.print_curl)
+            .field("deserialize_lenient", &self.deserialize_lenient)
+            .field("api_call_log", &self.api_call_log)
+            .finish()
+    }
+}
+
+impl Ovh {
+    /// Expand environment variables referenced in string values
+    #[tracing::instrument(skip(self))]
+    pub fn expand(self) -> Result<Self, Box<dyn Error + Send + Sync>> {
+        Ok(Self {
+            endpoint: expand(&self.endpoint)?,
+            application_key: expand(&self.application_key)?,
+            application_secret: expand(&self.application_secret)?,
+            consumer_key: match self.consumer_key {
+                Some(consumer_key) => Some(expand(&consumer_key)?),
+                None => None,
+            },
+            ..self
+        })
+    }
+}
+
+/// Credentials of another endpoint, e.g. an account on the canadian api, the
+/// other values are the ones of the '[ovh]' section
+#[derive(Deserialize, Clone)]
+pub struct Profile {
+    #[serde(rename = "endpoint")]
+    pub endpoint: String,
+    #[serde(rename = "application-key")]
+    pub application_key: String,
+    #[serde(rename = "application-secret")]
+    pub application_secret: String,
+    #[serde(rename = "consumer-key", default)]
+    pub consumer_key: Option<String>,
+}
+
+impl fmt::Debug for Profile {
+    /// Hides the application secret and the consumer key of the profile
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Profile")
+            .field("endpoint", &self.endpoint)
+            .field("application_key", &self.application_key)
+            .field("application_secret", &REDACTED)
+            .field(
+                "consumer_key",
+                &self.consumer_key.as_ref().map(|_| REDACTED),
+            )
+            .finish()
+    }
+}
+
+impl Profile {
+    /// Expand environment variables referenced in string values
+    #[tracing::instrument(skip(self))]
+    pub fn expand(self) -> Result<Self, Box<dyn Error + Send + Sync>> {
+        Ok(Self {
+            endpoint: expand(&self.endpoint)?,
+            application_key: expand(&self.application_key)?,
+            application_secret: expand(&self.application_secret)?,
+            consumer_key: match self.consumer_key {
+                Some(consumer_key) => Some(expand(&consumer_key)?),
+                None => None,
+            },
+        })
+    }
+
+    /// Returns the '[ovh]' section with the endpoint and the credentials of
+    /// the profile
+    #[tracing::instrument(skip(ovh))]
+    pub fn ovh(&self, ovh: &Ovh) -> Ovh {
+        Ovh {
+            endpoint: self.endpoint.to_owned(),
+            application_key: self.application_key.to_owned(),
+            application_secret: self.application_secret.to_owned(),
+            consumer_key: self.consumer_key.to_owned(),
+            ..ovh.to_owned()
+        }
+    }
+}
+
+#[derive(Deserialize, Clone, Debug, Default)]
+pub struct Output {
+    /// Output format of commands when none is given on the command line, e.g.
+    /// 'wide', replaces the default one of every command
+    #[serde(rename = "default")]
+    pub default: Option<String>,
+    /// Headers of the columns kept in tables, e.g. ["Name", "Status"], the
+    /// '--fields-file' and '--columns' flags take precedence over it
+    #[serde(rename = "fields", default)]
+    pub fields: Vec<String>,
 }
 
 #[derive(Deserialize, Clone, Debug)]
 pub struct Configuration {
     #[serde(rename = "ovh")]
     pub ovh: Ovh,
+    #[serde(rename = "output", default)]
+    pub output: Output,
+    /// Credentials of other endpoints, by name
+    #[serde(rename = "profiles", default)]
+    pub profiles: BTreeMap<String, Profile>,
+    /// File from which the configuration has been loaded, the last one when
+    /// several files are merged, if any
+    #[serde(skip)]
+    pub source: Option<PathBuf>,
 }
 
-impl TryFrom<PathBuf> for Configuration {
+/// Replace `${VAR}` tokens in the value by the content of the environment
+/// variable, the value is never written in errors as it may be a secret
+#[tracing::instrument(skip(value))]
+pub fn expand(value: &str) -> Result<String, Box<dyn Error + Send + Sync>> {
+    let mut expanded = String::new();
+    let mut rest = value;
+    while let Some(start) = rest.find("${") {
+        let end = match rest[start..].find('}') {
+            Some(end) => start + end,
+            None => return Err("could not expand value, missing closing '}'".into()),
+        };
+
+        let name = &rest[start + 2..end];
+        let variable = env::var(name)
+            .map_err(|err| format!("could not expand variable '{}', {}", name, err))?;
+
+        expanded.push_str(&rest[..start]);
+        expanded.push_str(&variable);
+        rest = &rest[end + 1..];
+    }
+
+    expanded.push_str(rest);
+    Ok(expanded)
+}
+
+impl TryFrom<Vec<PathBuf>> for Configuration {
     type Error = Box<dyn Error + Send + Sync>;
 
+    /// Load the given files in order, values of a file override the ones of the
+    /// files before it
     #[tracing::instrument]
-    fn try_from(path: PathBuf) -> Result<Self, Self::Error> {
-        Config::builder()
-            .set_default("ovh.endpoint", "https://eu.api.ovh.com/1.0")?
-            .add_source(File::from(path).required(true))
+    fn try_from(paths: Vec<PathBuf>) -> Result<Self, Self::Error> {
+        let mut builder = Config::builder()
+            .set_default("ovh.endpoint", DEFAULT_ENDPOINT)?
+            .set_default("ovh.application-key", "")?
+            .set_default("ovh.application-secret", "")?
+            .set_default("ovh.pool-max-idle", 8)?
+            .set_default("ovh.pool-idle-timeout", 30)?
+            .set_default("ovh.idempotency-keys", false)?
+            .set_default("ovh.best-effort", false)?
+            .set_default("ovh.id-cache-ttl", 0)?;
+
+        for path in &paths {
+            builder = builder.add_source(File::from(path.to_owned()).required(true));
+        }
+
+        builder
             .build()
             .map_err(|err| format!("failed to load configuration, {}", err))?
-            .try_deserialize()
-            .map_err(|err| format!("failed to deserialize configuration, {}", err).into())
+            .try_deserialize::<Self>()
+            .map(|config| Self {
+                source: paths.last().cloned(),
+                ..config
+            })
+            .map_err(|err| format!("failed to deserialize configuration, {}", err))?
+            .expand()
+    }
+}
+
+/// Returns if the path segment is an api version, e.g. '1.0' or 'v2'
+#[tracing::instrument]
+pub fn is_api_version(segment: &str) -> bool {
+    let version = segment.strip_prefix('v').unwrap_or(segment);
+
+    !version.is_empty()
+        && version
+            .split('.')
+            .all(|part| !part.is_empty() && part.chars().all(|c| c.is_ascii_digit()))
+}
+
+/// Replace the trailing api version of the endpoint by the given one
+#[tracing::instrument]
+pub fn with_api_version(
+    endpoint: &str,
+    version: &str,
+) -> Result<String, Box<dyn Error + Send + Sync>> {
+    if !is_api_version(version) {
+        return Err(format!("'{}' is not an api version, e.g. '1.0' or 'v2'", version).into());
+    }
+
+    let endpoint = endpoint.trim_end_matches('/');
+    match endpoint.rsplit_once('/') {
+        Some((base, segment)) if is_api_version(segment) => Ok(format!("{}/{}", base, version)),
+        _ => Err(format!(
+            "could not replace the api version of endpoint '{}', it does not end with a version",
+            endpoint
+        )
+        .into()),
+    }
+}
+
+/// Returns the configuration file with the highest precedence among the ones
+/// looked up by default, if any
+#[tracing::instrument]
+pub fn locate() -> Option<PathBuf> {
+    let mut names = vec![PathBuf::from("config")];
+    if let Ok(home) = env::var("HOME") {
+        names.push(PathBuf::from(format!(
+            "{}/.{}",
+            home,
+            env!("CARGO_PKG_NAME")
+        )));
+    }
+
+    names.push(PathBuf::from(format!(
+        "/etc/{}/config",
+        env!("CARGO_PKG_NAME")
+    )));
+    for name in names {
+        for extension in ["", "toml", "yaml", "yml", "json"] {
+            let path = name.with_extension(extension);
+            if path.is_file() {
+                return Some(path);
+            }
+        }
+    }
+
+    None
+}
+
+/// Write the consumer key in the given toml configuration file, other lines of
+/// the file are kept as is
+#[tracing::instrument(skip(consumer_key))]
+pub fn save_consumer_key(
+    path: &Path,
+    consumer_key: &str,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    match path.extension().and_then(|extension| extension.to_str()) {
+        None | Some("toml") => {}
+        Some(extension) => {
+            return Err(format!(
+                "could not save consumer key in '{}', only toml files are supported, got '{}'",
+                path.display(),
+                extension
+            )
+            .into())
+        }
+    }
+
+    let content = fs::read_to_string(path)
+        .map_err(|err| format!("could not read file '{}', {}", path.display(), err))?;
+
+    let line = format!("consumer-key = \"{}\"", consumer_key);
+    let mut lines: Vec<String> = content.lines().map(String::from).collect();
+    if let Some(index) = lines
+        .iter()
+        .position(|l| l.trim_start().starts_with("consumer-key"))
+    {
+        lines[index] = line;
+    } else if let Some(index) = lines.iter().position(|l| l.trim() == "[ovh]") {
+        lines.insert(index + 1, line);
+    } else {
+        return Err(format!("could not find section '[ovh]' in '{}'", path.display()).into());
+    }
+
+    fs::write(path, lines.join("\n") + "\n")
+        .map_err(|err| format!("could not write file '{}', {}", path.display(), err).into())
+}
+
+/// Write a commented starter configuration in the given toml file, an existing
+/// file is only overwritten if forced
+#[tracing::instrument]
+pub fn write_template(
+    path: &Path,
+    create_app_url: &str,
+    force: bool,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    if !force && path.exists() {
+        return Err(format!(
+            "could not write configuration in '{}', the file already exists, use '--force' to overwrite it",
+            path.display()
+        )
+        .into());
+    }
+
+    let content = format!(
+        r#"# Configuration of {name}, see https://github.com/FlorentinDUBOIS/{name}
+
+[ovh]
+# Endpoint of the api, e.g. "https://ca.api.ovh.com/1.0" for ovhcloud canada
+endpoint = "{endpoint}"
+
+# Key and secret of the application, create one on {url}
+# or run '{name} connect --bootstrap' to get the url of another endpoint
+application-key = ""
+application-secret = ""
+
+# Consumer key, which is written here by '{name} connect --wait --save'
+# consumer-key = ""
+
+[output]
+# Output format of commands which write a table by default, e.g. "wide" or "json"
+# default = "short"
+"#,
+        name = env!("CARGO_PKG_NAME"),
+        endpoint = DEFAULT_ENDPOINT,
+        url = create_app_url,
+    );
+
+    fs::write(path, content)
+        .map_err(|err| format!("could not write file '{}', {}", path.display(), err).into())
+}
+
+/// Environment variable holding the path to the configuration file, the
+/// `--config` flag takes precedence over it
+pub const CONFIG_PATH: &str = "OVHCTL_CONFIG";
+
+/// Returns the path to the configuration file given in the environment, if any
+#[tracing::instrument]
+pub fn path_from_env() -> Option<PathBuf> {
+    match env::var(CONFIG_PATH) {
+        Ok(path) if !path.is_empty() => Some(PathBuf::from(path)),
+        _ => None,
     }
 }
 
+/// Environment variable holding the alias of the endpoint, e.g. 'ovh-ca', the
+/// `--endpoint-region` flag takes precedence over it and both override the
+/// endpoint of the configuration
+pub const REGION: &str = "OVHCTL_REGION";
+
+/// Returns the alias of the endpoint given in the environment, if any
+#[tracing::instrument]
+pub fn region_from_env() -> Option<String> {
+    match env::var(REGION) {
+        Ok(region) if !region.is_empty() => Some(region),
+        _ => None,
+    }
+}
+
+/// Environment variable holding the base url used in endpoint test mode
+pub const TEST_ENDPOINT: &str = "OVHCTL_TEST_ENDPOINT";
+
 impl Configuration {
+    /// Load a configuration pointing to the endpoint given in the environment
+    /// with canned credentials, which allows to run commands against a mock or
+    /// a non-production api without any configuration file
     #[tracing::instrument]
-    pub fn try_new() -> Result<Self, Box<dyn Error + Send + Sync>> {
+    pub fn try_endpoint_test() -> Result<Self, Box<dyn Error + Send + Sync>> {
+        let endpoint = env::var(TEST_ENDPOINT)
+            .map_err(|err| format!("could not read variable '{}', {}", TEST_ENDPOINT, err))?;
+
         Config::builder()
-            .set_default("ovh.endpoint", "https://eu.api.ovh.com/1.0")?
+            .set_default("ovh.application-key", "")?
+            .set_default("ovh.application-secret", "")?
+            .set_default("ovh.pool-max-idle", 8)?
+            .set_default("ovh.pool-idle-timeout", 30)?
+            .set_default("ovh.idempotency-keys", false)?
+            .set_default("ovh.best-effort", false)?
+            .set_default("ovh.id-cache-ttl", 0)?
+            .set_override("ovh.endpoint", endpoint)?
+            .set_override("ovh.application-key", "test-application-key")?
+            .set_override("ovh.application-secret", "test-application-secret")?
+            .set_override("ovh.consumer-key", "test-consumer-key")?
+            .build()
+            .map_err(|err| format!("failed to load configuration, {}", err))?
+            .try_deserialize::<Self>()
+            .map_err(|err| format!("failed to deserialize configuration, {}", err).into())
+    }
+
+    #[tracing::instrument]
+    pub fn try_new() -> Result<Self, Box<dyn Error + Send + Sync>> {
+        let mut builder = Config::builder()
+            .set_default("ovh.endpoint", DEFAULT_ENDPOINT)?
+            .set_default("ovh.application-key", "")?
+            .set_default("ovh.application-secret", "")?
+            .set_default("ovh.pool-max-idle", 8)?
+            .set_default("ovh.pool-idle-timeout", 30)?
+            .set_default("ovh.idempotency-keys", false)?
+            .set_default("ovh.best-effort", false)?
+            .set_default("ovh.id-cache-ttl", 0)?
             .add_source(
                 File::with_name(&format!("/etc/{}/config", env!("CARGO_PKG_NAME"))).required(false),
-            )
-            .add_source(
-                File::with_name(&format!(
-                    "{}/.{}",
-                    env::var("HOME")?,
-                    env!("CARGO_PKG_NAME")
-                ))
-                .required(false),
-            )
+            );
+
+        // 'HOME' is not set in some containers and continuous integration jobs,
+        // the other sources are still looked up
+        if let Ok(home) = env::var("HOME") {
+            builder = builder.add_source(
+                File::with_name(&format!("{}/.{}", home, env!("CARGO_PKG_NAME"))).required(false),
+            );
+        }
+
+        builder
             .add_source(File::with_name("config").required(false))
             .add_source(Environment::with_prefix(env!("CARGO_PKG_NAME")))
             .build()
             .map_err(|err| format!("failed to load configuration, {}", err))?
-            .try_deserialize()
-            .map_err(|err| format!("failed to deserialize configuration, {}", err).into())
+            .try_deserialize::<Self>()
+            .map(|config| Self {
+                source: locate(),
+                ..config
+            })
+            .map_err(|err| format!("failed to deserialize configuration, {}", err))?
+            .expand()
+    }
+
+    /// Expand environment variables referenced in string values
+    #[tracing::instrument(skip(self))]
+    pub fn expand(self) -> Result<Self, Box<dyn Error + Send + Sync>> {
+        Ok(Self {
+            ovh: self.ovh.expand()?,
+            output: self.output,
+            profiles: self
+                .profiles
+                .into_iter()
+                .map(|(name, profile)| Ok((name, profile.expand()?)))
+                .collect::<Result<_, Box<dyn Error + Send + Sync>>>()?,
+            source: self.source,
+        })
+    }
+
+    /// Returns the given tenant or the default one, if any
+    #[tracing::instrument(skip(self))]
+    pub fn tenant(&self, tenant: &Option<String>) -> Result<String, Box<dyn Error + Send + Sync>> {
+        tenant
+            .to_owned()
+            .or_else(|| self.ovh.default_tenant.to_owned())
+            .ok_or_else(|| {
+                "no tenant given, please provide one or set 'ovh.default-tenant' in configuration"
+                    .into()
+            })
     }
 }
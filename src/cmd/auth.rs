@@ -0,0 +1,460 @@
+//! # Authentication module
+//!
+//! This module provide controller to handle authentication handlers
+use std::convert::{Infallible, TryFrom};
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use futures::channel::oneshot;
+use futures::future;
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{Body, Request, Response, Server};
+use prettytable::{Cell, Row};
+use tracing::{debug, info};
+
+use crate::cfg::{self, Configuration, Ovh};
+use crate::cmd::fmt::{self, Formatter, Json, Kind, Short, TableJson, Wide, Yaml};
+use crate::cmd::report::Report;
+use crate::cmd::ExecContext;
+use crate::ovh::auth::{self, Credential, CredentialValidation, Rule};
+use crate::ovh::{self, Client, ClientConfiguration, UnauthenticatedRestClient};
+use crate::util::types::Result;
+
+/// Methods granted on the paths of a scope
+const METHODS: [&str; 4] = ["GET", "POST", "PUT", "DELETE"];
+
+/// Interval between two checks of the validation of a consumer key
+const VALIDATION_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Frames of the spinner shown while waiting for the validation
+const SPINNER: [char; 4] = ['|', '/', '-', '\\'];
+
+/// Page on which the user is redirected once the credential is validated
+const REDIRECTION: &str = "https://upload.wikimedia.org/wikipedia/commons/thumb/f/f3/Emojione_1F4AA.svg/768px-Emojione_1F4AA.svg.png";
+
+/// Page written by the callback server once the user has been redirected to it
+const CALLBACK_PAGE: &str = "Consumer key has been validated, you could close this page";
+
+/// Returns the access rules granting every method on the paths of the scopes,
+/// the 'all' scope grants access to the whole api
+#[tracing::instrument]
+pub fn rules(scopes: &[String]) -> Result<Vec<Rule>> {
+    let mut paths = vec![];
+    for scope in scopes {
+        match scope.as_str() {
+            "all" => paths.push(String::from("/*")),
+            "cloud" | "dedicated" | "domain" | "ip" | "me" => {
+                paths.push(format!("/{}", scope));
+                paths.push(format!("/{}/*", scope));
+            }
+            _ => {
+                return Err(format!(
+                    "'{}' is not allowed, only 'all', 'cloud', 'dedicated', 'domain', 'ip' or 'me'",
+                    scope
+                )
+                .into())
+            }
+        }
+    }
+
+    let mut rules = vec![];
+    for path in paths {
+        for method in METHODS {
+            rules.push(Rule {
+                method: method.into(),
+                path: path.to_owned(),
+            });
+        }
+    }
+
+    Ok(rules)
+}
+
+/// Request a new consumer key granted with the given access rules on the
+/// endpoint of the given section
+#[tracing::instrument]
+async fn ask_credential(
+    ovh: Ovh,
+    access_rules: Vec<Rule>,
+    redirection: &str,
+) -> Result<CredentialValidation> {
+    // the request is not signed, so that there is no consumer key yet when
+    // connecting for the first time
+    let client = Client::from(
+        ClientConfiguration::try_from(Ovh {
+            consumer_key: Some(ovh.consumer_key.to_owned().unwrap_or_default()),
+            ..ovh
+        })
+        .map_err(|err| {
+            format!(
+                "could not create ovh client configuration from the current configuration, {}",
+                err
+            )
+        })?,
+    );
+
+    client
+        .post_unauthenticated(
+            "auth/credential",
+            &Credential {
+                access_rules,
+                redirection: redirection.into(),
+            },
+        )
+        .await
+}
+
+/// Request a new consumer key granted with the given access rules, the user is
+/// redirected to the given url once it is validated
+#[tracing::instrument]
+pub async fn request_credential(
+    config: Arc<Configuration>,
+    access_rules: Vec<Rule>,
+    redirection: &str,
+) -> Result<CredentialValidation> {
+    let credentials = ask_credential(config.ovh.to_owned(), access_rules, redirection).await?;
+
+    println!(
+        "Please login on this url '{}' before going further",
+        credentials.validation_url
+    );
+
+    Ok(credentials)
+}
+
+/// Wait until the user has validated the consumer key, by polling the api with
+/// it, or until the timeout is reached
+#[tracing::instrument(skip(consumer_key))]
+pub async fn wait_validation(
+    config: Arc<Configuration>,
+    consumer_key: &str,
+    timeout: Duration,
+) -> Result<()> {
+    let client = Client::from(
+        ClientConfiguration::try_from(Ovh {
+            consumer_key: Some(consumer_key.to_string()),
+            ..config.ovh.to_owned()
+        })
+        .map_err(|err| {
+            format!(
+                "could not create ovh client configuration from the current configuration, {}",
+                err
+            )
+        })?,
+    );
+
+    let begin = Instant::now();
+    let mut frames = SPINNER.iter().cycle();
+    loop {
+        match auth::current_credential(&client).await {
+            Ok(credential) if credential.is_validated() => break,
+            Ok(credential) if credential.is_rejected() => {
+                eprintln!();
+                return Err(format!("consumer key has been {}", credential.status).into());
+            }
+            Ok(credential) => debug!("consumer key is {}", credential.status),
+            Err(err) => debug!(
+                "could not check the validation of the consumer key, {}",
+                err
+            ),
+        }
+
+        if begin.elapsed() >= timeout {
+            eprintln!();
+            return Err(format!(
+                "consumer key has not been validated after {} seconds",
+                timeout.as_secs()
+            )
+            .into());
+        }
+
+        if let Some(frame) = frames.next() {
+            eprint!("\r{} waiting for the validation of the consumer key", frame);
+        }
+
+        tokio::time::sleep(VALIDATION_INTERVAL).await;
+    }
+
+    eprintln!();
+    println!("Consumer key has been validated");
+
+    Ok(())
+}
+
+/// Write the consumer key in the file from which the configuration has been
+/// loaded
+#[tracing::instrument(skip(consumer_key))]
+fn save_consumer_key(config: &Configuration, consumer_key: &str) -> Result<()> {
+    let path = config
+        .source
+        .to_owned()
+        .ok_or("could not save the consumer key, no configuration file has been loaded")?;
+
+    cfg::save_consumer_key(&path, consumer_key)?;
+    info!("Consumer key has been saved in '{}'", path.display());
+
+    Ok(())
+}
+
+/// Server answering the redirection which follows the validation of the
+/// consumer key
+struct CallbackServer {
+    /// Url of the server, given as redirection of the credential
+    url: String,
+    /// Resolved once the server has been requested
+    callback: oneshot::Receiver<()>,
+    /// Stop the server, it is also stopped once dropped
+    shutdown: oneshot::Sender<()>,
+}
+
+impl CallbackServer {
+    /// Listen on the given address, fails if it could not be bound, e.g. when
+    /// the port is already in use
+    #[tracing::instrument]
+    fn start(address: &SocketAddr) -> Result<Self> {
+        let builder = Server::try_bind(address).map_err(|err| {
+            format!(
+                "could not listen on '{}', the port may already be in use, {}",
+                address, err
+            )
+        })?;
+
+        let (sender, callback) = oneshot::channel();
+        let sender = Arc::new(Mutex::new(Some(sender)));
+        let service = make_service_fn(move |_| {
+            let sender = sender.to_owned();
+            async move {
+                Ok::<_, Infallible>(service_fn(move |request: Request<Body>| {
+                    debug!("callback server has been requested on '{}'", request.uri());
+                    if let Some(sender) = sender.lock().ok().and_then(|mut guard| guard.take()) {
+                        let _ = sender.send(());
+                    }
+
+                    async { Ok::<_, Infallible>(Response::new(Body::from(CALLBACK_PAGE))) }
+                }))
+            }
+        });
+
+        let (shutdown, stop) = oneshot::channel::<()>();
+        let server = builder.serve(service);
+        let url = format!("http://{}/", server.local_addr());
+        tokio::spawn(server.with_graceful_shutdown(async {
+            let _ = stop.await;
+        }));
+
+        Ok(Self {
+            url,
+            callback,
+            shutdown,
+        })
+    }
+
+    /// Wait until the server has been requested, then stop it, fails if it has
+    /// not been requested before the timeout
+    #[tracing::instrument(skip(self))]
+    async fn wait(self, timeout: Duration) -> Result<()> {
+        let result = tokio::time::timeout(timeout, self.callback).await;
+        let _ = self.shutdown.send(());
+
+        match result {
+            Ok(Ok(())) => Ok(()),
+            Ok(Err(_)) => Err("callback server stopped before being requested".into()),
+            Err(_) => Err(format!(
+                "callback server has not been requested after {} seconds, please check that the consumer key has been validated",
+                timeout.as_secs()
+            )
+            .into()),
+        }
+    }
+}
+
+#[tracing::instrument]
+pub async fn connect(
+    config: Arc<Configuration>,
+    wait: bool,
+    timeout: u64,
+    save: bool,
+    listen: &Option<SocketAddr>,
+) -> Result<()> {
+    let begin = Instant::now();
+    let timeout = Duration::from_secs(timeout);
+    // the server listens before requesting the credential, so that a port in use
+    // does not leave a pending consumer key
+    let server = match listen {
+        Some(address) => Some(CallbackServer::start(address)?),
+        None => None,
+    };
+
+    let redirection = match &server {
+        Some(server) => server.url.to_owned(),
+        None => REDIRECTION.to_string(),
+    };
+
+    let rules = rules(&[String::from("all")])?;
+    let credentials = request_credential(config.to_owned(), rules, &redirection).await?;
+
+    if let Some(server) = server {
+        server.wait(timeout).await?;
+        info!("User has been redirected to '{}'", redirection);
+    }
+
+    if wait || listen.is_some() {
+        wait_validation(
+            config.to_owned(),
+            &credentials.consumer_key,
+            timeout.saturating_sub(begin.elapsed()),
+        )
+        .await?;
+    }
+
+    if !save {
+        println!(
+            "Then, please add the following credentials '{}' as consumer key in configuration",
+            credentials.consumer_key
+        );
+
+        return Ok(());
+    }
+
+    save_consumer_key(&config, &credentials.consumer_key)
+}
+
+/// Request a consumer key on the endpoint of the '[ovh]' section and on the
+/// one of each profile at once, then print the urls on which to validate them
+#[tracing::instrument]
+pub async fn connect_all(ctx: &ExecContext) -> Result<()> {
+    if ctx.config.profiles.is_empty() {
+        return Err(
+            "no profile configured, please add '[profiles.<name>]' sections in configuration"
+                .into(),
+        );
+    }
+
+    let mut profiles = vec![(String::from("default"), ctx.config.ovh.to_owned())];
+    for (name, profile) in &ctx.config.profiles {
+        profiles.push((name.to_owned(), profile.ovh(&ctx.config.ovh)));
+    }
+
+    let access_rules = rules(&[String::from("all")])?;
+    let results = future::join_all(
+        profiles
+            .iter()
+            .map(|(_, ovh)| ask_credential(ovh.to_owned(), access_rules.to_owned(), REDIRECTION)),
+    )
+    .await;
+
+    let mut rows = vec![Row::new(vec![
+        Cell::new("Profile"),
+        Cell::new("Endpoint"),
+        Cell::new("Validation url"),
+        Cell::new("Consumer key"),
+    ])];
+
+    let mut report = Report::new("connect", "profile", ctx.only_errors);
+    for ((name, ovh), result) in profiles.iter().zip(results) {
+        match result {
+            Ok(credentials) => {
+                report.success();
+                rows.push(Row::new(vec![
+                    Cell::new(name),
+                    Cell::new(&ovh.endpoint),
+                    Cell::new(&credentials.validation_url),
+                    Cell::new(&credentials.consumer_key),
+                ]));
+            }
+            Err(err) => {
+                report.failure(name, err);
+                rows.push(Row::new(vec![
+                    Cell::new(name),
+                    Cell::new(&ovh.endpoint),
+                    Cell::new("<error>"),
+                    Cell::new("<error>"),
+                ]));
+            }
+        }
+    }
+
+    println!("{}", fmt::table(rows, &ctx.render));
+    println!(
+        "Then, please login on each url and add the consumer key in the matching section of the configuration"
+    );
+
+    report.finish()
+}
+
+/// Print the page on which an application is created for the configured
+/// endpoint, which gives the application key and secret
+#[tracing::instrument]
+pub async fn bootstrap(config: Arc<Configuration>) -> Result<()> {
+    let url = ovh::create_app_url(&config.ovh.endpoint)?;
+
+    println!(
+        "Please create an application on this url '{}' for endpoint '{}'",
+        url, config.ovh.endpoint
+    );
+    println!(
+        "Then, please fill 'application-key' and 'application-secret' of the '[ovh]' section in configuration with the given values, and run '{} connect'",
+        env!("CARGO_PKG_NAME")
+    );
+
+    Ok(())
+}
+
+#[tracing::instrument]
+pub async fn refresh(
+    config: Arc<Configuration>,
+    scopes: &[String],
+    save: bool,
+    timeout: u64,
+) -> Result<()> {
+    let credentials = request_credential(config.to_owned(), rules(scopes)?, REDIRECTION).await?;
+    if !save {
+        println!(
+            "Then, please replace the consumer key in configuration by the following credentials '{}'",
+            credentials.consumer_key
+        );
+
+        return Ok(());
+    }
+
+    // the consumer key in configuration is replaced, so that it has to be
+    // validated first, otherwise the working one would be lost
+    wait_validation(
+        config.to_owned(),
+        &credentials.consumer_key,
+        Duration::from_secs(timeout),
+    )
+    .await?;
+
+    save_consumer_key(&config, &credentials.consumer_key)
+}
+
+/// Print the status, the expiration and the access rules of the credential of
+/// the configured consumer key
+#[tracing::instrument]
+pub async fn current(ctx: &ExecContext, output: &Kind) -> Result<()> {
+    let client = Client::from(
+        ClientConfiguration::try_from(ctx.config.to_owned()).map_err(|err| {
+            format!(
+                "could not create ovh client configuration from the current configuration, {}",
+                err
+            )
+        })?,
+    );
+
+    let credential = auth::current_credential(&client).await?;
+    let formatter = Formatter::new(credential.to_owned(), &ctx.render);
+    let o = match output {
+        Kind::Short => credential.short(&ctx.render)?,
+        Kind::Wide => credential.wide(&ctx.render)?,
+        Kind::Json => formatter.json()?,
+        Kind::Yaml => formatter.yaml()?,
+        Kind::TableJson => formatter.table_json()?,
+        Kind::Prometheus | Kind::Ndjson => return Err(fmt::unsupported(output)),
+    };
+
+    println!("{}", o);
+
+    Ok(())
+}
@@ -1,31 +1,252 @@
 //! # Cloud module
 //!
 //! This module provide controller to handle cloud handlers
+use std::cmp::Ordering;
 use std::convert::TryFrom;
-use std::sync::Arc;
+use std::error::Error;
+use std::net::{IpAddr, Ipv4Addr};
+use std::time::Duration;
 
-use crate::cfg::Configuration;
-use crate::cmd::fmt::{Formatter, Json, Kind, Short, Wide, Yaml};
-use crate::ovh::cloud;
-use crate::ovh::{Client, ClientConfiguration};
+use glob::Pattern;
+use prettytable::{Cell, Row};
+use serde::Serialize;
+use tracing::{debug, warn};
+
+use crate::cmd::fmt::{
+    self, Formatter, Json, Kind, Ndjson, Prometheus, Short, TableJson, Wide, Yaml,
+};
+use crate::cmd::report;
+use crate::cmd::ExecContext;
+use crate::ovh::auth::Me;
+use crate::ovh::cloud::{self, Instance, InstanceCreation, Period};
+use crate::ovh::task::{self, Task};
+use crate::ovh::{Client, ClientConfiguration, RestClient};
+use crate::util::progress::Spinner;
+use crate::util::time;
 use crate::util::types::Result;
 
+/// Interval between two retrievals of a watched instance
+const WATCH_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Options of the listing of instances
+#[derive(clap::Args, Clone, Debug, Default)]
+pub struct InstanceListing {
+    /// Print a row per public address of the instances, i.e. the addresses
+    /// which 'domain record sync' points records to
+    #[clap(long = "ips")]
+    pub ips: bool,
+
+    /// Sort instances by their first public ipv4 address, numerically, those
+    /// without one are written last
+    #[clap(long = "sort-by-ip")]
+    pub sort_by_ip: bool,
+}
+
+/// Returns the first public ipv4 address of the instance, if any
+#[tracing::instrument(skip(instance))]
+fn public_ipv4(instance: &Instance) -> Option<Ipv4Addr> {
+    instance
+        .ip_addresses
+        .iter()
+        .filter(|address| "public" == address.kind)
+        .find_map(|address| match address.ip {
+            IpAddr::V4(ip) => Some(ip),
+            IpAddr::V6(_) => None,
+        })
+}
+
+/// Compare instances by their first public ipv4 address, e.g. '192.0.2.9' comes
+/// before '192.0.2.10', instances without one come last
+#[tracing::instrument(skip(a, b))]
+fn by_public_ipv4(a: &Instance, b: &Instance) -> Ordering {
+    match (public_ipv4(a), public_ipv4(b)) {
+        (Some(a), Some(b)) => a.cmp(&b),
+        (Some(_), None) => Ordering::Less,
+        (None, Some(_)) => Ordering::Greater,
+        (None, None) => Ordering::Equal,
+    }
+}
+
+/// Public address of an instance, as used by `domain record sync`
+#[derive(Serialize, Clone, Debug)]
+pub struct InstanceAddress {
+    #[serde(rename = "id")]
+    pub id: String,
+    #[serde(rename = "name")]
+    pub name: String,
+    #[serde(rename = "region")]
+    pub region: String,
+    #[serde(rename = "ip")]
+    pub ip: IpAddr,
+    #[serde(rename = "version")]
+    pub version: i32,
+}
+
+impl InstanceAddress {
+    /// Returns a row per public address of the instances
+    #[tracing::instrument(skip(instances))]
+    pub fn flatten(instances: &[Instance]) -> Vec<Self> {
+        instances
+            .iter()
+            .flat_map(|instance| {
+                instance
+                    .ip_addresses
+                    .iter()
+                    .filter(|address| "public" == address.kind)
+                    .map(move |address| Self {
+                        id: instance.id.to_owned(),
+                        name: instance.name.to_owned(),
+                        region: instance.region.to_owned(),
+                        ip: address.ip,
+                        version: address.version,
+                    })
+            })
+            .collect()
+    }
+}
+
+impl Short for Vec<InstanceAddress> {
+    type Error = Box<dyn Error + Send + Sync>;
+
+    #[tracing::instrument]
+    fn short(&self, options: &fmt::Options) -> std::result::Result<String, Self::Error> {
+        let mut rows = vec![Row::new(vec![Cell::new("Name"), Cell::new("Address")])];
+
+        for address in self {
+            rows.push(Row::new(vec![
+                Cell::new(&address.name),
+                Cell::new(&address.ip.to_string()),
+            ]));
+        }
+
+        Ok(fmt::table(rows, options))
+    }
+}
+
+impl Wide for Vec<InstanceAddress> {
+    type Error = Box<dyn Error + Send + Sync>;
+
+    #[tracing::instrument]
+    fn wide(&self, options: &fmt::Options) -> std::result::Result<String, Self::Error> {
+        let mut rows = vec![Row::new(vec![
+            Cell::new("Identifier"),
+            Cell::new("Name"),
+            Cell::new("Region"),
+            Cell::new("Version"),
+            Cell::new("Address"),
+        ])];
+
+        for address in self {
+            rows.push(Row::new(vec![
+                Cell::new(&address.id),
+                Cell::new(&address.name),
+                Cell::new(&address.region),
+                Cell::new(&format!("ipv{}", address.version)),
+                Cell::new(&address.ip.to_string()),
+            ]));
+        }
+
+        Ok(fmt::table(rows, options))
+    }
+}
+
 #[tracing::instrument]
-pub async fn list_tenants(config: Arc<Configuration>, output: &Kind) -> Result<()> {
-    let client = Client::from(ClientConfiguration::try_from(config).map_err(|err| {
-        format!(
-            "could not create ovh client configuration from the current configuration, {}",
-            err
-        )
-    })?);
+pub async fn list_tenants(ctx: &ExecContext, output: &Kind) -> Result<()> {
+    let client = Client::from(
+        ClientConfiguration::try_from(ctx.config.to_owned()).map_err(|err| {
+            format!(
+                "could not create ovh client configuration from the current configuration, {}",
+                err
+            )
+        })?,
+    );
 
     let tenants = cloud::list_tenants(&client).await?;
-    let formatter = Formatter::from(tenants.to_owned());
+    fmt::check_empty(tenants.len(), "tenant", &ctx.render)?;
+    let tenants = fmt::paginate(tenants, &ctx.render);
+    let formatter = Formatter::new(tenants.to_owned(), &ctx.render);
+    let o = match output {
+        Kind::Short => tenants.short(&ctx.render)?,
+        Kind::Wide => tenants.wide(&ctx.render)?,
+        Kind::Json => formatter.json()?,
+        Kind::Yaml => formatter.yaml()?,
+        Kind::TableJson => formatter.table_json()?,
+        Kind::Ndjson => formatter.ndjson()?,
+        Kind::Prometheus => return Err(fmt::unsupported(output)),
+    };
+
+    println!("{}", o);
+
+    Ok(())
+}
+
+#[tracing::instrument]
+pub async fn list_instances(
+    ctx: &ExecContext,
+    tenant: &str,
+    output: &Kind,
+    range: &time::Range,
+    status: &Option<String>,
+    names: &[Pattern],
+    listing: &InstanceListing,
+) -> Result<()> {
+    let client = Client::from(
+        ClientConfiguration::try_from(ctx.config.to_owned()).map_err(|err| {
+            format!(
+                "could not create ovh client configuration from the current configuration, {}",
+                err
+            )
+        })?,
+    );
+
+    let mut instances: Vec<_> = cloud::list_instances(&client, tenant)
+        .await?
+        .into_iter()
+        .filter(|instance| range.contains(instance.created.as_deref()))
+        .filter(|instance| match status {
+            Some(status) => instance.status.eq_ignore_ascii_case(status),
+            None => true,
+        })
+        .filter(|instance| {
+            names.is_empty() || names.iter().any(|name| name.matches(&instance.name))
+        })
+        .collect();
+
+    if listing.sort_by_ip {
+        instances.sort_by(by_public_ipv4);
+    }
+
+    if listing.ips {
+        let addresses = InstanceAddress::flatten(&instances);
+        fmt::check_empty(addresses.len(), "public address", &ctx.render)?;
+        let addresses = fmt::paginate(addresses, &ctx.render);
+        let formatter = Formatter::new(addresses.to_owned(), &ctx.render);
+        let o = match output {
+            Kind::Short => addresses.short(&ctx.render)?,
+            Kind::Wide => addresses.wide(&ctx.render)?,
+            Kind::Json => formatter.json()?,
+            Kind::Yaml => formatter.yaml()?,
+            Kind::TableJson => formatter.table_json()?,
+            Kind::Ndjson => formatter.ndjson()?,
+            Kind::Prometheus => return Err(fmt::unsupported(output)),
+        };
+
+        println!("{}", o);
+
+        return Ok(());
+    }
+
+    fmt::check_empty(instances.len(), "instance", &ctx.render)?;
+    let instances = fmt::paginate(instances, &ctx.render);
+    let formatter = Formatter::new(instances.to_owned(), &ctx.render);
     let o = match output {
-        Kind::Short => tenants.short()?,
-        Kind::Wide => tenants.wide()?,
+        Kind::Short => instances.short(&ctx.render)?,
+        Kind::Wide => instances.wide(&ctx.render)?,
         Kind::Json => formatter.json()?,
         Kind::Yaml => formatter.yaml()?,
+        Kind::TableJson => formatter.table_json()?,
+        Kind::Ndjson => formatter.ndjson()?,
+        Kind::Prometheus => instances.prometheus()?,
     };
 
     println!("{}", o);
@@ -34,21 +255,364 @@ pub async fn list_tenants(config: Arc<Configuration>, output: &Kind) -> Result<(
 }
 
 #[tracing::instrument]
-pub async fn list_instances(config: Arc<Configuration>, tenant: &str, output: &Kind) -> Result<()> {
-    let client = Client::from(ClientConfiguration::try_from(config).map_err(|err| {
+pub async fn rename_instance(
+    ctx: &ExecContext,
+    tenant: &str,
+    id: &str,
+    name: &str,
+    output: &Kind,
+) -> Result<()> {
+    let client = Client::from(
+        ClientConfiguration::try_from(ctx.config.to_owned()).map_err(|err| {
+            format!(
+                "could not create ovh client configuration from the current configuration, {}",
+                err
+            )
+        })?,
+    );
+
+    cloud::rename_instance(&client, tenant, id, name).await?;
+
+    let instances = vec![cloud::get_instance(&client, tenant, id).await?];
+    let formatter = Formatter::new(instances.to_owned(), &ctx.render);
+    let o = match output {
+        Kind::Short => instances.short(&ctx.render)?,
+        Kind::Wide => instances.wide(&ctx.render)?,
+        Kind::Json => formatter.json()?,
+        Kind::Yaml => formatter.yaml()?,
+        Kind::TableJson => formatter.table_json()?,
+        Kind::Ndjson => formatter.ndjson()?,
+        Kind::Prometheus => return Err(fmt::unsupported(output)),
+    };
+
+    println!("{}", o);
+
+    Ok(())
+}
+
+/// Retrieve the instance until it reaches the given status, fails if it is in
+/// error or if it did not reach the status before the timeout
+#[tracing::instrument(skip(client, spinner))]
+async fn wait_instance(
+    client: &Client,
+    tenant: &str,
+    id: &str,
+    until: &str,
+    timeout: u64,
+    spinner: &mut Spinner,
+) -> Result<Instance> {
+    let instance: Instance = task::poll_task(
+        client,
+        &cloud::instance_path(tenant, id),
+        |instance: &Instance| instance.status.eq_ignore_ascii_case(until),
+        Some(Duration::from_secs(timeout)),
+        WATCH_INTERVAL,
+        spinner,
+    )
+    .await
+    .map_err(|err| {
         format!(
-            "could not create ovh client configuration from the current configuration, {}",
-            err
+            "instance '{}' did not reach status '{}', {}",
+            id, until, err
         )
-    })?);
+    })?;
+
+    if !instance.status.eq_ignore_ascii_case(until) && instance.is_finished() {
+        return Err(format!(
+            "instance '{}' is in error while waiting for status '{}'",
+            id, until
+        )
+        .into());
+    }
+
+    Ok(instance)
+}
+
+#[tracing::instrument]
+pub async fn watch_instance(
+    ctx: &ExecContext,
+    tenant: &str,
+    id: &str,
+    until: &str,
+    timeout: u64,
+    output: &Kind,
+) -> Result<()> {
+    let client = Client::from(
+        ClientConfiguration::try_from(ctx.config.to_owned()).map_err(|err| {
+            format!(
+                "could not create ovh client configuration from the current configuration, {}",
+                err
+            )
+        })?,
+    );
+
+    let instance =
+        wait_instance(&client, tenant, id, until, timeout, &mut Spinner::hidden()).await?;
+
+    let instances = vec![instance];
+    let formatter = Formatter::new(instances.to_owned(), &ctx.render);
+    let o = match output {
+        Kind::Short => instances.short(&ctx.render)?,
+        Kind::Wide => instances.wide(&ctx.render)?,
+        Kind::Json => formatter.json()?,
+        Kind::Yaml => formatter.yaml()?,
+        Kind::TableJson => formatter.table_json()?,
+        Kind::Ndjson => formatter.ndjson()?,
+        Kind::Prometheus => return Err(fmt::unsupported(output)),
+    };
+
+    println!("{}", o);
+
+    Ok(())
+}
+
+/// Fail if no instance could be created anymore in the region, the check is
+/// left to the api when it gives no quota for the region
+#[tracing::instrument(skip(client))]
+async fn check_quota(client: &Client, tenant: &str, region: &str) -> Result<()> {
+    let quota = cloud::list_quotas(client, tenant)
+        .await?
+        .into_iter()
+        .find(|quota| quota.region.eq_ignore_ascii_case(region))
+        .and_then(|quota| quota.instance);
+
+    match quota {
+        Some(quota) if quota.is_reached() => Err(format!(
+            "region '{}' is at instance quota ({}/{}), please delete an instance or request a quota increase",
+            region, quota.used_instances, quota.max_instances
+        )
+        .into()),
+        Some(_) => Ok(()),
+        None => {
+            warn!(
+                "could not find instance quota of region '{}' on tenant '{}', skip the check",
+                region, tenant
+            );
+            Ok(())
+        }
+    }
+}
+
+#[tracing::instrument]
+pub async fn create_instance(
+    ctx: &ExecContext,
+    tenant: &str,
+    opts: &InstanceCreation,
+    quota: bool,
+    wait: bool,
+    timeout: u64,
+    output: &Kind,
+) -> Result<()> {
+    let client = Client::from(
+        ClientConfiguration::try_from(ctx.config.to_owned()).map_err(|err| {
+            format!(
+                "could not create ovh client configuration from the current configuration, {}",
+                err
+            )
+        })?,
+    );
+
+    if quota {
+        check_quota(&client, tenant, &opts.region).await?;
+    }
+
+    let mut instance = cloud::create_instance(&client, tenant, opts).await?;
+    if wait {
+        instance = wait_instance(
+            &client,
+            tenant,
+            &instance.id,
+            "active",
+            timeout,
+            &mut report::spinner(ctx.only_errors),
+        )
+        .await?;
+    }
+
+    let instances = vec![instance];
+    let formatter = Formatter::new(instances.to_owned(), &ctx.render);
+    let o = match output {
+        Kind::Short => instances.short(&ctx.render)?,
+        Kind::Wide => instances.wide(&ctx.render)?,
+        Kind::Json => formatter.json()?,
+        Kind::Yaml => formatter.yaml()?,
+        Kind::TableJson => formatter.table_json()?,
+        Kind::Ndjson => formatter.ndjson()?,
+        Kind::Prometheus => return Err(fmt::unsupported(output)),
+    };
+
+    println!("{}", o);
+
+    Ok(())
+}
+
+#[tracing::instrument]
+pub async fn list_images(
+    ctx: &ExecContext,
+    tenant: &str,
+    output: &Kind,
+    os: &Option<String>,
+    arch: &Option<String>,
+    name: &Option<String>,
+    range: &time::Range,
+) -> Result<()> {
+    let client = Client::from(
+        ClientConfiguration::try_from(ctx.config.to_owned()).map_err(|err| {
+            format!(
+                "could not create ovh client configuration from the current configuration, {}",
+                err
+            )
+        })?,
+    );
+
+    let images: Vec<_> = cloud::list_images(&client, tenant)
+        .await?
+        .into_iter()
+        .filter(|image| match os {
+            Some(os) => image.os_type.eq_ignore_ascii_case(os),
+            None => true,
+        })
+        .filter(|image| match arch {
+            // the architecture is only exposed in the name of the image
+            Some(arch) => image.name.to_lowercase().contains(&arch.to_lowercase()),
+            None => true,
+        })
+        .filter(|image| match name {
+            Some(name) => image.name.to_lowercase().contains(&name.to_lowercase()),
+            None => true,
+        })
+        .filter(|image| range.contains(Some(&image.creation_date)))
+        .collect();
+
+    fmt::check_empty(images.len(), "image", &ctx.render)?;
+    let images = fmt::paginate(images, &ctx.render);
+    let formatter = Formatter::new(images.to_owned(), &ctx.render);
+    let o = match output {
+        Kind::Short => images.short(&ctx.render)?,
+        Kind::Wide => images.wide(&ctx.render)?,
+        Kind::Json => formatter.json()?,
+        Kind::Yaml => formatter.yaml()?,
+        Kind::TableJson => formatter.table_json()?,
+        Kind::Ndjson => formatter.ndjson()?,
+        Kind::Prometheus => return Err(fmt::unsupported(output)),
+    };
+
+    println!("{}", o);
+
+    Ok(())
+}
+
+#[tracing::instrument]
+pub async fn list_flavors(
+    ctx: &ExecContext,
+    tenant: &str,
+    output: &Kind,
+    min_ram: &Option<i64>,
+    min_vcpus: &Option<i64>,
+) -> Result<()> {
+    let client = Client::from(
+        ClientConfiguration::try_from(ctx.config.to_owned()).map_err(|err| {
+            format!(
+                "could not create ovh client configuration from the current configuration, {}",
+                err
+            )
+        })?,
+    );
+
+    let flavors: Vec<_> = cloud::list_flavors(&client, tenant)
+        .await?
+        .into_iter()
+        .filter(|flavor| match min_ram {
+            Some(min_ram) => flavor.ram >= *min_ram,
+            None => true,
+        })
+        .filter(|flavor| match min_vcpus {
+            Some(min_vcpus) => flavor.vcpus >= *min_vcpus,
+            None => true,
+        })
+        .collect();
+
+    fmt::check_empty(flavors.len(), "flavor", &ctx.render)?;
+    let flavors = fmt::paginate(flavors, &ctx.render);
+    let formatter = Formatter::new(flavors.to_owned(), &ctx.render);
+    let o = match output {
+        Kind::Short => flavors.short(&ctx.render)?,
+        Kind::Wide => flavors.wide(&ctx.render)?,
+        Kind::Json => formatter.json()?,
+        Kind::Yaml => formatter.yaml()?,
+        Kind::TableJson => formatter.table_json()?,
+        Kind::Ndjson => formatter.ndjson()?,
+        Kind::Prometheus => return Err(fmt::unsupported(output)),
+    };
+
+    println!("{}", o);
+
+    Ok(())
+}
+
+#[tracing::instrument]
+pub async fn get_usage(
+    ctx: &ExecContext,
+    tenant: &str,
+    period: &Period,
+    output: &Kind,
+) -> Result<()> {
+    let client = Client::from(
+        ClientConfiguration::try_from(ctx.config.to_owned()).map_err(|err| {
+            format!(
+                "could not create ovh client configuration from the current configuration, {}",
+                err
+            )
+        })?,
+    );
+
+    let mut usage = cloud::get_usage(&client, tenant, period).await?;
+    if ctx.render.human && matches!(output, Kind::Short | Kind::Wide) {
+        // the currency is a property of the account, not of the usage
+        match client.get::<Me>("me").await {
+            Ok(me) => usage.currency = me.currency.map(|currency| currency.code),
+            Err(err) => debug!("could not retrieve the currency of the account, {}", err),
+        }
+    }
+
+    let formatter = Formatter::new(usage.to_owned(), &ctx.render);
+    let o = match output {
+        Kind::Short => usage.short(&ctx.render)?,
+        Kind::Wide => usage.wide(&ctx.render)?,
+        Kind::Json => formatter.json()?,
+        Kind::Yaml => formatter.yaml()?,
+        Kind::TableJson => formatter.table_json()?,
+        Kind::Prometheus | Kind::Ndjson => return Err(fmt::unsupported(output)),
+    };
+
+    println!("{}", o);
+
+    Ok(())
+}
+
+#[tracing::instrument]
+pub async fn list_regions(ctx: &ExecContext, tenant: &str, output: &Kind) -> Result<()> {
+    let client = Client::from(
+        ClientConfiguration::try_from(ctx.config.to_owned()).map_err(|err| {
+            format!(
+                "could not create ovh client configuration from the current configuration, {}",
+                err
+            )
+        })?,
+    );
 
-    let instances = cloud::list_instances(&client, tenant).await?;
-    let formatter = Formatter::from(instances.to_owned());
+    let regions = cloud::list_regions(&client, tenant).await?;
+    fmt::check_empty(regions.len(), "region", &ctx.render)?;
+    let regions = fmt::paginate(regions, &ctx.render);
+    let formatter = Formatter::new(regions.to_owned(), &ctx.render);
     let o = match output {
-        Kind::Short => instances.short()?,
-        Kind::Wide => instances.wide()?,
+        Kind::Short => regions.short(&ctx.render)?,
+        Kind::Wide => regions.wide(&ctx.render)?,
         Kind::Json => formatter.json()?,
         Kind::Yaml => formatter.yaml()?,
+        Kind::TableJson => formatter.table_json()?,
+        Kind::Ndjson => formatter.ndjson()?,
+        Kind::Prometheus => return Err(fmt::unsupported(output)),
     };
 
     println!("{}", o);
@@ -1,34 +1,245 @@
 //! # Server module
 //!
 //! This module provide controller to handle server handlers
+use std::cmp::Reverse;
 use std::convert::TryFrom;
-use std::sync::Arc;
+use std::time::Duration;
 
-use crate::cfg::Configuration;
-use crate::cmd::fmt::{Formatter, Json, Kind, Short, Wide, Yaml};
-use crate::ovh::dedicated::server;
+use crate::cmd::fmt::{
+    self, Formatter, Json, Kind, Ndjson, Prometheus, Short, TableJson, Wide, Yaml,
+};
+use crate::cmd::report;
+use crate::cmd::ExecContext;
+use crate::ovh::dedicated::server::{self, Installation, InstallationDetails};
+use crate::ovh::task::{self, Task};
 use crate::ovh::{Client, ClientConfiguration};
-use crate::util::types;
+use crate::util::{prompt, types};
+
+/// Interval between two retrievals of a task
+const TASK_POLL_INTERVAL: Duration = Duration::from_secs(10);
 
 #[tracing::instrument]
-pub async fn list_servers(config: Arc<Configuration>, output: &Kind) -> types::Result<()> {
-    let client = Client::from(ClientConfiguration::try_from(config).map_err(|err| {
-        format!(
-            "could not create ovh client configuration from the current configuration, {}",
-            err
-        )
-    })?);
+pub async fn list_servers(ctx: &ExecContext, output: &Kind) -> types::Result<()> {
+    let client = Client::from(
+        ClientConfiguration::try_from(ctx.config.to_owned()).map_err(|err| {
+            format!(
+                "could not create ovh client configuration from the current configuration, {}",
+                err
+            )
+        })?,
+    );
 
     let servers = server::list_servers(&client).await?;
-    let formatter = Formatter::from(servers.to_owned());
+    fmt::check_empty(servers.len(), "server", &ctx.render)?;
+    let servers = fmt::paginate(servers, &ctx.render);
+    let formatter = Formatter::new(servers.to_owned(), &ctx.render);
+    let o = match output {
+        Kind::Short => servers.short(&ctx.render)?,
+        Kind::Wide => servers.wide(&ctx.render)?,
+        Kind::Json => formatter.json()?,
+        Kind::Yaml => formatter.yaml()?,
+        Kind::TableJson => formatter.table_json()?,
+        Kind::Ndjson => formatter.ndjson()?,
+        Kind::Prometheus => servers.prometheus()?,
+    };
+
+    println!("{}", o);
+
+    Ok(())
+}
+
+#[tracing::instrument]
+pub async fn set_monitoring(
+    ctx: &ExecContext,
+    name: &str,
+    enabled: bool,
+    output: &Kind,
+) -> types::Result<()> {
+    let client = Client::from(
+        ClientConfiguration::try_from(ctx.config.to_owned()).map_err(|err| {
+            format!(
+                "could not create ovh client configuration from the current configuration, {}",
+                err
+            )
+        })?,
+    );
+
+    server::set_monitoring(&client, name, enabled).await?;
+
+    let servers = vec![server::get_server(&client, name).await?];
+    let formatter = Formatter::new(servers.to_owned(), &ctx.render);
+    let o = match output {
+        Kind::Short => servers.short(&ctx.render)?,
+        Kind::Wide => servers.wide(&ctx.render)?,
+        Kind::Json => formatter.json()?,
+        Kind::Yaml => formatter.yaml()?,
+        Kind::TableJson => formatter.table_json()?,
+        Kind::Ndjson => formatter.ndjson()?,
+        Kind::Prometheus => servers.prometheus()?,
+    };
+
+    println!("{}", o);
+
+    Ok(())
+}
+
+#[tracing::instrument]
+pub async fn list_templates(ctx: &ExecContext, output: &Kind) -> types::Result<()> {
+    let client = Client::from(
+        ClientConfiguration::try_from(ctx.config.to_owned()).map_err(|err| {
+            format!(
+                "could not create ovh client configuration from the current configuration, {}",
+                err
+            )
+        })?,
+    );
+
+    let templates = server::list_templates(&client).await?;
+    fmt::check_empty(templates.len(), "template", &ctx.render)?;
+    let templates = fmt::paginate(templates, &ctx.render);
+    let formatter = Formatter::new(templates.to_owned(), &ctx.render);
     let o = match output {
-        Kind::Short => servers.short()?,
-        Kind::Wide => servers.wide()?,
+        Kind::Short => templates.short(&ctx.render)?,
+        Kind::Wide => templates.wide(&ctx.render)?,
         Kind::Json => formatter.json()?,
         Kind::Yaml => formatter.yaml()?,
+        Kind::TableJson => formatter.table_json()?,
+        Kind::Ndjson => formatter.ndjson()?,
+        Kind::Prometheus => return Err(fmt::unsupported(output)),
     };
 
     println!("{}", o);
 
     Ok(())
 }
+
+#[tracing::instrument]
+pub async fn list_tasks(ctx: &ExecContext, name: &str, output: &Kind) -> types::Result<()> {
+    let client = Client::from(
+        ClientConfiguration::try_from(ctx.config.to_owned()).map_err(|err| {
+            format!(
+                "could not create ovh client configuration from the current configuration, {}",
+                err
+            )
+        })?,
+    );
+
+    let mut tasks = server::list_tasks(&client, name).await?;
+    tasks.sort_by_key(|task| Reverse(task.task_id));
+    fmt::check_empty(tasks.len(), "task", &ctx.render)?;
+    let tasks = fmt::paginate(tasks, &ctx.render);
+    let formatter = Formatter::new(tasks.to_owned(), &ctx.render);
+    let o = match output {
+        Kind::Short => tasks.short(&ctx.render)?,
+        Kind::Wide => tasks.wide(&ctx.render)?,
+        Kind::Json => formatter.json()?,
+        Kind::Yaml => formatter.yaml()?,
+        Kind::TableJson => formatter.table_json()?,
+        Kind::Ndjson => formatter.ndjson()?,
+        Kind::Prometheus => return Err(fmt::unsupported(output)),
+    };
+
+    println!("{}", o);
+
+    Ok(())
+}
+
+#[tracing::instrument]
+pub async fn get_task(ctx: &ExecContext, name: &str, id: &i64, output: &Kind) -> types::Result<()> {
+    let client = Client::from(
+        ClientConfiguration::try_from(ctx.config.to_owned()).map_err(|err| {
+            format!(
+                "could not create ovh client configuration from the current configuration, {}",
+                err
+            )
+        })?,
+    );
+
+    let tasks = vec![server::get_task(&client, name, id).await?];
+    let formatter = Formatter::new(tasks.to_owned(), &ctx.render);
+    let o = match output {
+        Kind::Short => tasks.short(&ctx.render)?,
+        Kind::Wide => tasks.wide(&ctx.render)?,
+        Kind::Json => formatter.json()?,
+        Kind::Yaml => formatter.yaml()?,
+        Kind::TableJson => formatter.table_json()?,
+        Kind::Ndjson => formatter.ndjson()?,
+        Kind::Prometheus => return Err(fmt::unsupported(output)),
+    };
+
+    println!("{}", o);
+
+    Ok(())
+}
+
+#[tracing::instrument]
+pub async fn reinstall_server(
+    ctx: &ExecContext,
+    name: &str,
+    template: &str,
+    ssh_key: &Option<String>,
+    wait: bool,
+    output: &Kind,
+) -> types::Result<()> {
+    let client = Client::from(
+        ClientConfiguration::try_from(ctx.config.to_owned()).map_err(|err| {
+            format!(
+                "could not create ovh client configuration from the current configuration, {}",
+                err
+            )
+        })?,
+    );
+
+    let message = format!(
+        "Reinstalling server '{}' with template '{}' wipes its disks",
+        name, template
+    );
+
+    if !prompt::confirm_destructive(ctx.confirm_destructive, &message, name)? {
+        return Err(format!("reinstallation of server '{}' has been aborted", name).into());
+    }
+
+    let installation = Installation {
+        template_name: template.to_string(),
+        details: InstallationDetails {
+            ssh_key_name: ssh_key.to_owned(),
+        },
+    };
+
+    let mut task = server::install(&client, name, &installation).await?;
+    if wait && !task.is_finished() {
+        task = task::poll_task(
+            &client,
+            &server::task_path(name, &task.task_id),
+            server::Task::is_finished,
+            None,
+            TASK_POLL_INTERVAL,
+            &mut report::spinner(ctx.only_errors),
+        )
+        .await?;
+    }
+
+    let tasks = vec![task.to_owned()];
+    let formatter = Formatter::new(tasks.to_owned(), &ctx.render);
+    let o = match output {
+        Kind::Short => tasks.short(&ctx.render)?,
+        Kind::Wide => tasks.wide(&ctx.render)?,
+        Kind::Json => formatter.json()?,
+        Kind::Yaml => formatter.yaml()?,
+        Kind::TableJson => formatter.table_json()?,
+        Kind::Ndjson => formatter.ndjson()?,
+        Kind::Prometheus => return Err(fmt::unsupported(output)),
+    };
+
+    println!("{}", o);
+
+    if task.is_finished() && !task.is_done() {
+        return Err(format!(
+            "could not reinstall server '{}', task '{}' ended with status '{}'",
+            name, task.task_id, task.status
+        )
+        .into());
+    }
+
+    Ok(())
+}
@@ -0,0 +1,177 @@
+//! # Doctor module
+//!
+//! This module provide controller to diagnose the environment of the command
+//! line interface
+use std::convert::TryFrom;
+use std::sync::Arc;
+
+use crate::cfg::Configuration;
+use crate::ovh::auth::{self, Me};
+use crate::ovh::{ApiError, Client, ClientConfiguration, RestClient, UnauthenticatedRestClient};
+use crate::util::types::{self, Result};
+
+/// Maximum difference in seconds between the local clock and the one of the
+/// api, requests are signed with the local timestamp
+const CLOCK_SKEW_TOLERANCE: i64 = 30;
+
+/// Operations checked against the access rules of the consumer key, along with
+/// the scope which grants them
+const OPERATIONS: [(&str, &str, &str); 5] = [
+    ("GET", "/domain/zone", "domain"),
+    ("POST", "/domain/zone/example.com/record", "domain"),
+    ("GET", "/cloud/project", "cloud"),
+    ("GET", "/dedicated/server", "dedicated"),
+    ("GET", "/ip", "ip"),
+];
+
+/// Outcome of the checks, printed as they are done
+#[derive(Debug, Default)]
+struct Checklist {
+    failures: usize,
+}
+
+impl Checklist {
+    #[tracing::instrument]
+    fn pass(&mut self, message: &str) {
+        println!("[pass] {}", message);
+    }
+
+    #[tracing::instrument]
+    fn warn(&mut self, message: &str, hint: &str) {
+        println!("[warn] {}\n       {}", message, hint);
+    }
+
+    #[tracing::instrument]
+    fn fail(&mut self, message: &str, hint: &str) {
+        self.failures += 1;
+        println!("[fail] {}\n       {}", message, hint);
+    }
+}
+
+#[tracing::instrument]
+pub async fn doctor(config: Arc<Configuration>) -> Result<()> {
+    let mut checklist = Checklist::default();
+    let name = env!("CARGO_PKG_NAME");
+
+    match &config.source {
+        Some(path) => checklist.pass(&format!("configuration loaded from '{}'", path.display())),
+        None => checklist.warn(
+            "no configuration file found, only defaults and environment variables are used",
+            &format!(
+                "run '{} --config-write ~/.{}.toml' to write one",
+                name, name
+            ),
+        ),
+    }
+
+    let client = match ClientConfiguration::try_from(config.to_owned()) {
+        Ok(client_config) => {
+            checklist.pass("application key, secret and consumer key are set");
+            Client::from(client_config)
+        }
+        Err(err) => {
+            checklist.fail(
+                &format!("configuration is not usable, {}", err),
+                &format!(
+                    "run '{} connect --bootstrap' to create an application, then '{} connect --wait --save'",
+                    name, name
+                ),
+            );
+
+            return finish(checklist);
+        }
+    };
+
+    match client.get_unauthenticated::<i64>("auth/time").await {
+        Ok(time) => {
+            checklist.pass(&format!("endpoint '{}' is reachable", config.ovh.endpoint));
+
+            let skew = (chrono::offset::Utc::now().timestamp() - time).abs();
+            if skew > CLOCK_SKEW_TOLERANCE {
+                checklist.fail(
+                    &format!("local clock is {} second(s) away from the api", skew),
+                    "synchronise the clock, e.g. using ntp, as requests are signed with it",
+                );
+            } else {
+                checklist.pass(&format!(
+                    "local clock is {} second(s) away from the api",
+                    skew
+                ));
+            }
+        }
+        Err(err) => {
+            checklist.fail(
+                &format!(
+                    "endpoint '{}' is not reachable, {}",
+                    config.ovh.endpoint, err
+                ),
+                "check the 'endpoint' of the configuration and the network, e.g. a proxy",
+            );
+
+            return finish(checklist);
+        }
+    }
+
+    match client.get::<Me>("me").await {
+        Ok(me) => checklist.pass(&format!("credentials are valid for '{}'", me.nic_handle)),
+        Err(err) => {
+            let invalid = types::find::<ApiError>(err.as_ref())
+                .map_or(false, ApiError::is_invalid_credential);
+
+            let hint = match invalid {
+                true => format!(
+                    "the consumer key has expired or has been revoked, run '{} connect --wait --save'",
+                    name
+                ),
+                false => format!(
+                    "check that the consumer key has been created on endpoint '{}' and granted 'GET /me'",
+                    config.ovh.endpoint
+                ),
+            };
+
+            checklist.fail(&format!("credentials are not valid, {}", err), &hint);
+            return finish(checklist);
+        }
+    }
+
+    match auth::current_credential(&client).await {
+        Ok(credential) => {
+            for (method, path, scope) in OPERATIONS {
+                if credential
+                    .rules
+                    .iter()
+                    .any(|rule| rule.allows(method, path))
+                {
+                    checklist.pass(&format!("consumer key is granted '{} {}'", method, path));
+                } else {
+                    checklist.warn(
+                        &format!("consumer key is not granted '{} {}'", method, path),
+                        &format!(
+                            "run '{} auth refresh --scope {} --save' if it is needed",
+                            name, scope
+                        ),
+                    );
+                }
+            }
+        }
+        Err(err) => checklist.warn(
+            &format!(
+                "access rules of the consumer key could not be retrieved, {}",
+                err
+            ),
+            &format!("run '{} auth current' once 'GET /auth/*' is granted", name),
+        ),
+    }
+
+    finish(checklist)
+}
+
+/// Returns an error if any hard check failed
+#[tracing::instrument]
+fn finish(checklist: Checklist) -> Result<()> {
+    if 0 != checklist.failures {
+        return Err(format!("{} check(s) failed", checklist.failures).into());
+    }
+
+    Ok(())
+}
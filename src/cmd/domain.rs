@@ -2,37 +2,103 @@
 //!
 //! This module provide controller to handle domain handlers
 use std::convert::TryFrom;
+use std::error::Error;
+use std::fs;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use futures::{stream, StreamExt};
+use trust_dns_resolver::config::{NameServerConfigGroup, ResolverConfig, ResolverOpts};
+use trust_dns_resolver::error::{ResolveError, ResolveErrorKind};
+use trust_dns_resolver::proto::op::ResponseCode;
+use trust_dns_resolver::proto::rr::{RData, RecordType};
+use trust_dns_resolver::TokioAsyncResolver;
 
 use ipnetwork::IpNetwork;
-use pbr::ProgressBar;
-use tracing::info;
+use prettytable::{Cell, Row};
+use serde::Serialize;
+use tracing::{info, warn};
 
 use crate::cfg::Configuration;
-use crate::cmd::fmt::{Formatter, Json, Kind, Short, Wide, Yaml};
-use crate::ovh::cloud::{list_instances, list_tenants};
-use crate::ovh::domain::Record;
-use crate::ovh::{domain, RestClient};
+use crate::cmd::fmt::{self, Formatter, Json, Kind, Ndjson, Short, TableJson, Wide, Yaml};
+use crate::cmd::report::{self, Report};
+use crate::cmd::ExecContext;
+use crate::ovh::cloud::{list_instances, list_tenants, Instance};
+use crate::ovh::domain;
+use crate::ovh::domain::{ExportedRecord, Record};
 use crate::ovh::{Client, ClientConfiguration};
 use crate::util::net;
-use crate::util::types::Result;
+use crate::util::progress::Progress;
+use crate::util::prompt;
+use crate::util::signal::CancellationToken;
+use crate::util::types::{Context, Result};
+
+/// Number of zones exported, searched or inspected at the same time
+const EXPORT_CONCURRENCY: usize = 4;
+/// Number of tenants whose instances are listed at the same time
+const LIST_CONCURRENCY: usize = 4;
+
+/// Interval between two queries to the public resolver
+const PROPAGATION_INTERVAL: Duration = Duration::from_secs(10);
+/// Time to live assumed for records which have none, which is the default of zones
+const PROPAGATION_DEFAULT_TTL: u64 = 3600;
+/// Time given to the name servers to load a refreshed zone, on top of the ttl
+const PROPAGATION_MARGIN: u64 = 60;
+
+/// Number of records resolved at the same time to compare them to the dns
+const RESOLVE_CONCURRENCY: usize = 8;
+/// Types of the records which are compared to the answers of the resolver
+const RESOLVED_TYPES: [&str; 3] = ["A", "AAAA", "CNAME"];
+
+/// Maximum length of a character-string in a TXT record as defined in RFC 1035
+const TXT_SEGMENT_LENGTH: usize = 255;
+/// Length over which a TXT record may not fit in an UDP response
+const TXT_WARN_LENGTH: usize = 512;
+/// Maximum length of the value of a TXT record that we accept to create
+const TXT_MAX_LENGTH: usize = 4096;
 
 #[tracing::instrument]
-pub async fn list_zones(config: Arc<Configuration>, output: &Kind) -> Result<()> {
-    let client = Client::from(ClientConfiguration::try_from(config).map_err(|err| {
-        format!(
-            "could not create ovh client configuration from the current configuration, {}",
-            err
-        )
-    })?);
+pub async fn list_zones(ctx: &ExecContext, output: &Kind, with_counts: bool) -> Result<()> {
+    let client = Client::from(
+        ClientConfiguration::try_from(ctx.config.to_owned()).map_err(|err| {
+            format!(
+                "could not create ovh client configuration from the current configuration, {}",
+                err
+            )
+        })?,
+    );
+
+    let mut zones = domain::list_zones(&client).await?;
+    if with_counts {
+        let names: Vec<_> = zones.iter().map(|zone| zone.name.to_owned()).collect();
+        let healths: Vec<_> = stream::iter(names)
+            .map(|zone| {
+                let client = &client;
+                async move { domain::get_zone_health(client, &zone).await }
+            })
+            .buffered(EXPORT_CONCURRENCY)
+            .collect()
+            .await;
+
+        for (zone, health) in zones.iter_mut().zip(healths) {
+            zone.health = Some(health?);
+        }
+    }
 
-    let zones = domain::list_zones(&client).await?;
-    let formatter = Formatter::from(zones.to_owned());
+    fmt::check_empty(zones.len(), "zone", &ctx.render)?;
+    let zones = fmt::paginate(zones, &ctx.render);
+    let formatter = Formatter::new(zones.to_owned(), &ctx.render);
     let o = match output {
-        Kind::Short => zones.short()?,
-        Kind::Wide => zones.wide()?,
+        Kind::Short => zones.short(&ctx.render)?,
+        Kind::Wide => zones.wide(&ctx.render)?,
         Kind::Json => formatter.json()?,
         Kind::Yaml => formatter.yaml()?,
+        Kind::TableJson => formatter.table_json()?,
+        Kind::Ndjson => formatter.ndjson()?,
+        Kind::Prometheus => return Err(fmt::unsupported(output)),
     };
 
     println!("{}", o);
@@ -41,7 +107,7 @@ pub async fn list_zones(config: Arc<Configuration>, output: &Kind) -> Result<()>
 }
 
 #[tracing::instrument]
-pub async fn list_records(config: Arc<Configuration>, zone: &str, output: &Kind) -> Result<()> {
+pub async fn export_zone(config: Arc<Configuration>, zone: &str) -> Result<()> {
     let client = Client::from(ClientConfiguration::try_from(config).map_err(|err| {
         format!(
             "could not create ovh client configuration from the current configuration, {}",
@@ -49,13 +115,327 @@ pub async fn list_records(config: Arc<Configuration>, zone: &str, output: &Kind)
         )
     })?);
 
+    println!("{}", domain::export_zone(&client, zone).await?);
+
+    Ok(())
+}
+
+#[tracing::instrument]
+pub async fn export_all_zones(ctx: &ExecContext, dir: &Path, timestamp: bool) -> Result<()> {
+    let client = Client::from(
+        ClientConfiguration::try_from(ctx.config.to_owned()).map_err(|err| {
+            format!(
+                "could not create ovh client configuration from the current configuration, {}",
+                err
+            )
+        })?,
+    );
+
+    fs::create_dir_all(dir)
+        .map_err(|err| format!("could not create directory '{}', {}", dir.display(), err))?;
+
+    let suffix = match timestamp {
+        true => chrono::offset::Utc::now()
+            .format("-%Y%m%d%H%M%S")
+            .to_string(),
+        false => String::new(),
+    };
+
+    info!("retrieve zones");
+    let zones = domain::list_zone_names(&client).await?;
+
+    info!("export {} zone(s) into '{}'", zones.len(), dir.display());
+    let mut pb = report::progress(zones.len() as u64, ctx.only_errors);
+    let mut exports = stream::iter(zones)
+        .map(|zone| {
+            let client = &client;
+            let path = dir.join(format!("{}{}.zone", zone, suffix));
+            async move {
+                let result = match domain::export_zone(client, &zone).await {
+                    Ok(content) => fs::write(&path, content).map_err(|err| {
+                        format!("could not write file '{}', {}", path.display(), err).into()
+                    }),
+                    Err(err) => Err(err),
+                };
+
+                (zone, result)
+            }
+        })
+        .buffer_unordered(EXPORT_CONCURRENCY);
+
+    let mut report = Report::new("export", "zone", ctx.only_errors);
+    while let Some((zone, result)) = exports.next().await {
+        match result {
+            Ok(_) => report.success(),
+            Err(err) => report.failure(zone, err),
+        }
+
+        pb.inc();
+    }
+
+    pb.finish();
+    report.finish()
+}
+
+/// Options to compare records to the answers of a resolver
+#[derive(clap::Args, Clone, Debug, Default)]
+pub struct Resolution {
+    /// Compare A, AAAA and CNAME records to the answers of a resolver, which
+    /// sends a dns query per record
+    #[clap(long = "resolve")]
+    pub resolve: bool,
+
+    /// Address of the resolver queried by '--resolve', e.g. '9.9.9.9:53',
+    /// cloudflare if omitted
+    #[clap(long = "resolver", requires = "resolve")]
+    pub resolver: Option<SocketAddr>,
+}
+
+/// Record along with the outcome of its comparison to the answers of a resolver
+#[derive(Serialize, Clone, Debug)]
+pub struct ResolvedRecord {
+    #[serde(flatten)]
+    pub record: Record,
+    /// Values answered by the resolver
+    #[serde(rename = "resolved")]
+    pub resolved: Vec<String>,
+    /// Either 'OK', 'MISMATCH', 'NXDOMAIN' or 'ERROR', none for the types of
+    /// records which are not compared
+    #[serde(rename = "resolution")]
+    pub resolution: Option<String>,
+}
+
+impl Short for Vec<ResolvedRecord> {
+    type Error = Box<dyn Error + Send + Sync>;
+
+    #[tracing::instrument]
+    fn short(&self, options: &fmt::Options) -> std::result::Result<String, Self::Error> {
+        let mut rows = vec![Row::new(vec![
+            Cell::new("Identifier"),
+            Cell::new("Type"),
+            Cell::new("Sub domain"),
+            Cell::new("Target"),
+            Cell::new("Resolution"),
+        ])];
+
+        for resolved in self {
+            let record = &resolved.record;
+            let id = match record.id {
+                Some(id) => id.to_string(),
+                None => "<none>".into(),
+            };
+
+            rows.push(Row::new(vec![
+                Cell::new(&id),
+                Cell::new(&record.field_type),
+                Cell::new(&record.sub_domain),
+                Cell::new(&record.target),
+                Cell::new(resolved.resolution.as_deref().unwrap_or("<none>")),
+            ]));
+        }
+
+        Ok(fmt::table(rows, options))
+    }
+}
+
+impl Wide for Vec<ResolvedRecord> {
+    type Error = Box<dyn Error + Send + Sync>;
+
+    #[tracing::instrument]
+    fn wide(&self, options: &fmt::Options) -> std::result::Result<String, Self::Error> {
+        let mut rows = vec![Row::new(vec![
+            Cell::new("Identifier"),
+            Cell::new("Zone"),
+            Cell::new("Type"),
+            Cell::new("Sub domain"),
+            Cell::new("TTL"),
+            Cell::new("Target"),
+            Cell::new("Resolved"),
+            Cell::new("Resolution"),
+        ])];
+
+        for resolved in self {
+            let record = &resolved.record;
+            let id = match record.id {
+                Some(id) => id.to_string(),
+                None => "<none>".into(),
+            };
+
+            let ttl = match record.ttl {
+                Some(ttl) => ttl.to_string(),
+                None => "<none>".into(),
+            };
+
+            let values = match resolved.resolved.is_empty() {
+                true => "<none>".into(),
+                false => resolved.resolved.join(", "),
+            };
+
+            rows.push(Row::new(vec![
+                Cell::new(&id),
+                Cell::new(&record.zone),
+                Cell::new(&record.field_type),
+                Cell::new(&record.sub_domain),
+                Cell::new(&ttl),
+                Cell::new(&record.target),
+                Cell::new(&values),
+                Cell::new(resolved.resolution.as_deref().unwrap_or("<none>")),
+            ]));
+        }
+
+        Ok(fmt::table(rows, options))
+    }
+}
+
+/// Create a resolver querying the given address, cloudflare if omitted
+#[tracing::instrument]
+fn resolver(address: &Option<SocketAddr>, opts: ResolverOpts) -> Result<TokioAsyncResolver> {
+    let config = match address {
+        Some(address) => ResolverConfig::from_parts(
+            None,
+            vec![],
+            NameServerConfigGroup::from_ips_clear(&[address.ip()], address.port(), true),
+        ),
+        None => ResolverConfig::cloudflare(),
+    };
+
+    Ok(TokioAsyncResolver::tokio(config, opts)
+        .map_err(|err| format!("could not create dns resolver, {}", err))?)
+}
+
+/// Returns the fully qualified name of the record
+#[tracing::instrument]
+fn fqdn(record: &Record) -> String {
+    match record.sub_domain.as_str() {
+        "" => format!("{}.", record.zone),
+        sub_domain => format!("{}.{}.", sub_domain, record.zone),
+    }
+}
+
+/// Returns the values answered by the resolver for the name, as written by
+/// [`normalize`]
+#[tracing::instrument(skip(resolver))]
+async fn lookup(
+    resolver: &TokioAsyncResolver,
+    name: &str,
+    kind: RecordType,
+) -> std::result::Result<Vec<String>, ResolveError> {
+    Ok(resolver
+        .lookup(name, kind)
+        .await?
+        .record_iter()
+        .filter_map(|answer| answer.data())
+        .map(|data| match data {
+            RData::TXT(txt) => normalize(
+                &txt.txt_data()
+                    .iter()
+                    .map(|segment| String::from_utf8_lossy(segment))
+                    .collect::<String>(),
+            ),
+            data => normalize(&data.to_string()),
+        })
+        .collect())
+}
+
+/// Compare the record to the answers of the resolver, records whose type is
+/// not compared are returned as is
+#[tracing::instrument(skip(resolver))]
+async fn resolve(resolver: &TokioAsyncResolver, record: Record) -> ResolvedRecord {
+    let kind = match RESOLVED_TYPES.contains(&record.field_type.as_str()) {
+        true => RecordType::from_str(&record.field_type).ok(),
+        false => None,
+    };
+
+    let kind = match kind {
+        Some(kind) => kind,
+        None => {
+            return ResolvedRecord {
+                record,
+                resolved: vec![],
+                resolution: None,
+            }
+        }
+    };
+
+    let name = fqdn(&record);
+    let (resolved, resolution) = match lookup(resolver, &name, kind).await {
+        Ok(values) if values.contains(&normalize(&record.target)) => (values, "OK"),
+        Ok(values) => (values, "MISMATCH"),
+        Err(err) => match err.kind() {
+            ResolveErrorKind::NoRecordsFound {
+                response_code: ResponseCode::NXDomain,
+                ..
+            } => (vec![], "NXDOMAIN"),
+            ResolveErrorKind::NoRecordsFound { .. } => (vec![], "MISMATCH"),
+            _ => {
+                warn!("could not resolve '{}' {}, {}", name, kind, err);
+                (vec![], "ERROR")
+            }
+        },
+    };
+
+    ResolvedRecord {
+        record,
+        resolved,
+        resolution: Some(resolution.to_string()),
+    }
+}
+
+#[tracing::instrument]
+pub async fn list_records(
+    ctx: &ExecContext,
+    zone: &str,
+    output: &Kind,
+    resolution: &Resolution,
+) -> Result<()> {
+    let client = Client::from(
+        ClientConfiguration::try_from(ctx.config.to_owned()).map_err(|err| {
+            format!(
+                "could not create ovh client configuration from the current configuration, {}",
+                err
+            )
+        })?,
+    );
+
     let records = domain::list_records(&client, zone).await?;
-    let formatter = Formatter::from(records.to_owned());
+    fmt::check_empty(records.len(), "record", &ctx.render)?;
+    let records = fmt::paginate(records, &ctx.render);
+    if resolution.resolve {
+        let resolver = resolver(&resolution.resolver, ResolverOpts::default())?;
+        let records: Vec<_> = stream::iter(records)
+            .map(|record| {
+                let resolver = &resolver;
+                async move { resolve(resolver, record).await }
+            })
+            .buffered(RESOLVE_CONCURRENCY)
+            .collect()
+            .await;
+
+        let formatter = Formatter::new(records.to_owned(), &ctx.render);
+        let o = match output {
+            Kind::Short => records.short(&ctx.render)?,
+            Kind::Wide => records.wide(&ctx.render)?,
+            Kind::Json => formatter.json()?,
+            Kind::Yaml => formatter.yaml()?,
+            Kind::TableJson => formatter.table_json()?,
+            Kind::Ndjson => formatter.ndjson()?,
+            Kind::Prometheus => return Err(fmt::unsupported(output)),
+        };
+
+        println!("{}", o);
+
+        return Ok(());
+    }
+
+    let formatter = Formatter::new(records.to_owned(), &ctx.render);
     let o = match output {
-        Kind::Short => records.short()?,
-        Kind::Wide => records.wide()?,
+        Kind::Short => records.short(&ctx.render)?,
+        Kind::Wide => records.wide(&ctx.render)?,
         Kind::Json => formatter.json()?,
         Kind::Yaml => formatter.yaml()?,
+        Kind::TableJson => formatter.table_json()?,
+        Kind::Ndjson => formatter.ndjson()?,
+        Kind::Prometheus => return Err(fmt::unsupported(output)),
     };
 
     println!("{}", o);
@@ -63,79 +443,302 @@ pub async fn list_records(config: Arc<Configuration>, zone: &str, output: &Kind)
     Ok(())
 }
 
-// todo(florentin.dubois): handle dedicated servers
 #[tracing::instrument]
-pub async fn sync_records(
-    config: Arc<Configuration>,
-    zone: &str,
+pub async fn find_records(
+    ctx: &ExecContext,
+    target: &str,
+    field_type: &Option<String>,
+    exact: bool,
     output: &Kind,
-    not_in_cidrs: &[IpNetwork],
 ) -> Result<()> {
-    let client = Client::from(ClientConfiguration::try_from(config).map_err(|err| {
-        format!(
-            "could not create ovh client configuration from the current configuration, {}",
-            err
-        )
-    })?);
+    let client = Client::from(
+        ClientConfiguration::try_from(ctx.config.to_owned()).map_err(|err| {
+            format!(
+                "could not create ovh client configuration from the current configuration, {}",
+                err
+            )
+        })?,
+    );
 
-    // -------------------------------------------------------------------------
-    // retrieve instances
+    info!("retrieve zones");
+    let zones = domain::list_zone_names(&client).await?;
 
-    info!("retrieve public cloud instances");
-    let tenants = list_tenants(&client).await?;
-    let mut pb = ProgressBar::new(tenants.len() as u64);
-    let mut instances = vec![];
-    for tenant in tenants {
-        instances.append(&mut list_instances(&client, &tenant.project_id).await?);
+    info!("search records of {} zone(s)", zones.len());
+    let needle = target.to_lowercase();
+    let mut pb = report::progress(zones.len() as u64, ctx.only_errors);
+    let mut searches = stream::iter(zones)
+        .map(|zone| {
+            let client = &client;
+            async move { domain::list_records(client, &zone).await }
+        })
+        .buffer_unordered(EXPORT_CONCURRENCY);
+
+    let mut records = vec![];
+    while let Some(result) = searches.next().await {
         pb.inc();
+        let hits = match client.tolerate(result)? {
+            Some(hits) => hits,
+            None => continue,
+        };
+
+        records.extend(hits.into_iter().filter(|record| {
+            let matched = if exact {
+                record.target == target
+            } else {
+                record.target.to_lowercase().contains(&needle)
+            };
+
+            matched
+                && match field_type {
+                    Some(field_type) => record.field_type.eq_ignore_ascii_case(field_type),
+                    None => true,
+                }
+        }));
     }
 
     pb.finish();
 
-    // -------------------------------------------------------------------------
-    // retrieve records
+    records.sort_by(|a, b| (&a.zone, &a.sub_domain).cmp(&(&b.zone, &b.sub_domain)));
+    fmt::check_empty(records.len(), "record", &ctx.render)?;
+    let records = fmt::paginate(records, &ctx.render);
+    let formatter = Formatter::new(records.to_owned(), &ctx.render);
+    let o = match output {
+        Kind::Short => records.short(&ctx.render)?,
+        Kind::Wide => records.wide(&ctx.render)?,
+        Kind::Json => formatter.json()?,
+        Kind::Yaml => formatter.yaml()?,
+        Kind::TableJson => formatter.table_json()?,
+        Kind::Ndjson => formatter.ndjson()?,
+        Kind::Prometheus => return Err(fmt::unsupported(output)),
+    };
 
-    // todo(florentin.dubois): use the domains::list_records function once optimized
-    info!("retrieve dns records '{}'", zone);
-    let ids: Vec<i64> = client
-        .get(&format!("domain/zone/{}/record", zone))
-        .await
-        .map_err(|err| format!("could not retrieve records in zone '{}', {}", zone, err))?;
+    println!("{}", o);
 
-    let mut pb = ProgressBar::new(ids.len() as u64);
-    let mut records = vec![];
-    for id in ids {
-        records.push(
-            client
-                .get(&format!("domain/zone/{}/record/{}", zone, id))
-                .await
-                .map_err(|err| {
-                    format!(
-                        "could not retrieve record '{}' in zone '{}', {}",
-                        id, zone, err
-                    )
-                })?,
-        );
+    Ok(())
+}
 
-        pb.inc();
+/// Outcome of the synchronisation of the records of a zone
+#[derive(Debug)]
+struct Synchronisation {
+    deleted: usize,
+    created: usize,
+    total: usize,
+    interrupted: bool,
+    report: Report,
+}
+
+/// Outcome of the synchronisation of a zone, as printed by `sync --all-zones`
+#[derive(Serialize, Clone, Debug)]
+pub struct ZoneSynchronisation {
+    #[serde(rename = "zone")]
+    pub zone: String,
+    #[serde(rename = "deleted")]
+    pub deleted: usize,
+    #[serde(rename = "created")]
+    pub created: usize,
+    #[serde(rename = "failed")]
+    pub failed: usize,
+    #[serde(rename = "error")]
+    pub error: Option<String>,
+}
+
+impl Short for Vec<ZoneSynchronisation> {
+    type Error = Box<dyn Error + Send + Sync>;
+
+    #[tracing::instrument]
+    fn short(&self, options: &fmt::Options) -> Result<String> {
+        let mut rows = vec![Row::new(vec![
+            Cell::new("Zone"),
+            Cell::new("Deleted"),
+            Cell::new("Created"),
+            Cell::new("Failed"),
+        ])];
+
+        for sync in self {
+            rows.push(Row::new(vec![
+                Cell::new(&sync.zone),
+                Cell::new(&sync.deleted.to_string()),
+                Cell::new(&sync.created.to_string()),
+                Cell::new(&sync.failed.to_string()),
+            ]));
+        }
+
+        Ok(fmt::table(rows, options))
+    }
+}
+
+impl Wide for Vec<ZoneSynchronisation> {
+    type Error = Box<dyn Error + Send + Sync>;
+
+    #[tracing::instrument]
+    fn wide(&self, options: &fmt::Options) -> Result<String> {
+        let mut rows = vec![Row::new(vec![
+            Cell::new("Zone"),
+            Cell::new("Deleted"),
+            Cell::new("Created"),
+            Cell::new("Failed"),
+            Cell::new("Error"),
+        ])];
+
+        for sync in self {
+            let error = match sync.error.to_owned() {
+                Some(error) => error,
+                None => "<none>".into(),
+            };
+
+            rows.push(Row::new(vec![
+                Cell::new(&sync.zone),
+                Cell::new(&sync.deleted.to_string()),
+                Cell::new(&sync.created.to_string()),
+                Cell::new(&sync.failed.to_string()),
+                Cell::new(&error),
+            ]));
+        }
+
+        Ok(fmt::table(rows, options))
+    }
+}
+
+/// Retrieve the instances of every tenant, tenants are listed concurrently, the
+/// progress is only drawn if visible
+#[tracing::instrument(skip(client))]
+async fn retrieve_instances(
+    ctx: &ExecContext,
+    client: &Client,
+    visible: bool,
+) -> Result<Vec<Instance>> {
+    info!("retrieve public cloud instances");
+    let tenants = list_tenants(client).await?;
+    let mut pb = match visible {
+        true => report::progress(tenants.len() as u64, ctx.only_errors),
+        false => Progress::hidden(tenants.len() as u64),
+    };
+    let tracker = pb.tracker();
+    let mut listings = stream::iter(tenants)
+        .map(|tenant| {
+            let tracker = tracker.to_owned();
+            async move {
+                let result = list_instances(client, &tenant.project_id).await;
+                tracker.inc();
+                result
+            }
+        })
+        .buffer_unordered(LIST_CONCURRENCY);
+
+    let mut instances = vec![];
+    while let Some(result) = listings.next().await {
+        instances.append(&mut result?);
+        pb.update();
     }
 
     pb.finish();
+    Ok(instances)
+}
 
-    // -------------------------------------------------------------------------
-    // compute records diff
+/// Records to delete and to create to synchronise a zone, as printed by
+/// `sync --diff-only`
+#[derive(Serialize, Clone, Debug, Default)]
+pub struct SyncPlan {
+    #[serde(rename = "create")]
+    pub create: Vec<Record>,
+    #[serde(rename = "delete")]
+    pub delete: Vec<Record>,
+}
 
-    info!(
-        "compute diff to apply, instances: {}, records: {}",
-        instances.len(),
-        records.len()
-    );
-    let mut pb = ProgressBar::new(instances.len() as u64);
+impl Short for SyncPlan {
+    type Error = Box<dyn Error + Send + Sync>;
+
+    #[tracing::instrument]
+    fn short(&self, options: &fmt::Options) -> Result<String> {
+        if self.create.is_empty() && self.delete.is_empty() {
+            return Ok(String::from("No changes"));
+        }
+
+        let mut lines = vec![];
+        for record in &self.delete {
+            lines.push(fmt::removed(&record.line(), options));
+        }
+
+        for record in &self.create {
+            lines.push(fmt::added(&record.line(), options));
+        }
+
+        Ok(lines.join("\n"))
+    }
+}
+
+impl Wide for SyncPlan {
+    type Error = Box<dyn Error + Send + Sync>;
+
+    #[tracing::instrument]
+    fn wide(&self, options: &fmt::Options) -> Result<String> {
+        if self.create.is_empty() && self.delete.is_empty() {
+            return Ok(String::from("No changes"));
+        }
+
+        let mut lines = vec![];
+        for record in &self.delete {
+            let id = match record.id {
+                Some(id) => format!("{}", id),
+                None => String::from("<none>"),
+            };
+
+            lines.push(fmt::removed(
+                &format!("{} ; {}", record.line(), id),
+                options,
+            ));
+        }
+
+        for record in &self.create {
+            lines.push(fmt::added(&record.line(), options));
+        }
+
+        Ok(lines.join("\n"))
+    }
+}
+
+/// Options of the synchronisation of the records of a zone
+#[derive(Clone, Debug)]
+struct SyncOptions<'a> {
+    not_in_cidrs: &'a [IpNetwork],
+    best_effort: bool,
+    /// Directory in which the zone is exported before applying changes, if any
+    backup_dir: Option<&'a Path>,
+    /// Draw progress bars
+    visible: bool,
+}
+
+/// Export the zone in a timestamped file of the directory, which could be used
+/// to restore the zone, and returns the path of the file
+#[tracing::instrument(skip(client))]
+async fn backup_zone(client: &Client, zone: &str, dir: &Path) -> Result<PathBuf> {
+    fs::create_dir_all(dir)
+        .map_err(|err| format!("could not create directory '{}', {}", dir.display(), err))?;
+
+    let timestamp = chrono::offset::Utc::now().format("%Y%m%d%H%M%S");
+    let path = dir.join(format!("{}-{}.zone", zone, timestamp));
+    let content = domain::export_zone(client, zone).await?;
+    fs::write(&path, content)
+        .map_err(|err| format!("could not write file '{}', {}", path.display(), err))?;
+
+    Ok(path)
+}
+
+/// Compute the records to delete and to create so that the records of the
+/// zone point to the public addresses of the given instances
+#[tracing::instrument(skip(records, instances, pb))]
+fn plan_sync(
+    zone: &str,
+    records: &[Record],
+    instances: &[Instance],
+    not_in_cidrs: &[IpNetwork],
+    pb: &mut Progress,
+) -> SyncPlan {
     let mut records_to_create = vec![];
     let mut records_to_delete = vec![];
     for instance in instances {
-        for address in instance.ip_addresses {
-            let record = domain::contains(&records, &address.ip);
+        for address in &instance.ip_addresses {
+            let record = domain::contains(records, &address.ip);
             if "public" != address.kind {
                 if let Some(record) = record {
                     records_to_delete.push(record);
@@ -185,6 +788,48 @@ pub async fn sync_records(
         pb.inc();
     }
 
+    SyncPlan {
+        create: records_to_create,
+        delete: records_to_delete,
+    }
+}
+
+/// Make the records of the zone point to the public addresses of the given
+/// instances, then refresh the zone
+#[tracing::instrument(skip(client, instances, token))]
+async fn sync_zone(
+    ctx: &ExecContext,
+    client: &Client,
+    zone: &str,
+    instances: &[Instance],
+    opts: &SyncOptions<'_>,
+    token: &CancellationToken,
+) -> Result<Synchronisation> {
+    let progress = |total: usize| match opts.visible {
+        true => report::progress(total as u64, ctx.only_errors),
+        false => Progress::hidden(total as u64),
+    };
+
+    // -------------------------------------------------------------------------
+    // retrieve records
+
+    info!("retrieve dns records '{}'", zone);
+    let records = domain::list_records(client, zone).await?;
+
+    // -------------------------------------------------------------------------
+    // compute records diff
+
+    info!(
+        "compute diff to apply, instances: {}, records: {}",
+        instances.len(),
+        records.len()
+    );
+    let mut pb = progress(instances.len());
+    let SyncPlan {
+        create: records_to_create,
+        delete: records_to_delete,
+    } = plan_sync(zone, &records, instances, opts.not_in_cidrs, &mut pb);
+
     pb.finish();
 
     // -------------------------------------------------------------------------
@@ -195,8 +840,27 @@ pub async fn sync_records(
         records_to_create.len(),
         records_to_delete.len()
     );
-    let mut pb = ProgressBar::new((records_to_delete.len() + records_to_create.len()) as u64);
+    let total = records_to_delete.len() + records_to_create.len();
+    if let (Some(dir), true) = (opts.backup_dir, 0 != total) {
+        let path = backup_zone(client, zone, dir).await.map_err(|err| {
+            Context::new(
+                format!("could not back up zone '{}' before applying changes", zone),
+                err,
+            )
+        })?;
+
+        info!("zone '{}' has been backed up in '{}'", zone, path.display());
+    }
+
+    let mut deleted = 0;
+    let mut created = 0;
+    let mut pb = progress(total);
+    let mut report = Report::new("synchronise", "record", ctx.only_errors);
     for record in records_to_delete {
+        if token.is_cancelled() {
+            break;
+        }
+
         let id = match record.id {
             Some(id) => id,
             None => {
@@ -205,17 +869,34 @@ pub async fn sync_records(
             }
         };
 
-        domain::delete_record(&client, zone, &id)
-            .await
-            .map_err(|err| format!("could not delete record '{}', {}", id, err))?;
+        // in best effort mode, failures are reported once every change has been tried
+        match domain::delete_record(client, zone, &id).await {
+            Ok(_) => {
+                report.success();
+                deleted += 1;
+            }
+            Err(err) if opts.best_effort => report.failure(record.line(), err),
+            Err(err) => {
+                return Err(Context::new(format!("could not delete record '{}'", id), err).into())
+            }
+        }
 
         pb.inc();
     }
 
     for record in records_to_create {
-        domain::create_record(&client, zone, &record)
-            .await
-            .map_err(|err| format!("could not create record, {}", err))?;
+        if token.is_cancelled() {
+            break;
+        }
+
+        match domain::create_record(client, zone, &record).await {
+            Ok(_) => {
+                report.success();
+                created += 1;
+            }
+            Err(err) if opts.best_effort => report.failure(record.line(), err),
+            Err(err) => return Err(Context::new("could not create record", err).into()),
+        }
 
         pb.inc();
     }
@@ -226,35 +907,223 @@ pub async fn sync_records(
     // Refresh records
 
     info!("refresh records");
-    domain::refresh_records(&client, zone).await?;
-
-    let records = domain::list_records(&client, zone).await?;
-    let formatter = Formatter::from(records.to_owned());
-    let o = match output {
-        Kind::Short => records.short()?,
-        Kind::Wide => records.wide()?,
-        Kind::Json => formatter.json()?,
-        Kind::Yaml => formatter.yaml()?,
-    };
+    domain::refresh_records(client, zone).await?;
 
-    println!("{}", o);
-    Ok(())
+    Ok(Synchronisation {
+        deleted,
+        created,
+        total,
+        interrupted: token.is_cancelled(),
+        report,
+    })
 }
 
+// todo(florentin.dubois): handle dedicated servers
 #[tracing::instrument]
-pub async fn refresh_records(config: Arc<Configuration>, zone: &str) -> Result<()> {
-    let client = Client::from(ClientConfiguration::try_from(config).map_err(|err| {
-        format!(
-            "could not create ovh client configuration from the current configuration, {}",
-            err
-        )
-    })?);
+pub async fn sync_records(
+    ctx: &ExecContext,
+    zone: &str,
+    output: &Kind,
+    not_in_cidrs: &[IpNetwork],
+    backup_dir: &Option<PathBuf>,
+    diff_only: bool,
+) -> Result<()> {
+    let best_effort = ctx.config.ovh.best_effort;
+    let client = Client::from(
+        ClientConfiguration::try_from(ctx.config.to_owned()).map_err(|err| {
+            format!(
+                "could not create ovh client configuration from the current configuration, {}",
+                err
+            )
+        })?,
+    );
 
-    domain::refresh_records(&client, zone).await
+    // the plan is the only thing written on the standard output, so that it
+    // could be parsed
+    let instances = retrieve_instances(ctx, &client, !diff_only).await?;
+    if diff_only {
+        let records = domain::list_records(&client, zone).await?;
+        let plan = plan_sync(
+            zone,
+            &records,
+            &instances,
+            not_in_cidrs,
+            &mut Progress::hidden(instances.len() as u64),
+        );
+
+        let formatter = Formatter::new(plan.to_owned(), &ctx.render);
+        let o = match output {
+            Kind::Short => plan.short(&ctx.render)?,
+            Kind::Wide => plan.wide(&ctx.render)?,
+            Kind::Json => formatter.json()?,
+            Kind::Yaml => formatter.yaml()?,
+            Kind::TableJson => formatter.table_json()?,
+            Kind::Prometheus | Kind::Ndjson => return Err(fmt::unsupported(output)),
+        };
+
+        println!("{}", o);
+
+        return Ok(());
+    }
+
+    let token = CancellationToken::ctrl_c();
+    let opts = SyncOptions {
+        not_in_cidrs,
+        best_effort,
+        backup_dir: backup_dir.as_deref(),
+        visible: true,
+    };
+
+    let sync = sync_zone(ctx, &client, zone, &instances, &opts, &token).await?;
+
+    if sync.interrupted {
+        println!(
+            "Interrupted, applied {} deletion(s) and {} creation(s) out of {} change(s)",
+            sync.deleted, sync.created, sync.total
+        );
+
+        return Err(format!("synchronisation of zone '{}' has been interrupted", zone).into());
+    }
+
+    if ctx.only_errors {
+        return sync.report.finish();
+    }
+
+    let records = domain::list_records(&client, zone).await?;
+    let formatter = Formatter::new(records.to_owned(), &ctx.render);
+    let o = match output {
+        Kind::Short => records.short(&ctx.render)?,
+        Kind::Wide => records.wide(&ctx.render)?,
+        Kind::Json => formatter.json()?,
+        Kind::Yaml => formatter.yaml()?,
+        Kind::TableJson => formatter.table_json()?,
+        Kind::Ndjson => formatter.ndjson()?,
+        Kind::Prometheus => return Err(fmt::unsupported(output)),
+    };
+
+    println!("{}", o);
+    sync.report.finish()
+}
+
+/// Synchronise the records of every zone with the instances whose name ends
+/// with the zone, e.g. 'web.example.com' for 'example.com'. Zones are
+/// synchronised concurrently and a failing zone does not stop the others.
+#[tracing::instrument]
+pub async fn sync_all_zones(
+    ctx: &ExecContext,
+    output: &Kind,
+    not_in_cidrs: &[IpNetwork],
+    backup_dir: &Option<PathBuf>,
+) -> Result<()> {
+    let best_effort = ctx.config.ovh.best_effort;
+    let client = Client::from(
+        ClientConfiguration::try_from(ctx.config.to_owned()).map_err(|err| {
+            format!(
+                "could not create ovh client configuration from the current configuration, {}",
+                err
+            )
+        })?,
+    );
+
+    let instances = retrieve_instances(ctx, &client, true).await?;
+
+    info!("retrieve zones");
+    let zones = domain::list_zone_names(&client).await?;
+
+    info!("synchronise {} zone(s)", zones.len());
+    let opts = SyncOptions {
+        not_in_cidrs,
+        best_effort,
+        backup_dir: backup_dir.as_deref(),
+        visible: false,
+    };
+
+    let token = CancellationToken::ctrl_c();
+    let mut pb = report::progress(zones.len() as u64, ctx.only_errors);
+    let mut syncs = stream::iter(zones)
+        .map(|zone| {
+            let client = &client;
+            let instances = &instances;
+            let opts = &opts;
+            let token = &token;
+            async move {
+                let suffix = format!(".{}", zone);
+                let instances: Vec<Instance> = instances
+                    .iter()
+                    .filter(|instance| instance.name.ends_with(&suffix))
+                    .cloned()
+                    .collect();
+
+                let result = sync_zone(ctx, client, &zone, &instances, opts, token).await;
+
+                (zone, result)
+            }
+        })
+        .buffer_unordered(EXPORT_CONCURRENCY);
+
+    let mut report = Report::new("synchronise", "zone", ctx.only_errors);
+    let mut summary = vec![];
+    while let Some((zone, result)) = syncs.next().await {
+        let mut outcome = ZoneSynchronisation {
+            zone: zone.to_owned(),
+            deleted: 0,
+            created: 0,
+            failed: 0,
+            error: None,
+        };
+
+        match result {
+            Ok(sync) => {
+                outcome.deleted = sync.deleted;
+                outcome.created = sync.created;
+                outcome.failed = sync.report.failed();
+                if sync.interrupted {
+                    outcome.error = Some("interrupted".into());
+                } else if let Err(err) = sync.report.finish() {
+                    outcome.error = Some(err.to_string());
+                }
+            }
+            Err(err) => outcome.error = Some(err.to_string()),
+        }
+
+        match &outcome.error {
+            Some(err) => report.failure(&zone, err),
+            None => report.success(),
+        }
+
+        summary.push(outcome);
+        pb.inc();
+    }
+
+    pb.finish();
+
+    if token.is_cancelled() {
+        return Err("synchronisation of zones has been interrupted".into());
+    }
+
+    if !ctx.only_errors {
+        summary.sort_by(|a, b| a.zone.cmp(&b.zone));
+        let formatter = Formatter::new(summary.to_owned(), &ctx.render);
+        let o = match output {
+            Kind::Short => summary.short(&ctx.render)?,
+            Kind::Wide => summary.wide(&ctx.render)?,
+            Kind::Json => formatter.json()?,
+            Kind::Yaml => formatter.yaml()?,
+            Kind::TableJson => formatter.table_json()?,
+            Kind::Ndjson => formatter.ndjson()?,
+            Kind::Prometheus => return Err(fmt::unsupported(output)),
+        };
+
+        println!("{}", o);
+    }
+
+    report.finish()
 }
 
+/// Print the records of the zone in json or yaml, along with their zone, which
+/// could be read back by commands taking a file of records, e.g. 'diff'
 #[tracing::instrument]
-pub async fn delete_record(config: Arc<Configuration>, zone: &str, id: &i64) -> Result<()> {
+pub async fn export_records(config: Arc<Configuration>, zone: &str, output: &Kind) -> Result<()> {
     let client = Client::from(ClientConfiguration::try_from(config).map_err(|err| {
         format!(
             "could not create ovh client configuration from the current configuration, {}",
@@ -262,5 +1131,669 @@ pub async fn delete_record(config: Arc<Configuration>, zone: &str, id: &i64) ->
         )
     })?);
 
-    domain::delete_record(&client, zone, id).await
+    let mut records: Vec<ExportedRecord> = domain::list_records(&client, zone)
+        .await?
+        .into_iter()
+        .map(ExportedRecord::from)
+        .collect();
+
+    // a stable order keeps exports of an unchanged zone identical
+    records.sort_by(|a, b| {
+        (&a.sub_domain, &a.field_type, &a.target).cmp(&(&b.sub_domain, &b.field_type, &b.target))
+    });
+
+    let o = match output {
+        Kind::Json => serde_json::to_string_pretty(&records)
+            .map_err(|err| format!("could not serialize in json, {}", err))?,
+        Kind::Yaml => serde_yaml::to_string(&records)
+            .map_err(|err| format!("could not serialize in yaml, {}", err))?,
+        Kind::Short | Kind::Wide | Kind::Prometheus | Kind::TableJson | Kind::Ndjson => {
+            return Err(fmt::unsupported(output))
+        }
+    };
+
+    println!("{}", o);
+
+    Ok(())
+}
+
+/// Read the desired records of the zone from a yaml or json file, records of an
+/// export keep their zone which must be the given one
+#[tracing::instrument]
+pub fn read_records(zone: &str, path: &Path) -> Result<Vec<Record>> {
+    let content = fs::read_to_string(path)
+        .map_err(|err| format!("could not read file '{}', {}", path.display(), err))?;
+
+    let exported: Vec<ExportedRecord> = serde_yaml::from_str(&content).map_err(|err| {
+        format!(
+            "could not parse records of file '{}', {}",
+            path.display(),
+            err
+        )
+    })?;
+
+    let mut records = vec![];
+    for record in exported {
+        if !record.zone.is_empty() && record.zone != zone {
+            return Err(format!(
+                "record '{}' of type '{}' in file '{}' belongs to zone '{}', not '{}'",
+                record.sub_domain,
+                record.field_type,
+                path.display(),
+                record.zone,
+                zone
+            )
+            .into());
+        }
+
+        let record = Record {
+            zone: String::from(zone),
+            ..Record::from(record)
+        };
+
+        record.validate()?;
+        records.push(record);
+    }
+
+    Ok(records)
+}
+
+#[tracing::instrument]
+pub async fn diff_records(ctx: &ExecContext, zone: &str, path: &Path, output: &Kind) -> Result<()> {
+    let client = Client::from(
+        ClientConfiguration::try_from(ctx.config.to_owned()).map_err(|err| {
+            format!(
+                "could not create ovh client configuration from the current configuration, {}",
+                err
+            )
+        })?,
+    );
+
+    let desired = read_records(zone, path)?;
+    let live = domain::list_records(&client, zone).await?;
+    let changes = domain::diff(&live, &desired);
+
+    let formatter = Formatter::new(changes.to_owned(), &ctx.render);
+    let o = match output {
+        Kind::Short => changes.short(&ctx.render)?,
+        Kind::Wide => changes.wide(&ctx.render)?,
+        Kind::Json => formatter.json()?,
+        Kind::Yaml => formatter.yaml()?,
+        Kind::TableJson => formatter.table_json()?,
+        Kind::Ndjson => formatter.ndjson()?,
+        Kind::Prometheus => return Err(fmt::unsupported(output)),
+    };
+
+    println!("{}", o);
+
+    Ok(())
+}
+
+#[tracing::instrument]
+pub async fn refresh_records(config: Arc<Configuration>, zone: &str) -> Result<()> {
+    let client = Client::from(ClientConfiguration::try_from(config).map_err(|err| {
+        format!(
+            "could not create ovh client configuration from the current configuration, {}",
+            err
+        )
+    })?);
+
+    domain::refresh_records(&client, zone).await
+}
+
+#[tracing::instrument]
+pub async fn refresh_all_zones(ctx: &ExecContext) -> Result<()> {
+    let client = Client::from(
+        ClientConfiguration::try_from(ctx.config.to_owned()).map_err(|err| {
+            format!(
+                "could not create ovh client configuration from the current configuration, {}",
+                err
+            )
+        })?,
+    );
+
+    info!("retrieve zones");
+    let zones = domain::list_zone_names(&client).await?;
+
+    info!("refresh {} zone(s)", zones.len());
+    let mut pb = report::progress(zones.len() as u64, ctx.only_errors);
+    let mut report = Report::new("refresh", "zone", ctx.only_errors);
+    for zone in zones {
+        match domain::refresh_records(&client, &zone).await {
+            Ok(_) => report.success(),
+            Err(err) => report.failure(zone, err),
+        }
+
+        pb.inc();
+    }
+
+    pb.finish();
+    report.finish()
+}
+
+#[tracing::instrument]
+pub async fn delete_record(
+    ctx: &ExecContext,
+    zone: &str,
+    id: &i64,
+    propagation: &Propagation,
+) -> Result<()> {
+    let client = Client::from(
+        ClientConfiguration::try_from(ctx.config.to_owned()).map_err(|err| {
+            format!(
+                "could not create ovh client configuration from the current configuration, {}",
+                err
+            )
+        })?,
+    );
+
+    let message = format!("Deleting record '{}' of zone '{}'", id, zone);
+    if !prompt::confirm_destructive(ctx.confirm_destructive, &message, &id.to_string())? {
+        return Err(format!("deletion of record '{}' has been aborted", id).into());
+    }
+
+    // the record is only known before its deletion
+    let record = match propagation.wait {
+        true => Some(domain::get_record(&client, zone, id).await?),
+        false => None,
+    };
+
+    domain::delete_record(&client, zone, id).await?;
+
+    match record {
+        Some(record) => {
+            domain::refresh_records(&client, zone).await?;
+            wait_propagation(&record, propagation, true).await
+        }
+        None => Ok(()),
+    }
+}
+
+/// Returns the target given on the command line or the trimmed content of
+/// the given file
+#[tracing::instrument]
+pub fn read_target(target: &Option<String>, target_file: &Option<PathBuf>) -> Result<String> {
+    match (target, target_file) {
+        (Some(_), Some(_)) => Err("could not use both a target and a target file".into()),
+        (Some(target), None) => Ok(target.to_owned()),
+        (None, Some(path)) => Ok(fs::read_to_string(path)
+            .map_err(|err| {
+                format!(
+                    "could not read target from file '{}', {}",
+                    path.display(),
+                    err
+                )
+            })?
+            .trim()
+            .to_string()),
+        (None, None) => Err("a target or a target file is required".into()),
+    }
+}
+
+#[tracing::instrument]
+pub async fn create_record(
+    ctx: &ExecContext,
+    zone: &str,
+    field_type: &str,
+    sub_domain: &str,
+    target: &str,
+    ttl: &Option<i64>,
+    output: &Kind,
+) -> Result<Record> {
+    let client = Client::from(
+        ClientConfiguration::try_from(ctx.config.to_owned()).map_err(|err| {
+            format!(
+                "could not create ovh client configuration from the current configuration, {}",
+                err
+            )
+        })?,
+    );
+
+    let field_type = field_type.to_uppercase();
+    let target = match field_type.as_str() {
+        "TXT" => txt_target(target)?,
+        _ => target.to_string(),
+    };
+
+    let record = Record {
+        id: None,
+        field_type,
+        sub_domain: String::from(sub_domain),
+        ttl: ttl.to_owned(),
+        zone: String::from(zone),
+        target,
+    };
+
+    let records = vec![domain::create_record(&client, zone, &record)
+        .await
+        .map_err(|err| Context::new("could not create record", err))?];
+
+    domain::refresh_records(&client, zone).await?;
+
+    let formatter = Formatter::new(records.to_owned(), &ctx.render);
+    let o = match output {
+        Kind::Short => records.short(&ctx.render)?,
+        Kind::Wide => records.wide(&ctx.render)?,
+        Kind::Json => formatter.json()?,
+        Kind::Yaml => formatter.yaml()?,
+        Kind::TableJson => formatter.table_json()?,
+        Kind::Ndjson => formatter.ndjson()?,
+        Kind::Prometheus => return Err(fmt::unsupported(output)),
+    };
+
+    println!("{}", o);
+
+    Ok(records[0].to_owned())
+}
+
+/// Options to wait for a change of record to be visible on a public resolver
+#[derive(clap::Args, Clone, Debug, Default)]
+pub struct Propagation {
+    /// Wait for the change to be visible on a public resolver
+    #[clap(long = "wait-propagation")]
+    pub wait: bool,
+
+    /// Number of seconds after which to stop waiting, defaults to the ttl of the
+    /// record plus a minute as resolvers may have cached the previous answer
+    #[clap(long = "propagation-timeout")]
+    pub timeout: Option<u64>,
+}
+
+/// Returns the value as written by resolvers, without the quotes and the
+/// segmentation of TXT records nor the trailing dot of names
+#[tracing::instrument]
+fn normalize(value: &str) -> String {
+    let value = value.trim();
+    let value = match value.starts_with('"') && value.ends_with('"') && value.len() > 1 {
+        true => unquote(value),
+        false => value.to_string(),
+    };
+
+    value.trim_end_matches('.').to_lowercase()
+}
+
+/// Returns the content of the quoted segments joined together, with their
+/// escaped characters restored, as written by `txt_target`
+#[tracing::instrument]
+fn unquote(value: &str) -> String {
+    let mut unquoted = String::new();
+    let mut quoted = false;
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        match (c, quoted) {
+            ('"', _) => quoted = !quoted,
+            ('\\', true) => unquoted.extend(chars.next()),
+            (_, true) => unquoted.push(c),
+            // spaces between segments
+            (_, false) => {}
+        }
+    }
+
+    unquoted
+}
+
+/// Query a public resolver until the record is visible, or not anymore if it
+/// has been deleted, or the timeout elapses
+#[tracing::instrument]
+pub async fn wait_propagation(
+    record: &Record,
+    propagation: &Propagation,
+    deleted: bool,
+) -> Result<()> {
+    if !propagation.wait {
+        return Ok(());
+    }
+
+    let name = fqdn(record);
+    let kind = RecordType::from_str(&record.field_type).map_err(|err| {
+        format!(
+            "could not wait for the propagation of record type '{}', {}",
+            record.field_type, err
+        )
+    })?;
+
+    // answers must not be cached by the resolver as we are polling it
+    let mut opts = ResolverOpts::default();
+    opts.cache_size = 0;
+
+    let resolver = resolver(&None, opts)?;
+
+    let timeout = propagation.timeout.unwrap_or_else(|| {
+        record
+            .ttl
+            .filter(|ttl| *ttl > 0)
+            .map_or(PROPAGATION_DEFAULT_TTL, |ttl| ttl as u64)
+            + PROPAGATION_MARGIN
+    });
+
+    let expected = normalize(&record.target);
+    let deadline = Instant::now() + Duration::from_secs(timeout);
+    loop {
+        let values = match lookup(&resolver, &name, kind).await {
+            Ok(values) => values,
+            Err(err) if matches!(err.kind(), ResolveErrorKind::NoRecordsFound { .. }) => vec![],
+            Err(err) => {
+                warn!("could not resolve '{}' {}, {}", name, kind, err);
+                vec![]
+            }
+        };
+
+        if deleted != values.contains(&expected) {
+            info!("Record '{}' {} has been propagated", name, kind);
+            return Ok(());
+        }
+
+        if Instant::now() >= deadline {
+            return Err(format!(
+                "record '{}' {} has not been propagated after {} second(s)",
+                name, kind, timeout
+            )
+            .into());
+        }
+
+        info!("Record '{}' {} is not propagated yet, waiting", name, kind);
+        tokio::time::sleep(PROPAGATION_INTERVAL).await;
+    }
+}
+
+/// Split the value in segments of at most 255 bytes, without breaking characters,
+/// and quote them as expected by the dns
+#[tracing::instrument]
+pub fn txt_target(value: &str) -> Result<String> {
+    if value.is_empty() {
+        return Err("TXT record value could not be empty".into());
+    }
+
+    if value.len() > TXT_MAX_LENGTH {
+        return Err(format!(
+            "TXT record value is {} bytes long, which exceeds the maximum of {} bytes",
+            value.len(),
+            TXT_MAX_LENGTH
+        )
+        .into());
+    }
+
+    if value.len() > TXT_WARN_LENGTH {
+        warn!(
+            "TXT record value is {} bytes long, resolvers may need to fallback on tcp to retrieve it",
+            value.len()
+        );
+    }
+
+    let mut segments = vec![];
+    let mut segment = String::new();
+    for c in value.chars() {
+        if segment.len() + c.len_utf8() > TXT_SEGMENT_LENGTH {
+            segments.push(segment);
+            segment = String::new();
+        }
+
+        segment.push(c);
+    }
+
+    segments.push(segment);
+
+    Ok(segments
+        .iter()
+        .map(|segment| format!("\"{}\"", segment.replace('\\', "\\\\").replace('"', "\\\"")))
+        .collect::<Vec<_>>()
+        .join(" "))
+}
+
+#[tracing::instrument]
+pub async fn add_txt(
+    ctx: &ExecContext,
+    zone: &str,
+    sub_domain: &str,
+    value: &str,
+    output: &Kind,
+) -> Result<Record> {
+    let client = Client::from(
+        ClientConfiguration::try_from(ctx.config.to_owned()).map_err(|err| {
+            format!(
+                "could not create ovh client configuration from the current configuration, {}",
+                err
+            )
+        })?,
+    );
+
+    let record = Record {
+        id: None,
+        field_type: String::from("TXT"),
+        sub_domain: String::from(sub_domain),
+        ttl: None,
+        zone: String::from(zone),
+        target: txt_target(value)?,
+    };
+
+    let records = vec![domain::create_record(&client, zone, &record)
+        .await
+        .map_err(|err| Context::new("could not create record", err))?];
+
+    domain::refresh_records(&client, zone).await?;
+
+    let formatter = Formatter::new(records.to_owned(), &ctx.render);
+    let o = match output {
+        Kind::Short => records.short(&ctx.render)?,
+        Kind::Wide => records.wide(&ctx.render)?,
+        Kind::Json => formatter.json()?,
+        Kind::Yaml => formatter.yaml()?,
+        Kind::TableJson => formatter.table_json()?,
+        Kind::Ndjson => formatter.ndjson()?,
+        Kind::Prometheus => return Err(fmt::unsupported(output)),
+    };
+
+    println!("{}", o);
+
+    Ok(records[0].to_owned())
+}
+
+#[tracing::instrument]
+pub async fn add_spf(
+    ctx: &ExecContext,
+    zone: &str,
+    sub_domain: &str,
+    includes: &[String],
+    cidrs: &[IpNetwork],
+    all: &str,
+    output: &Kind,
+) -> Result<Record> {
+    let mut mechanisms = vec![String::from("v=spf1")];
+    for cidr in cidrs {
+        match cidr {
+            IpNetwork::V4(cidr) => mechanisms.push(format!("ip4:{}", cidr)),
+            IpNetwork::V6(cidr) => mechanisms.push(format!("ip6:{}", cidr)),
+        }
+    }
+
+    for include in includes {
+        mechanisms.push(format!("include:{}", include));
+    }
+
+    mechanisms.push(String::from(all));
+
+    add_txt(ctx, zone, sub_domain, &mechanisms.join(" "), output).await
+}
+
+#[tracing::instrument]
+pub async fn add_dkim(
+    ctx: &ExecContext,
+    zone: &str,
+    selector: &str,
+    key_type: &str,
+    public_key: &str,
+    output: &Kind,
+) -> Result<Record> {
+    // public keys are often copied from pem files, so strip the surrounding spaces
+    let public_key: String = public_key.split_whitespace().collect();
+    let value = format!("v=DKIM1; k={}; p={}", key_type, public_key);
+
+    add_txt(
+        ctx,
+        zone,
+        &format!("{}._domainkey", selector),
+        &value,
+        output,
+    )
+    .await
+}
+
+/// Mail providers for which we know the records to set up
+#[derive(Clone, Debug)]
+pub enum MailProvider {
+    Ovh,
+    Google,
+    Office365,
+}
+
+impl FromStr for MailProvider {
+    type Err = Box<dyn Error + Send + Sync>;
+
+    #[tracing::instrument]
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "ovh" => Ok(Self::Ovh),
+            "google" => Ok(Self::Google),
+            "o365" => Ok(Self::Office365),
+            _ => Err(format!("'{}' is not allowed, only 'ovh', 'google' or 'o365'", s).into()),
+        }
+    }
+}
+
+impl MailProvider {
+    /// Returns the field type, sub domain and target of the records to set up
+    #[tracing::instrument]
+    pub fn template(&self, zone: &str) -> Vec<(&'static str, &'static str, String)> {
+        let mut template = match self {
+            Self::Ovh => vec![
+                ("MX", "", String::from("1 mx1.mail.ovh.net.")),
+                ("MX", "", String::from("5 mx2.mail.ovh.net.")),
+                ("MX", "", String::from("100 mx3.mail.ovh.net.")),
+                ("TXT", "", String::from("v=spf1 include:mx.ovh.com ~all")),
+                (
+                    "SRV",
+                    "_autodiscover._tcp",
+                    String::from("0 0 443 mailconfig.ovh.net."),
+                ),
+            ],
+            Self::Google => vec![
+                ("MX", "", String::from("1 aspmx.l.google.com.")),
+                ("MX", "", String::from("5 alt1.aspmx.l.google.com.")),
+                ("MX", "", String::from("5 alt2.aspmx.l.google.com.")),
+                ("MX", "", String::from("10 alt3.aspmx.l.google.com.")),
+                ("MX", "", String::from("10 alt4.aspmx.l.google.com.")),
+                (
+                    "TXT",
+                    "",
+                    String::from("v=spf1 include:_spf.google.com ~all"),
+                ),
+            ],
+            Self::Office365 => vec![
+                (
+                    "MX",
+                    "",
+                    format!("0 {}.mail.protection.outlook.com.", zone.replace('.', "-")),
+                ),
+                (
+                    "TXT",
+                    "",
+                    String::from("v=spf1 include:spf.protection.outlook.com -all"),
+                ),
+                (
+                    "CNAME",
+                    "autodiscover",
+                    String::from("autodiscover.outlook.com."),
+                ),
+            ],
+        };
+
+        template.push(("TXT", "_dmarc", String::from("v=DMARC1; p=none")));
+        template
+    }
+}
+
+#[tracing::instrument]
+pub async fn email_setup(
+    ctx: &ExecContext,
+    zone: &str,
+    provider: &MailProvider,
+    dry_run: bool,
+    output: &Kind,
+) -> Result<()> {
+    let client = Client::from(
+        ClientConfiguration::try_from(ctx.config.to_owned()).map_err(|err| {
+            format!(
+                "could not create ovh client configuration from the current configuration, {}",
+                err
+            )
+        })?,
+    );
+
+    let existing_records = domain::list_records(&client, zone).await?;
+
+    let mut records = vec![];
+    let mut created = 0;
+    for (field_type, sub_domain, target) in provider.template(zone) {
+        let target = match field_type {
+            "TXT" => txt_target(&target)?,
+            _ => target,
+        };
+
+        let record = Record {
+            id: None,
+            field_type: String::from(field_type),
+            sub_domain: String::from(sub_domain),
+            ttl: None,
+            zone: String::from(zone),
+            target,
+        };
+
+        // ensure semantic, we only create records that are not already present
+        if let Some(existing) = existing_records.iter().find(|r| **r == record) {
+            info!(
+                "record '{}' of type '{}' already exists, skip it",
+                record.target, record.field_type
+            );
+
+            records.push(existing.to_owned());
+            continue;
+        }
+
+        if dry_run {
+            info!(
+                "would create record '{}' of type '{}'",
+                record.target, record.field_type
+            );
+
+            records.push(record);
+            continue;
+        }
+
+        records.push(
+            domain::create_record(&client, zone, &record)
+                .await
+                .map_err(|err| Context::new("could not create record", err))?,
+        );
+
+        created += 1;
+    }
+
+    if created > 0 {
+        domain::refresh_records(&client, zone).await?;
+    }
+
+    let formatter = Formatter::new(records.to_owned(), &ctx.render);
+    let o = match output {
+        Kind::Short => records.short(&ctx.render)?,
+        Kind::Wide => records.wide(&ctx.render)?,
+        Kind::Json => formatter.json()?,
+        Kind::Yaml => formatter.yaml()?,
+        Kind::TableJson => formatter.table_json()?,
+        Kind::Ndjson => formatter.ndjson()?,
+        Kind::Prometheus => return Err(fmt::unsupported(output)),
+    };
+
+    println!("{}", o);
+
+    Ok(())
 }
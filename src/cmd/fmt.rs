@@ -2,9 +2,49 @@
 //!
 //! This module provide utilities to format command line output
 use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io::Write;
+use std::path::Path;
 use std::str::FromStr;
 
+use jsonpath_lib::Compiled;
+use prettytable::format::{consts, FormatBuilder, LinePosition, LineSeparator};
+use prettytable::{color, Attr, Cell, Row, Table};
 use serde::Serialize;
+use term::{color::Color, terminfo::TerminfoTerminal, Terminal};
+
+use crate::ovh;
+use crate::util::types::Empty;
+
+/// Options of the rendering of command outputs, given by the global flags of the
+/// command line
+#[derive(Clone, Debug)]
+pub struct Options {
+    pub colorize: bool,
+    pub table_style: TableStyle,
+    pub bool_style: BoolStyle,
+    /// Maximum number of characters in a table cell, unlimited if none
+    pub max_col_width: Option<usize>,
+    /// Fail listings which have no result, for health checks
+    pub fail_on_empty: bool,
+    /// Maximum number of items written by listings, unlimited if none
+    pub limit: Option<usize>,
+    /// Number of items of listings skipped before the written ones
+    pub offset: usize,
+    /// Write sizes and prices in a human readable way in tables
+    pub human: bool,
+    /// Append a row with the sums of numeric columns to tables
+    pub totals: bool,
+    /// Write collections as a yaml document per item
+    pub yaml_multidoc: bool,
+    /// Wrap json documents in an envelope holding the metrics of the requests
+    pub with_meta: bool,
+    /// Query applied to the json, yaml and ndjson documents, if any
+    pub select: Option<Select>,
+    /// Headers of the columns kept in tables, all of them if empty
+    pub columns: Vec<String>,
+}
 
 #[derive(Clone, Debug)]
 pub enum Kind {
@@ -12,6 +52,12 @@ pub enum Kind {
     Wide,
     Json,
     Yaml,
+    Prometheus,
+    /// The short table followed by the json document, to check that formatters
+    /// match the data
+    TableJson,
+    /// One compact json object per line, e.g. for log pipelines
+    Ndjson,
 }
 
 impl FromStr for Kind {
@@ -24,8 +70,58 @@ impl FromStr for Kind {
             "wide" => Ok(Self::Wide),
             "json" => Ok(Self::Json),
             "yaml" => Ok(Self::Yaml),
+            "prometheus" => Ok(Self::Prometheus),
+            "table+json" => Ok(Self::TableJson),
+            "ndjson" => Ok(Self::Ndjson),
+            _ => Err(format!(
+                "'{}' is not allowed, only 'short', 'wide', 'json', 'yaml', 'prometheus', 'table+json' or 'ndjson'",
+                s
+            )
+            .into()),
+        }
+    }
+}
+
+impl Kind {
+    /// Returns the name of the format, as given on the command line
+    pub const fn name(&self) -> &'static str {
+        match self {
+            Self::Short => "short",
+            Self::Wide => "wide",
+            Self::Json => "json",
+            Self::Yaml => "yaml",
+            Self::Prometheus => "prometheus",
+            Self::TableJson => "table+json",
+            Self::Ndjson => "ndjson",
+        }
+    }
+}
+
+impl fmt::Display for Kind {
+    #[tracing::instrument(skip(f))]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TableStyle {
+    Default,
+    Compact,
+    Markdown,
+}
+
+impl FromStr for TableStyle {
+    type Err = Box<dyn Error + Send + Sync>;
+
+    #[tracing::instrument]
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "default" => Ok(Self::Default),
+            "compact" => Ok(Self::Compact),
+            "markdown" => Ok(Self::Markdown),
             _ => Err(format!(
-                "'{}' is not allowed, only 'short', 'wide', 'json' or 'yaml",
+                "'{}' is not allowed, only 'default', 'compact' or 'markdown'",
                 s
             )
             .into()),
@@ -33,6 +129,111 @@ impl FromStr for Kind {
     }
 }
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BoolStyle {
+    TrueFalse,
+    YesNo,
+    Symbol,
+}
+
+impl FromStr for BoolStyle {
+    type Err = Box<dyn Error + Send + Sync>;
+
+    #[tracing::instrument]
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "true-false" => Ok(Self::TrueFalse),
+            "yes-no" => Ok(Self::YesNo),
+            "symbol" => Ok(Self::Symbol),
+            _ => Err(format!(
+                "'{}' is not allowed, only 'true-false', 'yes-no' or 'symbol'",
+                s
+            )
+            .into()),
+        }
+    }
+}
+
+/// Version of the json and yaml documents, a released version is never changed,
+/// new fields are written under a new version so that scripts could pin one
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FormatVersion {
+    /// Documents as written when versions were introduced
+    V1,
+}
+
+impl FromStr for FormatVersion {
+    type Err = Box<dyn Error + Send + Sync>;
+
+    #[tracing::instrument]
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "v1" => Ok(Self::V1),
+            _ => Err(format!("'{}' is not allowed, only 'v1'", s).into()),
+        }
+    }
+}
+
+impl fmt::Display for FormatVersion {
+    #[tracing::instrument(skip(f))]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::V1 => write!(f, "v1"),
+        }
+    }
+}
+
+/// JSONPath query selecting values of a serialized document, e.g.
+/// `$[?(@.fieldType == 'A')].target`
+#[derive(Clone, Debug)]
+pub struct Select {
+    path: String,
+    compiled: Compiled,
+}
+
+impl FromStr for Select {
+    type Err = Box<dyn Error + Send + Sync>;
+
+    #[tracing::instrument]
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let compiled = Compiled::compile(s)
+            .map_err(|err| format!("could not parse jsonpath query '{}', {}", s, err))?;
+
+        Ok(Self {
+            path: s.to_string(),
+            compiled,
+        })
+    }
+}
+
+impl Select {
+    /// Returns the matched values one per line, strings are written without
+    /// quotes, objects and arrays as compact json
+    #[tracing::instrument(skip(inner))]
+    pub fn apply<T>(&self, inner: &T) -> Result<String, Box<dyn Error + Send + Sync>>
+    where
+        T: Serialize,
+    {
+        let document = serde_json::to_value(inner)
+            .map_err(|err| format!("could not serialize in json, {}", err))?;
+
+        let values = self
+            .compiled
+            .select(&document)
+            .map_err(|err| format!("could not apply jsonpath query '{}', {}", self.path, err))?;
+
+        let mut lines = vec![];
+        for value in values {
+            match value {
+                serde_json::Value::String(s) => lines.push(s.to_owned()),
+                value => lines.push(value.to_string()),
+            }
+        }
+
+        Ok(lines.join("\n"))
+    }
+}
+
 pub trait Json {
     type Error;
 
@@ -45,36 +246,70 @@ pub trait Yaml {
     fn yaml(&self) -> Result<String, Self::Error>;
 }
 
+/// Render metrics in the prometheus text format, e.g. for the textfile
+/// collector of the node exporter
+pub trait Prometheus {
+    type Error;
+
+    fn prometheus(&self) -> Result<String, Self::Error>;
+}
+
+/// Render the short table followed by the json document, which helps to check
+/// formatters when adding a resource
+pub trait TableJson {
+    type Error;
+
+    fn table_json(&self) -> Result<String, Self::Error>;
+}
+
+/// Render each item of a collection as a compact json object on its own line
+pub trait Ndjson {
+    type Error;
+
+    fn ndjson(&self) -> Result<String, Self::Error>;
+}
+
 pub trait Short {
     type Error;
 
-    fn short(&self) -> Result<String, Self::Error>;
+    fn short(&self, options: &Options) -> Result<String, Self::Error>;
 }
 
 pub trait Wide {
     type Error;
 
-    fn wide(&self) -> Result<String, Self::Error>;
+    fn wide(&self, options: &Options) -> Result<String, Self::Error>;
 }
 
-pub struct Formatter<T>
+pub struct Formatter<'a, T>
 where
     T: Sized + Serialize + Short + Wide,
 {
     inner: T,
+    options: &'a Options,
 }
 
-impl<T> From<T> for Formatter<T>
+impl<'a, T> Formatter<'a, T>
 where
     T: Sized + Serialize + Short + Wide,
 {
+    /// Create a formatter of the value, rendered using the given options
     #[tracing::instrument(skip(inner))]
-    fn from(inner: T) -> Self {
-        Self { inner }
+    pub fn new(inner: T, options: &'a Options) -> Self {
+        Self { inner, options }
     }
 }
 
-impl<T> Json for Formatter<T>
+/// Json document along with the metrics of the requests sent to the api
+#[derive(Serialize)]
+struct Envelope<'a, T> {
+    #[serde(rename = "data")]
+    data: &'a T,
+    #[serde(rename = "meta")]
+    meta: ovh::Metrics,
+}
+
+impl<'a, T> Json for Formatter<'a, T>
 where
     T: Sized + Serialize + Short + Wide,
 {
@@ -82,12 +317,23 @@ where
 
     #[tracing::instrument(skip(self))]
     fn json(&self) -> Result<String, Self::Error> {
-        Ok(serde_json::to_string_pretty(&self.inner)
-            .map_err(|err| format!("could not serialize in json, {}", err))?)
+        if let Some(select) = &self.options.select {
+            return select.apply(&self.inner);
+        }
+
+        let document = match self.options.with_meta {
+            false => serde_json::to_string_pretty(&self.inner),
+            true => serde_json::to_string_pretty(&Envelope {
+                data: &self.inner,
+                meta: ovh::metrics(),
+            }),
+        };
+
+        Ok(document.map_err(|err| format!("could not serialize in json, {}", err))?)
     }
 }
 
-impl<T> Yaml for Formatter<T>
+impl<'a, T> Yaml for Formatter<'a, T>
 where
     T: Sized + Serialize + Short + Wide,
 {
@@ -95,12 +341,35 @@ where
 
     #[tracing::instrument(skip(self))]
     fn yaml(&self) -> Result<String, Self::Error> {
+        if let Some(select) = &self.options.select {
+            return select.apply(&self.inner);
+        }
+
+        // each document starts with its separator, so that the stream could be
+        // concatenated with others
+        if self.options.yaml_multidoc {
+            let value = serde_yaml::to_value(&self.inner)
+                .map_err(|err| format!("could not serialize in yaml, {}", err))?;
+
+            if let serde_yaml::Value::Sequence(items) = value {
+                let mut documents = vec![];
+                for item in items {
+                    let document = serde_yaml::to_string(&item)
+                        .map_err(|err| format!("could not serialize in yaml, {}", err))?;
+
+                    documents.push(format!("---\n{}", document.trim_end()));
+                }
+
+                return Ok(documents.join("\n"));
+            }
+        }
+
         Ok(serde_yaml::to_string(&self.inner)
             .map_err(|err| format!("could not serialize in yaml, {}", err))?)
     }
 }
 
-impl<T> Short for Formatter<T>
+impl<'a, T> Short for Formatter<'a, T>
 where
     T: Sized + Serialize + Short + Wide,
     <T as Short>::Error: Error + Send + Sync,
@@ -108,15 +377,15 @@ where
     type Error = Box<dyn Error + Send + Sync>;
 
     #[tracing::instrument(skip(self))]
-    fn short(&self) -> Result<String, Self::Error> {
+    fn short(&self, options: &Options) -> Result<String, Self::Error> {
         Ok(self
             .inner
-            .short()
+            .short(options)
             .map_err(|err| format!("could not serialize in short format, {}", err))?)
     }
 }
 
-impl<T> Wide for Formatter<T>
+impl<'a, T> Wide for Formatter<'a, T>
 where
     T: Sized + Serialize + Short + Wide,
     <T as Wide>::Error: Error + Send + Sync,
@@ -124,10 +393,387 @@ where
     type Error = Box<dyn Error + Send + Sync>;
 
     #[tracing::instrument(skip(self))]
-    fn wide(&self) -> Result<String, Self::Error> {
+    fn wide(&self, options: &Options) -> Result<String, Self::Error> {
         Ok(self
             .inner
-            .wide()
+            .wide(options)
             .map_err(|err| format!("could not serialize in wide format, {}", err))?)
     }
 }
+
+impl<'a, T> TableJson for Formatter<'a, T>
+where
+    T: Sized + Serialize + Short + Wide,
+    <T as Short>::Error: fmt::Display,
+{
+    type Error = Box<dyn Error + Send + Sync>;
+
+    #[tracing::instrument(skip(self))]
+    fn table_json(&self) -> Result<String, Self::Error> {
+        let table = self
+            .inner
+            .short(self.options)
+            .map_err(|err| format!("could not serialize in short format, {}", err))?;
+
+        Ok(format!("{}\n{}", table, self.json()?))
+    }
+}
+
+impl<'a, I> Ndjson for Formatter<'a, Vec<I>>
+where
+    I: Serialize,
+    Vec<I>: Sized + Serialize + Short + Wide,
+{
+    type Error = Box<dyn Error + Send + Sync>;
+
+    #[tracing::instrument(skip(self))]
+    fn ndjson(&self) -> Result<String, Self::Error> {
+        if let Some(select) = &self.options.select {
+            return select.apply(&self.inner);
+        }
+
+        let mut lines = vec![];
+        for item in &self.inner {
+            lines.push(
+                serde_json::to_string(item)
+                    .map_err(|err| format!("could not serialize in ndjson, {}", err))?,
+            );
+        }
+
+        Ok(lines.join("\n"))
+    }
+}
+
+/// Returns an error if the listing has no result and it has been asked to fail
+/// in that case
+#[tracing::instrument]
+pub fn check_empty(count: usize, noun: &str, options: &Options) -> Result<(), Empty> {
+    if 0 == count && options.fail_on_empty {
+        return Err(Empty::new(noun));
+    }
+
+    Ok(())
+}
+
+/// Returns the error of an output format which is not supported by a command
+#[tracing::instrument]
+pub fn unsupported(output: &Kind) -> Box<dyn Error + Send + Sync> {
+    format!("output '{}' is not supported by this command", output).into()
+}
+
+/// Render a sample of the metric in the prometheus text format, label values
+/// are escaped as expected by the format
+#[tracing::instrument]
+pub fn sample(name: &str, labels: &[(&str, &str)], value: f64) -> String {
+    let labels: Vec<String> = labels
+        .iter()
+        .map(|(label, value)| {
+            let value = value
+                .replace('\\', "\\\\")
+                .replace('"', "\\\"")
+                .replace('\n', "\\n");
+
+            format!("{}=\"{}\"", label, value)
+        })
+        .collect();
+
+    format!("{}{{{}}} {}", name, labels.join(","), value)
+}
+
+/// Returns the line prefixed by '+', in green if colors are enabled
+#[tracing::instrument]
+pub fn added(line: &str, options: &Options) -> String {
+    paint(&format!("+ {}", line), color::GREEN, options)
+}
+
+/// Returns the line prefixed by '-', in red if colors are enabled
+#[tracing::instrument]
+pub fn removed(line: &str, options: &Options) -> String {
+    paint(&format!("- {}", line), color::RED, options)
+}
+
+/// Returns the text written in the given color by the terminal, as is if colors
+/// are disabled or the terminal is unknown
+#[tracing::instrument]
+fn paint(text: &str, color: Color, options: &Options) -> String {
+    if !options.colorize {
+        return text.to_string();
+    }
+
+    let mut terminal = match TerminfoTerminal::new(Vec::new()) {
+        Some(terminal) => terminal,
+        None => return text.to_string(),
+    };
+
+    if terminal.fg(color).is_err()
+        || write!(terminal, "{}", text).is_err()
+        || terminal.reset().is_err()
+    {
+        return text.to_string();
+    }
+
+    String::from_utf8_lossy(&terminal.into_inner()).into_owned()
+}
+
+/// Render the table, along with the style of its cells if colors are enabled
+/// and the terminal is known
+#[tracing::instrument(skip(table))]
+fn render(table: &Table, options: &Options) -> String {
+    if !options.colorize {
+        return format!("{}", table);
+    }
+
+    let mut terminal = match TerminfoTerminal::new(Vec::new()) {
+        Some(terminal) => terminal,
+        None => return format!("{}", table),
+    };
+
+    match table.print_term(&mut terminal) {
+        Ok(_) => String::from_utf8_lossy(&terminal.into_inner()).into_owned(),
+        Err(_) => format!("{}", table),
+    }
+}
+
+/// Returns the page of the listing to write, once items have been retrieved,
+/// filtered and sorted
+#[tracing::instrument(skip(items))]
+pub fn paginate<T>(items: Vec<T>, options: &Options) -> Vec<T> {
+    items
+        .into_iter()
+        .skip(options.offset)
+        .take(options.limit.unwrap_or(usize::MAX))
+        .collect()
+}
+
+/// Read the headers of the columns kept in tables from a file, one per line or
+/// separated by commas, empty lines and lines starting with '#' are skipped
+#[tracing::instrument]
+pub fn read_columns(path: &Path) -> Result<Vec<String>, Box<dyn Error + Send + Sync>> {
+    let content = fs::read_to_string(path)
+        .map_err(|err| format!("could not read file '{}', {}", path.display(), err))?;
+
+    Ok(content
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.starts_with('#'))
+        .flat_map(|line| line.split(','))
+        .map(|column| column.trim().to_string())
+        .filter(|column| !column.is_empty())
+        .collect())
+}
+
+/// Keep the cells of the columns whose header, compared without case, is one of
+/// the selected ones, in the order of the selection. Tables which have none of
+/// them are left as is, so that the same selection could be used across commands
+#[tracing::instrument(skip(rows))]
+fn select_columns(rows: Vec<Row>, columns: &[String]) -> Vec<Row> {
+    if columns.is_empty() {
+        return rows;
+    }
+
+    let indexes: Vec<usize> = match rows.first() {
+        Some(header) => columns
+            .iter()
+            .filter_map(|column| {
+                header
+                    .iter()
+                    .position(|cell| cell.get_content().eq_ignore_ascii_case(column))
+            })
+            .collect(),
+        None => return rows,
+    };
+
+    if indexes.is_empty() {
+        return rows;
+    }
+
+    rows.iter()
+        .map(|row| {
+            indexes
+                .iter()
+                .filter_map(|index| row.get_cell(*index).cloned())
+                .collect()
+        })
+        .collect()
+}
+
+/// Returns the row with the sums of the numeric columns of the rows, the first
+/// one being the header. A column is numeric if every cell which is neither
+/// empty nor '<none>' is a number, identifiers are left out as their sum is
+/// meaningless
+#[tracing::instrument(skip(rows))]
+fn footer(rows: &[Row]) -> Row {
+    let columns = rows.first().map(|header| header.len()).unwrap_or(0);
+    let mut cells = vec![];
+    for column in 0..columns {
+        let header = rows[0]
+            .get_cell(column)
+            .map(|cell| cell.get_content())
+            .unwrap_or_default();
+
+        let mut sum = Some(0.0);
+        let mut integers = true;
+        let mut count = 0;
+        for row in &rows[1..] {
+            let content = row
+                .get_cell(column)
+                .map(|cell| cell.get_content())
+                .unwrap_or_default();
+
+            if content.trim().is_empty() || "<none>" == content.trim() {
+                continue;
+            }
+
+            match content.trim().parse::<f64>() {
+                Ok(value) => {
+                    sum = sum.map(|sum| sum + value);
+                    integers &= !content.contains('.');
+                    count += 1;
+                }
+                Err(_) => sum = None,
+            }
+        }
+
+        let content = match sum {
+            _ if header.to_lowercase().contains("identifier") => String::new(),
+            Some(sum) if 0 != count && integers => format!("{}", sum),
+            Some(sum) if 0 != count => format!("{:.2}", sum),
+            _ => String::new(),
+        };
+
+        cells.push(Cell::new(&content));
+    }
+
+    if let Some(first) = cells.first_mut() {
+        if first.get_content().is_empty() {
+            *first = Cell::new("Total");
+        }
+    }
+
+    Row::new(cells)
+}
+
+/// Create a cell for the given boolean using the configured style, symbols are
+/// colored if colors are enabled
+#[tracing::instrument]
+pub fn boolean(value: bool, options: &Options) -> Cell {
+    if BoolStyle::YesNo == options.bool_style {
+        return Cell::new(if value { "yes" } else { "no" });
+    }
+
+    if BoolStyle::Symbol == options.bool_style {
+        let (color, symbol) = if value {
+            (color::GREEN, "✓")
+        } else {
+            (color::RED, "✗")
+        };
+
+        return Cell::new(symbol).with_style(Attr::ForegroundColor(color));
+    }
+
+    Cell::new(&format!("{}", value))
+}
+
+/// Render the rows as a table using the configured style, the first row is the
+/// header of the table
+#[tracing::instrument(skip(rows))]
+pub fn table(rows: Vec<Row>, options: &Options) -> String {
+    let mut rows = select_columns(rows, &options.columns);
+    if options.totals && rows.len() > 1 {
+        let footer = footer(&rows);
+        rows.push(footer);
+    }
+
+    let max_col_width = options.max_col_width.unwrap_or(0);
+    let rows: Vec<Row> = if 0 == max_col_width {
+        rows
+    } else {
+        rows.iter()
+            .map(|row| {
+                row.iter()
+                    .map(|cell| truncate(cell, max_col_width))
+                    .collect()
+            })
+            .collect()
+    };
+
+    if TableStyle::Compact == options.table_style {
+        let mut table = Table::init(rows);
+        table.set_format(*consts::FORMAT_CLEAN);
+        return render(&table, options);
+    }
+
+    if TableStyle::Markdown == options.table_style {
+        let mut rows = rows;
+        let titles = if rows.is_empty() {
+            Row::empty()
+        } else {
+            rows.remove(0)
+        };
+
+        let mut table = Table::init(rows);
+        table.set_titles(titles);
+        table.set_format(
+            FormatBuilder::new()
+                .column_separator('|')
+                .borders('|')
+                .separators(
+                    &[LinePosition::Title],
+                    LineSeparator::new('-', '|', '|', '|'),
+                )
+                .padding(1, 1)
+                .build(),
+        );
+
+        return render(&table, options);
+    }
+
+    render(&Table::init(rows), options)
+}
+
+/// Truncate the content of the cell with an ellipsis, cells which already fit
+/// are kept as is along with their style
+#[tracing::instrument]
+fn truncate(cell: &Cell, max_col_width: usize) -> Cell {
+    let content = cell.get_content();
+    if content.chars().count() <= max_col_width {
+        return cell.to_owned();
+    }
+
+    Cell::new(&ellipsis(&content, max_col_width))
+}
+
+/// Returns the content cut to the maximum number of characters, ended by an
+/// ellipsis if it does not fit
+#[tracing::instrument]
+fn ellipsis(content: &str, max_col_width: usize) -> String {
+    if 0 == max_col_width || content.chars().count() <= max_col_width {
+        return content.to_string();
+    }
+
+    let mut truncated: String = content
+        .chars()
+        .take(max_col_width.saturating_sub(1))
+        .collect();
+
+    truncated.push('…');
+    truncated
+}
+
+/// Create a cell for the given status, colored according to its meaning. Unknown
+/// statuses are kept uncolored. The status is truncated here, as the style of
+/// the cell would be lost by truncating it with the table.
+#[tracing::instrument]
+pub fn status(status: &str, options: &Options) -> Cell {
+    let cell = Cell::new(&ellipsis(status, options.max_col_width.unwrap_or(0)));
+    let color = match status.to_lowercase().as_str() {
+        "active" | "ok" | "running" | "ready" | "done" | "up" => color::GREEN,
+        "building" | "pending" | "creating" | "updating" | "rebooting" | "resizing" | "init"
+        | "todo" | "doing" | "maintenance" => color::YELLOW,
+        "error" | "deleted" | "deleting" | "suspended" | "hacked" | "cancelled"
+        | "customererror" | "ovherror" | "down" => color::RED,
+        _ => return cell,
+    };
+
+    cell.with_style(Attr::ForegroundColor(color))
+}
@@ -0,0 +1,77 @@
+//! # Ip module
+//!
+//! This module provide controller to handle ip handlers
+use std::convert::TryFrom;
+use std::time::Duration;
+
+use crate::cmd::fmt::{self, Formatter, Json, Kind, Ndjson, Short, TableJson, Wide, Yaml};
+use crate::cmd::report;
+use crate::cmd::ExecContext;
+use crate::ovh::ip::{self, IpMove, IpTask};
+use crate::ovh::task::{self, Task};
+use crate::ovh::{Client, ClientConfiguration};
+use crate::util::types::Result;
+
+/// Interval between two retrievals of a task
+const TASK_POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+#[tracing::instrument]
+pub async fn move_ip(
+    ctx: &ExecContext,
+    ip: &str,
+    to: &str,
+    nexthop: &Option<String>,
+    no_wait: bool,
+    output: &Kind,
+) -> Result<()> {
+    let client = Client::from(
+        ClientConfiguration::try_from(ctx.config.to_owned()).map_err(|err| {
+            format!(
+                "could not create ovh client configuration from the current configuration, {}",
+                err
+            )
+        })?,
+    );
+
+    let opts = IpMove {
+        to: to.to_string(),
+        nexthop: nexthop.to_owned(),
+    };
+
+    let mut task = ip::move_ip(&client, ip, &opts).await?;
+    if !no_wait && !task.is_finished() {
+        task = task::poll_task(
+            &client,
+            &ip::task_path(ip, &task.task_id),
+            IpTask::is_finished,
+            None,
+            TASK_POLL_INTERVAL,
+            &mut report::spinner(ctx.only_errors),
+        )
+        .await?;
+    }
+
+    let tasks = vec![task.to_owned()];
+    let formatter = Formatter::new(tasks.to_owned(), &ctx.render);
+    let o = match output {
+        Kind::Short => tasks.short(&ctx.render)?,
+        Kind::Wide => tasks.wide(&ctx.render)?,
+        Kind::Json => formatter.json()?,
+        Kind::Yaml => formatter.yaml()?,
+        Kind::TableJson => formatter.table_json()?,
+        Kind::Ndjson => formatter.ndjson()?,
+        Kind::Prometheus => return Err(fmt::unsupported(output)),
+    };
+
+    println!("{}", o);
+
+    if task.is_finished() && !task.is_done() {
+        return Err(format!(
+            "could not move ip '{}' to service '{}', task '{}' ended with status '{}'",
+            ip, to, task.task_id, task.status
+        )
+        .into());
+    }
+
+    Ok(())
+}
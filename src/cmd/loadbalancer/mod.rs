@@ -2,30 +2,36 @@
 //!
 //! This module provide handlers to manage load balancer
 use std::convert::TryFrom;
-use std::sync::Arc;
 
-use crate::cfg::Configuration;
-use crate::cmd::fmt::{Formatter, Json, Kind, Short, Wide, Yaml};
+use crate::cmd::fmt::{self, Formatter, Json, Kind, Ndjson, Short, TableJson, Wide, Yaml};
+use crate::cmd::ExecContext;
 use crate::ovh::cloud::loadbalancer;
 use crate::ovh::{Client, ClientConfiguration};
-use crate::util::types;
+use crate::util::{prompt, types};
 
 #[tracing::instrument]
-pub async fn list(config: Arc<Configuration>, output: &Kind, tenant: &str) -> types::Result<()> {
-    let client = Client::from(ClientConfiguration::try_from(config).map_err(|err| {
-        format!(
-            "could not create internal client configuration from the current configuration, {}",
-            err
-        )
-    })?);
+pub async fn list(ctx: &ExecContext, output: &Kind, tenant: &str) -> types::Result<()> {
+    let client = Client::from(
+        ClientConfiguration::try_from(ctx.config.to_owned()).map_err(|err| {
+            format!(
+                "could not create internal client configuration from the current configuration, {}",
+                err
+            )
+        })?,
+    );
 
     let loadbalancers = loadbalancer::list(&client, tenant).await?;
-    let formatter = Formatter::from(loadbalancers.to_owned());
+    fmt::check_empty(loadbalancers.len(), "load balancer", &ctx.render)?;
+    let loadbalancers = fmt::paginate(loadbalancers, &ctx.render);
+    let formatter = Formatter::new(loadbalancers.to_owned(), &ctx.render);
     let o = match output {
-        Kind::Short => loadbalancers.short()?,
-        Kind::Wide => loadbalancers.wide()?,
+        Kind::Short => loadbalancers.short(&ctx.render)?,
+        Kind::Wide => loadbalancers.wide(&ctx.render)?,
         Kind::Json => formatter.json()?,
         Kind::Yaml => formatter.yaml()?,
+        Kind::TableJson => formatter.table_json()?,
+        Kind::Ndjson => formatter.ndjson()?,
+        Kind::Prometheus => return Err(fmt::unsupported(output)),
     };
 
     println!("{}", o);
@@ -35,25 +41,38 @@ pub async fn list(config: Arc<Configuration>, output: &Kind, tenant: &str) -> ty
 
 #[tracing::instrument]
 pub async fn create(
-    config: Arc<Configuration>,
+    ctx: &ExecContext,
     output: &Kind,
     tenant: &str,
     region: &str,
+    name: &Option<String>,
+    description: &Option<String>,
 ) -> types::Result<()> {
-    let client = Client::from(ClientConfiguration::try_from(config).map_err(|err| {
-        format!(
-            "could not create internal client configuration from the current configuration, {}",
-            err
-        )
-    })?);
-
-    let loadbalancers = vec![loadbalancer::create(&client, tenant, &region.into()).await?];
-    let formatter = Formatter::from(loadbalancers.to_owned());
+    let client = Client::from(
+        ClientConfiguration::try_from(ctx.config.to_owned()).map_err(|err| {
+            format!(
+                "could not create internal client configuration from the current configuration, {}",
+                err
+            )
+        })?,
+    );
+
+    let opts = loadbalancer::LoadBalancerCreation {
+        name: name.to_owned(),
+        description: description.to_owned(),
+        ..loadbalancer::LoadBalancerCreation::from(region)
+    };
+
+    let loadbalancers = vec![loadbalancer::create(&client, tenant, &opts).await?];
+    let formatter = Formatter::new(loadbalancers.to_owned(), &ctx.render);
     let o = match output {
-        Kind::Short => loadbalancers.short()?,
-        Kind::Wide => loadbalancers.wide()?,
+        Kind::Short => loadbalancers.short(&ctx.render)?,
+        Kind::Wide => loadbalancers.wide(&ctx.render)?,
         Kind::Json => formatter.json()?,
         Kind::Yaml => formatter.yaml()?,
+        Kind::TableJson => formatter.table_json()?,
+        Kind::Ndjson => formatter.ndjson()?,
+        Kind::Prometheus => return Err(fmt::unsupported(output)),
     };
 
     println!("{}", o);
@@ -62,28 +81,152 @@ pub async fn create(
 }
 
 #[tracing::instrument]
-pub async fn delete(
-    config: Arc<Configuration>,
+pub async fn update(
+    ctx: &ExecContext,
     output: &Kind,
     tenant: &str,
     id: &str,
+    name: &Option<String>,
+    description: &Option<String>,
 ) -> types::Result<()> {
-    let client = Client::from(ClientConfiguration::try_from(config).map_err(|err| {
-        format!(
-            "could not create internal client configuration from the current configuration, {}",
-            err
-        )
-    })?);
+    let client = Client::from(
+        ClientConfiguration::try_from(ctx.config.to_owned()).map_err(|err| {
+            format!(
+                "could not create internal client configuration from the current configuration, {}",
+                err
+            )
+        })?,
+    );
+
+    let loadbalancers = vec![loadbalancer::update(&client, tenant, id, name, description).await?];
+    let formatter = Formatter::new(loadbalancers.to_owned(), &ctx.render);
+    let o = match output {
+        Kind::Short => loadbalancers.short(&ctx.render)?,
+        Kind::Wide => loadbalancers.wide(&ctx.render)?,
+        Kind::Json => formatter.json()?,
+        Kind::Yaml => formatter.yaml()?,
+        Kind::TableJson => formatter.table_json()?,
+        Kind::Ndjson => formatter.ndjson()?,
+        Kind::Prometheus => return Err(fmt::unsupported(output)),
+    };
+
+    println!("{}", o);
+
+    Ok(())
+}
+
+#[tracing::instrument]
+pub async fn delete(ctx: &ExecContext, output: &Kind, tenant: &str, id: &str) -> types::Result<()> {
+    let client = Client::from(
+        ClientConfiguration::try_from(ctx.config.to_owned()).map_err(|err| {
+            format!(
+                "could not create internal client configuration from the current configuration, {}",
+                err
+            )
+        })?,
+    );
+
+    let message = format!("Deleting load balancer '{}' of tenant '{}'", id, tenant);
+    if !prompt::confirm_destructive(ctx.confirm_destructive, &message, id)? {
+        return Err(format!("deletion of load balancer '{}' has been aborted", id).into());
+    }
 
     loadbalancer::delete(&client, tenant, id).await?;
 
     let loadbalancers = loadbalancer::list(&client, tenant).await?;
-    let formatter = Formatter::from(loadbalancers.to_owned());
+    let formatter = Formatter::new(loadbalancers.to_owned(), &ctx.render);
+    let o = match output {
+        Kind::Short => loadbalancers.short(&ctx.render)?,
+        Kind::Wide => loadbalancers.wide(&ctx.render)?,
+        Kind::Json => formatter.json()?,
+        Kind::Yaml => formatter.yaml()?,
+        Kind::TableJson => formatter.table_json()?,
+        Kind::Ndjson => formatter.ndjson()?,
+        Kind::Prometheus => return Err(fmt::unsupported(output)),
+    };
+
+    println!("{}", o);
+
+    Ok(())
+}
+
+#[tracing::instrument]
+pub async fn list_pools(
+    ctx: &ExecContext,
+    output: &Kind,
+    tenant: &str,
+    id: &str,
+) -> types::Result<()> {
+    let client = Client::from(
+        ClientConfiguration::try_from(ctx.config.to_owned()).map_err(|err| {
+            format!(
+                "could not create internal client configuration from the current configuration, {}",
+                err
+            )
+        })?,
+    );
+
+    let pools = loadbalancer::list_pools(&client, tenant, id).await?;
+    fmt::check_empty(pools.len(), "pool", &ctx.render)?;
+    let pools = fmt::paginate(pools, &ctx.render);
+    let formatter = Formatter::new(pools.to_owned(), &ctx.render);
+    let o = match output {
+        Kind::Short => pools.short(&ctx.render)?,
+        Kind::Wide => pools.wide(&ctx.render)?,
+        Kind::Json => formatter.json()?,
+        Kind::Yaml => formatter.yaml()?,
+        Kind::TableJson => formatter.table_json()?,
+        Kind::Ndjson => formatter.ndjson()?,
+        Kind::Prometheus => return Err(fmt::unsupported(output)),
+    };
+
+    println!("{}", o);
+
+    Ok(())
+}
+
+#[tracing::instrument]
+pub async fn list_members(
+    ctx: &ExecContext,
+    output: &Kind,
+    tenant: &str,
+    id: &str,
+    pool: &Option<String>,
+) -> types::Result<()> {
+    let client = Client::from(
+        ClientConfiguration::try_from(ctx.config.to_owned()).map_err(|err| {
+            format!(
+                "could not create internal client configuration from the current configuration, {}",
+                err
+            )
+        })?,
+    );
+
+    let pools = match pool {
+        Some(pool) => vec![pool.to_owned()],
+        None => loadbalancer::list_pools(&client, tenant, id)
+            .await?
+            .into_iter()
+            .map(|pool| pool.id)
+            .collect(),
+    };
+
+    let mut members = vec![];
+    for pool in pools {
+        members.append(&mut loadbalancer::list_members(&client, tenant, id, &pool).await?);
+    }
+
+    fmt::check_empty(members.len(), "member", &ctx.render)?;
+    let members = fmt::paginate(members, &ctx.render);
+    let formatter = Formatter::new(members.to_owned(), &ctx.render);
     let o = match output {
-        Kind::Short => loadbalancers.short()?,
-        Kind::Wide => loadbalancers.wide()?,
+        Kind::Short => members.short(&ctx.render)?,
+        Kind::Wide => members.wide(&ctx.render)?,
         Kind::Json => formatter.json()?,
         Kind::Yaml => formatter.yaml()?,
+        Kind::TableJson => formatter.table_json()?,
+        Kind::Ndjson => formatter.ndjson()?,
+        Kind::Prometheus => return Err(fmt::unsupported(output)),
     };
 
     println!("{}", o);
@@ -1,22 +1,35 @@
 //! # Command interface
 //!
 //! This module provide all stuffs to interact with the command line
-use std::{convert::TryFrom, error::Error, path::PathBuf, sync::Arc};
+use std::{error::Error, io, net::SocketAddr, path::PathBuf, str::FromStr, sync::Arc};
 
 use async_trait::async_trait;
 use clap::{ArgAction, Parser, Subcommand};
+use glob::Pattern;
 use ipnetwork::IpNetwork;
+use is_terminal::IsTerminal;
 
-use crate::cfg::Configuration;
+use crate::cfg::{self, Configuration, Ovh};
 use crate::cmd::dedicated::server;
-use crate::cmd::fmt::Kind;
-use crate::ovh::{auth, Client, ClientConfiguration, UnauthenticatedRestClient};
+use crate::cmd::domain::MailProvider;
+use crate::cmd::fmt::{BoolStyle, FormatVersion, Kind, Select, TableStyle};
+use crate::ovh;
+use crate::ovh::cloud::{InstanceCreation, Period};
+use crate::util::{prompt, time};
 
+pub mod auth;
 pub mod cloud;
 pub mod dedicated;
+pub mod doctor;
 pub mod domain;
 pub mod fmt;
+pub mod ip;
 pub mod loadbalancer;
+pub mod ping;
+pub mod report;
+pub mod storage;
+pub mod update;
+pub mod volume;
 
 /// Manage domain zone
 #[derive(Subcommand, Clone, Debug)]
@@ -24,9 +37,34 @@ pub enum DomainZone {
     /// List domain zone
     #[clap(name = "list", alias = "l")]
     List {
-        /// Choose the output format
-        #[clap(short = 'o', long = "output", default_value = "short")]
-        output: Kind,
+        /// Choose the output format, 'short' by default
+        #[clap(short = 'o', long = "output")]
+        output: Option<Kind>,
+
+        /// Retrieve the record count and the serial of each zone, which are
+        /// written in the wide output, it costs two requests per zone
+        #[clap(long = "with-counts")]
+        with_counts: bool,
+    },
+
+    /// Export domain zone in the bind format
+    #[clap(name = "export", alias = "e")]
+    Export {
+        /// Zone to export
+        #[clap(name = "zone")]
+        zone: String,
+    },
+
+    /// Export every domain zone in the bind format into a directory
+    #[clap(name = "export-all")]
+    ExportAll {
+        /// Directory in which zones are written as '<zone>.zone'
+        #[clap(short = 'd', long = "dir")]
+        dir: PathBuf,
+
+        /// Suffix file names with the current timestamp
+        #[clap(short = 't', long = "timestamp")]
+        timestamp: bool,
     },
 }
 
@@ -35,9 +73,17 @@ impl Execute for DomainZone {
     type Error = Box<dyn Error + Send + Sync>;
 
     #[tracing::instrument]
-    async fn execute(&self, config: Arc<Configuration>) -> Result<(), Self::Error> {
+    async fn execute(&self, ctx: &ExecContext) -> Result<(), Self::Error> {
+        let config = ctx.config.to_owned();
         match self {
-            Self::List { output } => domain::list_zones(config, output).await,
+            Self::List {
+                output,
+                with_counts,
+            } => domain::list_zones(ctx, &ctx.output(output, Kind::Short)?, *with_counts).await,
+            Self::Export { zone } => domain::export_zone(config, zone).await,
+            Self::ExportAll { dir, timestamp } => {
+                domain::export_all_zones(ctx, dir, *timestamp).await
+            }
         }
     }
 }
@@ -52,25 +98,42 @@ pub enum DomainRecord {
         #[clap(name = "zone")]
         zone: String,
 
-        /// Choose the output format
-        #[clap(short = 'o', long = "output", default_value = "short")]
-        output: Kind,
+        /// Choose the output format, 'short' by default
+        #[clap(short = 'o', long = "output")]
+        output: Option<Kind>,
+
+        #[clap(flatten)]
+        resolution: domain::Resolution,
     },
 
     /// Synchronise domain records
     #[clap(name = "sync", alias = "s")]
     Sync {
         /// Zone that contains domain records
-        #[clap(name = "zone")]
-        zone: String,
+        #[clap(name = "zone", required_unless_present = "all_zones")]
+        zone: Option<String>,
 
-        /// Choose the output format
-        #[clap(short = 'o', long = "output", default_value = "short")]
-        output: Kind,
+        /// Synchronise every zone with the instances whose name ends with it
+        #[clap(long = "all-zones", conflicts_with = "zone")]
+        all_zones: bool,
+
+        /// Choose the output format, 'short' by default
+        #[clap(short = 'o', long = "output")]
+        output: Option<Kind>,
 
         /// List of cidr to discard from the sync
         #[clap(short = 'n', long = "not-in-cidrs")]
         not_in_cidrs: Vec<IpNetwork>,
+
+        /// Export the zone in '<zone>-<timestamp>.zone' in the given directory
+        /// before applying changes, the sync is aborted if it fails
+        #[clap(long = "backup-dir")]
+        backup_dir: Option<PathBuf>,
+
+        /// Print the records which would be deleted and created without
+        /// applying any change, e.g. '-o json' to review them
+        #[clap(long = "diff-only", conflicts_with_all = &["all_zones", "backup_dir"])]
+        diff_only: bool,
     },
 
     /// Delete domain record
@@ -83,14 +146,193 @@ pub enum DomainRecord {
         /// Zone that contains domain records
         #[clap(name = "record")]
         id: i64,
+
+        #[clap(flatten)]
+        propagation: domain::Propagation,
+    },
+
+    /// Create a domain record
+    #[clap(name = "create", alias = "c")]
+    Create {
+        /// Zone that contains domain records
+        #[clap(name = "zone")]
+        zone: String,
+
+        /// Type of the record, e.g. A, CNAME or TXT
+        #[clap(name = "type")]
+        field_type: String,
+
+        /// Sub domain of the record
+        #[clap(name = "subdomain")]
+        sub_domain: String,
+
+        /// Target of the record
+        #[clap(short = 't', long = "target", conflicts_with = "target_file")]
+        target: Option<String>,
+
+        /// File from which the target of the record is read
+        #[clap(short = 'f', long = "target-file")]
+        target_file: Option<PathBuf>,
+
+        /// Time to live of the record, in seconds
+        #[clap(long = "ttl")]
+        ttl: Option<i64>,
+
+        #[clap(flatten)]
+        propagation: domain::Propagation,
+
+        /// Choose the output format, 'short' by default
+        #[clap(short = 'o', long = "output")]
+        output: Option<Kind>,
+    },
+
+    /// Create a TXT record, splitting long values in segments of 255 bytes
+    #[clap(name = "add-txt")]
+    AddTxt {
+        /// Zone that contains domain records
+        #[clap(name = "zone")]
+        zone: String,
+
+        /// Sub domain of the record
+        #[clap(name = "subdomain")]
+        sub_domain: String,
+
+        /// Value of the record, without quotes
+        #[clap(name = "value", conflicts_with = "target_file")]
+        value: Option<String>,
+
+        /// File from which the value of the record is read
+        #[clap(short = 'f', long = "target-file")]
+        target_file: Option<PathBuf>,
+
+        #[clap(flatten)]
+        propagation: domain::Propagation,
+
+        /// Choose the output format, 'short' by default
+        #[clap(short = 'o', long = "output")]
+        output: Option<Kind>,
+    },
+
+    /// Create a SPF record
+    #[clap(name = "add-spf")]
+    AddSpf {
+        /// Zone that contains domain records
+        #[clap(name = "zone")]
+        zone: String,
+
+        /// Sub domain of the record, the zone apex by default
+        #[clap(short = 's', long = "subdomain", default_value = "")]
+        sub_domain: String,
+
+        /// Domain whose spf policy is included
+        #[clap(short = 'i', long = "include")]
+        includes: Vec<String>,
+
+        /// Network allowed to send mails
+        #[clap(short = 'n', long = "cidr")]
+        cidrs: Vec<IpNetwork>,
+
+        /// Policy for senders not matching any mechanism
+        #[clap(short = 'a', long = "all", default_value = "~all")]
+        all: String,
+
+        #[clap(flatten)]
+        propagation: domain::Propagation,
+
+        /// Choose the output format, 'short' by default
+        #[clap(short = 'o', long = "output")]
+        output: Option<Kind>,
+    },
+
+    /// Create a DKIM record
+    #[clap(name = "add-dkim")]
+    AddDkim {
+        /// Zone that contains domain records
+        #[clap(name = "zone")]
+        zone: String,
+
+        /// Selector of the dkim key
+        #[clap(name = "selector")]
+        selector: String,
+
+        /// Public key encoded in base64
+        #[clap(name = "public-key", conflicts_with = "target_file")]
+        public_key: Option<String>,
+
+        /// File from which the public key is read
+        #[clap(short = 'f', long = "target-file")]
+        target_file: Option<PathBuf>,
+
+        /// Type of the dkim key
+        #[clap(short = 'k', long = "key-type", default_value = "rsa")]
+        key_type: String,
+
+        #[clap(flatten)]
+        propagation: domain::Propagation,
+
+        /// Choose the output format, 'short' by default
+        #[clap(short = 'o', long = "output")]
+        output: Option<Kind>,
+    },
+
+    /// Find records pointing to the target across every zone
+    #[clap(name = "find", alias = "f")]
+    Find {
+        /// Target to look for, e.g. an ip address
+        #[clap(name = "target")]
+        target: String,
+
+        /// Only look for records of this type, e.g. 'A'
+        #[clap(short = 't', long = "type")]
+        field_type: Option<String>,
+
+        /// Match the whole target instead of a part of it
+        #[clap(short = 'e', long = "exact")]
+        exact: bool,
+
+        /// Choose the output format, 'short' by default
+        #[clap(short = 'o', long = "output")]
+        output: Option<Kind>,
+    },
+
+    /// Export records along with their zone, in a file which could be given to 'diff'
+    #[clap(name = "export")]
+    Export {
+        /// Zone that contains domain records
+        #[clap(name = "zone")]
+        zone: String,
+
+        /// Choose the output format, either 'json' or 'yaml', 'yaml' by default
+        #[clap(short = 'o', long = "output")]
+        output: Option<Kind>,
+    },
+
+    /// Show the changes needed to reach the records of a file, without applying them
+    #[clap(name = "diff")]
+    Diff {
+        /// Zone that contains domain records
+        #[clap(name = "zone")]
+        zone: String,
+
+        /// Yaml or json file listing the desired records
+        #[clap(name = "file")]
+        file: PathBuf,
+
+        /// Choose the output format, 'short' by default
+        #[clap(short = 'o', long = "output")]
+        output: Option<Kind>,
     },
 
     /// Refresh domain records
     #[clap(name = "refresh", alias = "r")]
     Refresh {
         /// Zone that contains domain records
-        #[clap(name = "zone")]
-        zone: String,
+        #[clap(name = "zone", required_unless_present = "all")]
+        zone: Option<String>,
+
+        /// Refresh every domain zone
+        #[clap(short = 'a', long = "all", conflicts_with = "zone")]
+        all: bool,
     },
 }
 
@@ -99,16 +341,163 @@ impl Execute for DomainRecord {
     type Error = Box<dyn Error + Send + Sync>;
 
     #[tracing::instrument]
-    async fn execute(&self, config: Arc<Configuration>) -> Result<(), Self::Error> {
+    async fn execute(&self, ctx: &ExecContext) -> Result<(), Self::Error> {
+        let config = ctx.config.to_owned();
         match self {
-            Self::List { zone, output } => domain::list_records(config, zone, output).await,
+            Self::List {
+                zone,
+                output,
+                resolution,
+            } => {
+                domain::list_records(ctx, zone, &ctx.output(output, Kind::Short)?, resolution).await
+            }
             Self::Sync {
                 zone,
+                all_zones,
                 output,
                 not_in_cidrs,
-            } => domain::sync_records(config, zone, output, not_in_cidrs).await,
-            Self::Refresh { zone } => domain::refresh_records(config, zone).await,
-            Self::Delete { zone, id } => domain::delete_record(config, zone, id).await,
+                backup_dir,
+                diff_only,
+            } => match (zone, all_zones) {
+                (Some(zone), false) => {
+                    domain::sync_records(
+                        ctx,
+                        zone,
+                        &ctx.output(output, Kind::Short)?,
+                        not_in_cidrs,
+                        backup_dir,
+                        *diff_only,
+                    )
+                    .await
+                }
+                _ => {
+                    domain::sync_all_zones(
+                        ctx,
+                        &ctx.output(output, Kind::Short)?,
+                        not_in_cidrs,
+                        backup_dir,
+                    )
+                    .await
+                }
+            },
+            Self::Create {
+                zone,
+                field_type,
+                sub_domain,
+                target,
+                target_file,
+                ttl,
+                propagation,
+                output,
+            } => {
+                let target = domain::read_target(target, target_file)?;
+                let record = domain::create_record(
+                    ctx,
+                    zone,
+                    field_type,
+                    sub_domain,
+                    &target,
+                    ttl,
+                    &ctx.output(output, Kind::Short)?,
+                )
+                .await?;
+
+                domain::wait_propagation(&record, propagation, false).await
+            }
+            Self::AddTxt {
+                zone,
+                sub_domain,
+                value,
+                target_file,
+                propagation,
+                output,
+            } => {
+                let value = domain::read_target(value, target_file)?;
+                let record = domain::add_txt(
+                    ctx,
+                    zone,
+                    sub_domain,
+                    &value,
+                    &ctx.output(output, Kind::Short)?,
+                )
+                .await?;
+
+                domain::wait_propagation(&record, propagation, false).await
+            }
+            Self::AddSpf {
+                zone,
+                sub_domain,
+                includes,
+                cidrs,
+                all,
+                propagation,
+                output,
+            } => {
+                let record = domain::add_spf(
+                    ctx,
+                    zone,
+                    sub_domain,
+                    includes,
+                    cidrs,
+                    all,
+                    &ctx.output(output, Kind::Short)?,
+                )
+                .await?;
+
+                domain::wait_propagation(&record, propagation, false).await
+            }
+            Self::AddDkim {
+                zone,
+                selector,
+                public_key,
+                target_file,
+                key_type,
+                propagation,
+                output,
+            } => {
+                let public_key = domain::read_target(public_key, target_file)?;
+                let record = domain::add_dkim(
+                    ctx,
+                    zone,
+                    selector,
+                    key_type,
+                    &public_key,
+                    &ctx.output(output, Kind::Short)?,
+                )
+                .await?;
+
+                domain::wait_propagation(&record, propagation, false).await
+            }
+            Self::Find {
+                target,
+                field_type,
+                exact,
+                output,
+            } => {
+                domain::find_records(
+                    ctx,
+                    target,
+                    field_type,
+                    *exact,
+                    &ctx.output(output, Kind::Short)?,
+                )
+                .await
+            }
+            Self::Export { zone, output } => {
+                domain::export_records(config, zone, &ctx.output(output, Kind::Yaml)?).await
+            }
+            Self::Diff { zone, file, output } => {
+                domain::diff_records(ctx, zone, file, &ctx.output(output, Kind::Short)?).await
+            }
+            Self::Refresh {
+                zone: Some(zone), ..
+            } => domain::refresh_records(config, zone).await,
+            Self::Refresh { zone: None, .. } => domain::refresh_all_zones(ctx).await,
+            Self::Delete {
+                zone,
+                id,
+                propagation,
+            } => domain::delete_record(ctx, zone, id, propagation).await,
         }
     }
 }
@@ -123,6 +512,26 @@ pub enum Domain {
     /// Manage domain records
     #[clap(name = "record", alias = "r", subcommand)]
     Record(DomainRecord),
+
+    /// Set up the records needed to receive and send mails with a provider
+    #[clap(name = "email-setup")]
+    EmailSetup {
+        /// Zone that contains domain records
+        #[clap(name = "zone")]
+        zone: String,
+
+        /// Mail provider, one of 'ovh', 'google' or 'o365'
+        #[clap(short = 'p', long = "provider")]
+        provider: MailProvider,
+
+        /// Only show the records that would be created
+        #[clap(long = "dry-run")]
+        dry_run: bool,
+
+        /// Choose the output format, 'short' by default
+        #[clap(short = 'o', long = "output")]
+        output: Option<Kind>,
+    },
 }
 
 #[async_trait]
@@ -130,10 +539,25 @@ impl Execute for Domain {
     type Error = Box<dyn Error + Send + Sync>;
 
     #[tracing::instrument]
-    async fn execute(&self, config: Arc<Configuration>) -> Result<(), Self::Error> {
+    async fn execute(&self, ctx: &ExecContext) -> Result<(), Self::Error> {
         match self {
-            Self::Zone(cmd) => cmd.execute(config).await,
-            Self::Record(cmd) => cmd.execute(config).await,
+            Self::Zone(cmd) => cmd.execute(ctx).await,
+            Self::Record(cmd) => cmd.execute(ctx).await,
+            Self::EmailSetup {
+                zone,
+                provider,
+                dry_run,
+                output,
+            } => {
+                domain::email_setup(
+                    ctx,
+                    zone,
+                    provider,
+                    *dry_run,
+                    &ctx.output(output, Kind::Short)?,
+                )
+                .await
+            }
         }
     }
 }
@@ -142,7 +566,101 @@ impl Execute for Domain {
 pub trait Execute {
     type Error;
 
-    async fn execute(&self, config: Arc<Configuration>) -> Result<(), Self::Error>;
+    async fn execute(&self, ctx: &ExecContext) -> Result<(), Self::Error>;
+}
+
+/// Context in which commands are executed, it carries the configuration along
+/// with the global options given on the command line
+#[derive(Clone, Debug)]
+pub struct ExecContext {
+    pub config: Arc<Configuration>,
+    /// Options of the rendering of the output, given to formatters
+    pub render: fmt::Options,
+    /// Only print failures and a final summary of bulk operations
+    pub only_errors: bool,
+    /// Output format of commands given by 'output.default' in configuration
+    pub output: Option<Kind>,
+    pub confirm_destructive: prompt::Policy,
+}
+
+impl ExecContext {
+    #[tracing::instrument(skip(config))]
+    pub fn new(
+        config: Arc<Configuration>,
+        args: &Args,
+    ) -> Result<Self, Box<dyn Error + Send + Sync>> {
+        // the flag takes precedence over the file, which takes precedence over
+        // the configuration
+        let columns = match (&args.columns[..], &args.fields_file) {
+            ([], Some(path)) => fmt::read_columns(path)
+                .map_err(|err| format!("could not read '--fields-file', {}", err))?,
+            ([], None) => config.output.fields.to_owned(),
+            (columns, _) => columns.to_vec(),
+        };
+
+        let output = match &config.output.default {
+            Some(output) => Some(
+                Kind::from_str(output)
+                    .map_err(|err| format!("could not parse 'output.default', {}", err))?,
+            ),
+            None => None,
+        };
+
+        let render = fmt::Options {
+            // escape sequences are not welcome in documents in which markdown tables are pasted,
+            // nor in files or pipes to which the output is redirected
+            colorize: !args.no_color
+                && TableStyle::Markdown != args.table_style
+                && io::stdout().is_terminal(),
+            table_style: args.table_style,
+            bool_style: args.bool_style,
+            max_col_width: args.max_col_width,
+            fail_on_empty: args.fail_on_empty,
+            limit: args.limit,
+            offset: args.offset,
+            human: args.human,
+            totals: args.totals,
+            yaml_multidoc: args.yaml_multidoc,
+            with_meta: args.with_meta,
+            select: args.select.to_owned(),
+            columns,
+        };
+
+        Ok(Self {
+            config,
+            render,
+            only_errors: args.only_errors,
+            output,
+            confirm_destructive: args.confirm_destructive,
+        })
+    }
+
+    /// Returns the output format of a command, the one given on the command line
+    /// takes precedence over json when a query is given, which takes precedence
+    /// over 'output.default' in configuration, then the default of the command,
+    /// fails if a query is given along with a format which is not a document
+    #[tracing::instrument(skip(self))]
+    pub fn output(
+        &self,
+        output: &Option<Kind>,
+        default: Kind,
+    ) -> Result<Kind, Box<dyn Error + Send + Sync>> {
+        match (output, &self.render.select, &self.output) {
+            (
+                Some(output @ (Kind::Short | Kind::Wide | Kind::TableJson | Kind::Prometheus)),
+                Some(_),
+                _,
+            ) => Err(format!(
+                "'--select' only applies to 'json', 'yaml' or 'ndjson' outputs, got '{}'",
+                output
+            )
+            .into()),
+            (Some(output), _, _) => Ok(output.to_owned()),
+            (None, Some(_), _) => Ok(Kind::Json),
+            (None, None, Some(output)) => Ok(output.to_owned()),
+            (None, None, None) => Ok(default),
+        }
+    }
 }
 
 /// Manage cloud loadbalancer
@@ -151,46 +669,114 @@ pub enum LoadBalancer {
     /// List load balancer in tenant
     #[clap(name = "list", alias = "l")]
     List {
-        /// Choose the output format
-        #[clap(short = 'o', long = "output", default_value = "short")]
-        output: Kind,
+        /// Choose the output format, 'short' by default
+        #[clap(short = 'o', long = "output")]
+        output: Option<Kind>,
 
-        /// Tenant on which we scope the search
+        /// Tenant on which we scope the search, the default one if omitted
         #[clap(name = "tenant")]
-        tenant: String,
+        tenant: Option<String>,
     },
 
     /// List load balancer in tenant
-    #[clap(name = "create", alias = "c")]
+    #[clap(name = "create", alias = "c", allow_missing_positional = true)]
     Create {
-        /// Choose the output format
-        #[clap(short = 'o', long = "output", default_value = "short")]
-        output: Kind,
+        /// Choose the output format, 'short' by default
+        #[clap(short = 'o', long = "output")]
+        output: Option<Kind>,
 
-        /// Tenant on which we scope the search
+        /// Tenant on which we scope the search, the default one if omitted
         #[clap(name = "tenant")]
-        tenant: String,
+        tenant: Option<String>,
 
         /// Tenant on which we scope the search
         #[clap(name = "region")]
         region: String,
+
+        /// Name of the load balancer
+        #[clap(short = 'n', long = "name")]
+        name: Option<String>,
+
+        /// Description of the load balancer
+        #[clap(short = 'd', long = "description")]
+        description: Option<String>,
+    },
+
+    /// Rename or describe a load balancer
+    #[clap(name = "update", alias = "u", allow_missing_positional = true)]
+    Update {
+        /// Choose the output format, 'short' by default
+        #[clap(short = 'o', long = "output")]
+        output: Option<Kind>,
+
+        /// Tenant on which we scope the search, the default one if omitted
+        #[clap(name = "tenant")]
+        tenant: Option<String>,
+
+        /// Identifier of the load balancer
+        #[clap(name = "id")]
+        id: String,
+
+        /// New name of the load balancer
+        #[clap(short = 'n', long = "name")]
+        name: Option<String>,
+
+        /// New description of the load balancer
+        #[clap(short = 'd', long = "description")]
+        description: Option<String>,
     },
 
     /// List load balancer in tenant
-    #[clap(name = "delete", alias = "d")]
+    #[clap(name = "delete", alias = "d", allow_missing_positional = true)]
     Delete {
-        /// Choose the output format
-        #[clap(short = 'o', long = "output", default_value = "short")]
-        output: Kind,
+        /// Choose the output format, 'short' by default
+        #[clap(short = 'o', long = "output")]
+        output: Option<Kind>,
 
-        /// Tenant on which we scope the search
+        /// Tenant on which we scope the search, the default one if omitted
         #[clap(name = "tenant")]
-        tenant: String,
+        tenant: Option<String>,
 
         /// Tenant on which we scope the search
         #[clap(name = "id")]
         id: String,
     },
+
+    /// List pools of a load balancer
+    #[clap(name = "pools", alias = "p", allow_missing_positional = true)]
+    Pools {
+        /// Choose the output format, 'short' by default
+        #[clap(short = 'o', long = "output")]
+        output: Option<Kind>,
+
+        /// Tenant on which we scope the search, the default one if omitted
+        #[clap(name = "tenant")]
+        tenant: Option<String>,
+
+        /// Identifier of the load balancer
+        #[clap(name = "id")]
+        id: String,
+    },
+
+    /// List members of load balancer pools
+    #[clap(name = "members", alias = "m", allow_missing_positional = true)]
+    Members {
+        /// Choose the output format, 'short' by default
+        #[clap(short = 'o', long = "output")]
+        output: Option<Kind>,
+
+        /// Tenant on which we scope the search, the default one if omitted
+        #[clap(name = "tenant")]
+        tenant: Option<String>,
+
+        /// Identifier of the load balancer
+        #[clap(name = "id")]
+        id: String,
+
+        /// Identifier of the pool, members of every pool if omitted
+        #[clap(short = 'p', long = "pool")]
+        pool: Option<String>,
+    },
 }
 
 #[async_trait]
@@ -198,16 +784,260 @@ impl Execute for LoadBalancer {
     type Error = Box<dyn Error + Send + Sync>;
 
     #[tracing::instrument]
-    async fn execute(&self, config: Arc<Configuration>) -> Result<(), Self::Error> {
+    async fn execute(&self, ctx: &ExecContext) -> Result<(), Self::Error> {
+        let config = ctx.config.to_owned();
         match self {
-            Self::List { output, tenant } => loadbalancer::list(config, output, tenant).await,
+            Self::List { output, tenant } => {
+                let tenant = config.tenant(tenant)?;
+                loadbalancer::list(ctx, &ctx.output(output, Kind::Short)?, &tenant).await
+            }
             Self::Create {
                 output,
                 tenant,
                 region,
-            } => loadbalancer::create(config, output, tenant, region).await,
+                name,
+                description,
+            } => {
+                let tenant = config.tenant(tenant)?;
+                loadbalancer::create(
+                    ctx,
+                    &ctx.output(output, Kind::Short)?,
+                    &tenant,
+                    region,
+                    name,
+                    description,
+                )
+                .await
+            }
+            Self::Update {
+                output,
+                tenant,
+                id,
+                name,
+                description,
+            } => {
+                let tenant = config.tenant(tenant)?;
+                loadbalancer::update(
+                    ctx,
+                    &ctx.output(output, Kind::Short)?,
+                    &tenant,
+                    id,
+                    name,
+                    description,
+                )
+                .await
+            }
             Self::Delete { output, tenant, id } => {
-                loadbalancer::delete(config, output, tenant, id).await
+                let tenant = config.tenant(tenant)?;
+                loadbalancer::delete(ctx, &ctx.output(output, Kind::Short)?, &tenant, id).await
+            }
+            Self::Pools { output, tenant, id } => {
+                let tenant = config.tenant(tenant)?;
+                loadbalancer::list_pools(ctx, &ctx.output(output, Kind::Short)?, &tenant, id).await
+            }
+            Self::Members {
+                output,
+                tenant,
+                id,
+                pool,
+            } => {
+                let tenant = config.tenant(tenant)?;
+                loadbalancer::list_members(
+                    ctx,
+                    &ctx.output(output, Kind::Short)?,
+                    &tenant,
+                    id,
+                    pool,
+                )
+                .await
+            }
+        }
+    }
+}
+
+/// Manage block storage volumes
+#[derive(Subcommand, Clone, Debug)]
+pub enum Volume {
+    /// List volumes in tenant
+    #[clap(name = "list", alias = "l")]
+    List {
+        /// Choose the output format, 'short' by default
+        #[clap(short = 'o', long = "output")]
+        output: Option<Kind>,
+
+        /// Tenant on which we scope the search, the default one if omitted
+        #[clap(name = "tenant")]
+        tenant: Option<String>,
+    },
+
+    /// Create a volume
+    #[clap(name = "create", alias = "c")]
+    Create {
+        /// Choose the output format, 'short' by default
+        #[clap(short = 'o', long = "output")]
+        output: Option<Kind>,
+
+        /// Tenant on which we create the volume, the default one if omitted
+        #[clap(name = "tenant")]
+        tenant: Option<String>,
+
+        /// Region in which the volume is created
+        #[clap(short = 'r', long = "region")]
+        region: String,
+
+        /// Size of the volume, in gigabytes
+        #[clap(short = 's', long = "size")]
+        size: i64,
+
+        /// Type of the volume, one of classic, high-speed or high-speed-gen2
+        #[clap(short = 't', long = "type", default_value = "classic")]
+        kind: String,
+
+        /// Name of the volume
+        #[clap(short = 'n', long = "name")]
+        name: Option<String>,
+
+        /// Description of the volume
+        #[clap(short = 'd', long = "description")]
+        description: Option<String>,
+    },
+
+    /// Attach a volume to an instance
+    #[clap(name = "attach", alias = "a", allow_missing_positional = true)]
+    Attach {
+        /// Choose the output format, 'short' by default
+        #[clap(short = 'o', long = "output")]
+        output: Option<Kind>,
+
+        /// Tenant on which we scope the search, the default one if omitted
+        #[clap(name = "tenant")]
+        tenant: Option<String>,
+
+        /// Identifier of the volume
+        #[clap(name = "id")]
+        id: String,
+
+        /// Identifier of the instance
+        #[clap(short = 'i', long = "instance")]
+        instance: String,
+    },
+
+    /// Detach a volume from an instance
+    #[clap(name = "detach", allow_missing_positional = true)]
+    Detach {
+        /// Choose the output format, 'short' by default
+        #[clap(short = 'o', long = "output")]
+        output: Option<Kind>,
+
+        /// Tenant on which we scope the search, the default one if omitted
+        #[clap(name = "tenant")]
+        tenant: Option<String>,
+
+        /// Identifier of the volume
+        #[clap(name = "id")]
+        id: String,
+
+        /// Identifier of the instance
+        #[clap(short = 'i', long = "instance")]
+        instance: String,
+    },
+}
+
+#[async_trait]
+impl Execute for Volume {
+    type Error = Box<dyn Error + Send + Sync>;
+
+    #[tracing::instrument]
+    async fn execute(&self, ctx: &ExecContext) -> Result<(), Self::Error> {
+        let config = ctx.config.to_owned();
+        match self {
+            Self::List { output, tenant } => {
+                let tenant = config.tenant(tenant)?;
+                volume::list(ctx, &ctx.output(output, Kind::Short)?, &tenant).await
+            }
+            Self::Create {
+                output,
+                tenant,
+                region,
+                size,
+                kind,
+                name,
+                description,
+            } => {
+                let tenant = config.tenant(tenant)?;
+                let opts = ovh::cloud::volume::VolumeCreation {
+                    region: region.to_owned(),
+                    size: *size,
+                    kind: kind.to_owned(),
+                    name: name.to_owned(),
+                    description: description.to_owned(),
+                };
+
+                volume::create(ctx, &ctx.output(output, Kind::Short)?, &tenant, &opts).await
+            }
+            Self::Attach {
+                output,
+                tenant,
+                id,
+                instance,
+            } => {
+                let tenant = config.tenant(tenant)?;
+                volume::attach(
+                    ctx,
+                    &ctx.output(output, Kind::Short)?,
+                    &tenant,
+                    id,
+                    instance,
+                )
+                .await
+            }
+            Self::Detach {
+                output,
+                tenant,
+                id,
+                instance,
+            } => {
+                let tenant = config.tenant(tenant)?;
+                volume::detach(
+                    ctx,
+                    &ctx.output(output, Kind::Short)?,
+                    &tenant,
+                    id,
+                    instance,
+                )
+                .await
+            }
+        }
+    }
+}
+
+/// Manage object storage containers
+#[derive(Subcommand, Clone, Debug)]
+pub enum Storage {
+    /// List object storage containers in tenant
+    #[clap(name = "list", alias = "l")]
+    List {
+        /// Choose the output format, 'short' by default
+        #[clap(short = 'o', long = "output")]
+        output: Option<Kind>,
+
+        /// Tenant on which we scope the search, the default one if omitted
+        #[clap(name = "tenant")]
+        tenant: Option<String>,
+    },
+}
+
+#[async_trait]
+impl Execute for Storage {
+    type Error = Box<dyn Error + Send + Sync>;
+
+    #[tracing::instrument]
+    async fn execute(&self, ctx: &ExecContext) -> Result<(), Self::Error> {
+        let config = ctx.config.to_owned();
+        match self {
+            Self::List { output, tenant } => {
+                let tenant = config.tenant(tenant)?;
+                storage::list(ctx, &ctx.output(output, Kind::Short)?, &tenant).await
             }
         }
     }
@@ -219,9 +1049,25 @@ pub enum Tenant {
     /// List tenants
     #[clap(name = "list", alias = "l")]
     List {
-        /// Choose the output format
-        #[clap(short = 'o', long = "output", default_value = "short")]
-        output: Kind,
+        /// Choose the output format, 'short' by default
+        #[clap(short = 'o', long = "output")]
+        output: Option<Kind>,
+    },
+
+    /// Show resource usage and estimated cost of a tenant
+    #[clap(name = "usage", alias = "u")]
+    Usage {
+        /// Choose the output format, 'short' by default
+        #[clap(short = 'o', long = "output")]
+        output: Option<Kind>,
+
+        /// Billing period, either 'current' or 'forecast'
+        #[clap(short = 'p', long = "period", default_value = "current")]
+        period: Period,
+
+        /// Tenant to inspect, the default one if omitted
+        #[clap(name = "tenant")]
+        tenant: Option<String>,
     },
 }
 
@@ -230,9 +1076,20 @@ impl Execute for Tenant {
     type Error = Box<dyn Error + Send + Sync>;
 
     #[tracing::instrument]
-    async fn execute(&self, config: Arc<Configuration>) -> Result<(), Self::Error> {
+    async fn execute(&self, ctx: &ExecContext) -> Result<(), Self::Error> {
+        let config = ctx.config.to_owned();
         match self {
-            Self::List { output } => cloud::list_tenants(config, output).await,
+            Self::List { output } => {
+                cloud::list_tenants(ctx, &ctx.output(output, Kind::Short)?).await
+            }
+            Self::Usage {
+                output,
+                period,
+                tenant,
+            } => {
+                let tenant = config.tenant(tenant)?;
+                cloud::get_usage(ctx, &tenant, period, &ctx.output(output, Kind::Short)?).await
+            }
         }
     }
 }
@@ -243,13 +1100,118 @@ pub enum Instance {
     /// List instances
     #[clap(name = "list", alias = "l")]
     List {
-        /// Tenant to use
+        /// Tenant to use, the default one if omitted
         #[clap(name = "tenant")]
-        tenant: String,
+        tenant: Option<String>,
+
+        /// Choose the output format, 'short' by default
+        #[clap(short = 'o', long = "output")]
+        output: Option<Kind>,
+
+        #[clap(flatten)]
+        range: time::Range,
 
-        /// Choose the output format
-        #[clap(short = 'o', long = "output", default_value = "short")]
-        output: Kind,
+        /// Only keep instances with the given status, e.g. 'active'
+        #[clap(long = "status")]
+        status: Option<String>,
+
+        /// Only keep instances whose name matches the glob, e.g. 'web-*', could
+        /// be repeated to keep instances matching any of them
+        #[clap(long = "name")]
+        names: Vec<Pattern>,
+
+        #[clap(flatten)]
+        listing: cloud::InstanceListing,
+    },
+
+    /// Create an instance
+    #[clap(name = "create", alias = "c", allow_missing_positional = true)]
+    Create {
+        /// Tenant to use, the default one if omitted
+        #[clap(name = "tenant")]
+        tenant: Option<String>,
+
+        /// Name of the instance
+        #[clap(name = "name")]
+        name: String,
+
+        /// Identifier of the flavor of the instance
+        #[clap(short = 'f', long = "flavor")]
+        flavor: String,
+
+        /// Identifier of the image to install on the instance
+        #[clap(short = 'i', long = "image")]
+        image: String,
+
+        /// Region in which the instance is created, e.g. 'GRA11'
+        #[clap(short = 'r', long = "region")]
+        region: String,
+
+        /// Identifier of the ssh key to install on the instance
+        #[clap(long = "ssh-key")]
+        ssh_key: Option<String>,
+
+        /// Fail before creating the instance if the quota of instances of the
+        /// region is reached
+        #[clap(long = "check-quota")]
+        check_quota: bool,
+
+        /// Wait until the instance is active, fails if it is in error
+        #[clap(short = 'w', long = "wait")]
+        wait: bool,
+
+        /// Number of seconds after which we stop waiting and fail
+        #[clap(long = "wait-timeout", default_value = "600")]
+        wait_timeout: u64,
+
+        /// Choose the output format, 'short' by default
+        #[clap(short = 'o', long = "output")]
+        output: Option<Kind>,
+    },
+
+    /// Rename an instance
+    #[clap(name = "rename", allow_missing_positional = true)]
+    Rename {
+        /// Tenant to use, the default one if omitted
+        #[clap(name = "tenant")]
+        tenant: Option<String>,
+
+        /// Identifier of the instance
+        #[clap(name = "id")]
+        id: String,
+
+        /// New name of the instance, given as option, so that the tenant could
+        /// be omitted
+        #[clap(short = 'n', long = "name")]
+        name: String,
+
+        /// Choose the output format, 'short' by default
+        #[clap(short = 'o', long = "output")]
+        output: Option<Kind>,
+    },
+
+    /// Wait for an instance to reach a status
+    #[clap(name = "watch", alias = "w", allow_missing_positional = true)]
+    Watch {
+        /// Tenant to use, the default one if omitted
+        #[clap(name = "tenant")]
+        tenant: Option<String>,
+
+        /// Identifier of the instance
+        #[clap(name = "id")]
+        id: String,
+
+        /// Status to wait for
+        #[clap(short = 'u', long = "until", default_value = "active")]
+        until: String,
+
+        /// Number of seconds after which we stop waiting and fail
+        #[clap(short = 't', long = "timeout", default_value = "600")]
+        timeout: u64,
+
+        /// Choose the output format, 'short' by default
+        #[clap(short = 'o', long = "output")]
+        output: Option<Kind>,
     },
 }
 
@@ -258,9 +1220,345 @@ impl Execute for Instance {
     type Error = Box<dyn Error + Send + Sync>;
 
     #[tracing::instrument]
-    async fn execute(&self, config: Arc<Configuration>) -> Result<(), Self::Error> {
+    async fn execute(&self, ctx: &ExecContext) -> Result<(), Self::Error> {
+        let config = ctx.config.to_owned();
+        match self {
+            Self::List {
+                tenant,
+                output,
+                range,
+                status,
+                names,
+                listing,
+            } => {
+                let tenant = config.tenant(tenant)?;
+                cloud::list_instances(
+                    ctx,
+                    &tenant,
+                    &ctx.output(output, Kind::Short)?,
+                    range,
+                    status,
+                    names,
+                    listing,
+                )
+                .await
+            }
+            Self::Create {
+                tenant,
+                name,
+                flavor,
+                image,
+                region,
+                ssh_key,
+                check_quota,
+                wait,
+                wait_timeout,
+                output,
+            } => {
+                let tenant = config.tenant(tenant)?;
+                let opts = InstanceCreation {
+                    name: name.to_owned(),
+                    flavor_id: flavor.to_owned(),
+                    image_id: image.to_owned(),
+                    region: region.to_owned(),
+                    ssh_key_id: ssh_key.to_owned(),
+                };
+
+                cloud::create_instance(
+                    ctx,
+                    &tenant,
+                    &opts,
+                    *check_quota,
+                    *wait,
+                    *wait_timeout,
+                    &ctx.output(output, Kind::Short)?,
+                )
+                .await
+            }
+            Self::Watch {
+                tenant,
+                id,
+                until,
+                timeout,
+                output,
+            } => {
+                let tenant = config.tenant(tenant)?;
+                cloud::watch_instance(
+                    ctx,
+                    &tenant,
+                    id,
+                    until,
+                    *timeout,
+                    &ctx.output(output, Kind::Short)?,
+                )
+                .await
+            }
+            Self::Rename {
+                tenant,
+                id,
+                name,
+                output,
+            } => {
+                let tenant = config.tenant(tenant)?;
+                cloud::rename_instance(ctx, &tenant, id, name, &ctx.output(output, Kind::Short)?)
+                    .await
+            }
+        }
+    }
+}
+
+/// Manage images
+#[derive(Subcommand, Clone, Debug)]
+pub enum Image {
+    /// List images
+    #[clap(name = "list", alias = "l")]
+    List {
+        /// Tenant to use, the default one if omitted
+        #[clap(name = "tenant")]
+        tenant: Option<String>,
+
+        /// Choose the output format, 'short' by default
+        #[clap(short = 'o', long = "output")]
+        output: Option<Kind>,
+
+        /// Only keep images of the given operating system type, 'linux' or 'windows'
+        #[clap(long = "os")]
+        os: Option<String>,
+
+        /// Only keep images built for the given architecture, as written in their name
+        #[clap(long = "arch")]
+        arch: Option<String>,
+
+        /// Only keep images whose name contains the given value
+        #[clap(long = "name")]
+        name: Option<String>,
+
+        #[clap(flatten)]
+        range: time::Range,
+    },
+}
+
+#[async_trait]
+impl Execute for Image {
+    type Error = Box<dyn Error + Send + Sync>;
+
+    #[tracing::instrument]
+    async fn execute(&self, ctx: &ExecContext) -> Result<(), Self::Error> {
+        let config = ctx.config.to_owned();
         match self {
-            Self::List { tenant, output } => cloud::list_instances(config, tenant, output).await,
+            Self::List {
+                tenant,
+                output,
+                os,
+                arch,
+                name,
+                range,
+            } => {
+                let tenant = config.tenant(tenant)?;
+                cloud::list_images(
+                    ctx,
+                    &tenant,
+                    &ctx.output(output, Kind::Short)?,
+                    os,
+                    arch,
+                    name,
+                    range,
+                )
+                .await
+            }
+        }
+    }
+}
+
+/// Manage flavors
+#[derive(Subcommand, Clone, Debug)]
+pub enum Flavor {
+    /// List flavors
+    #[clap(name = "list", alias = "l")]
+    List {
+        /// Tenant to use, the default one if omitted
+        #[clap(name = "tenant")]
+        tenant: Option<String>,
+
+        /// Choose the output format, 'short' by default
+        #[clap(short = 'o', long = "output")]
+        output: Option<Kind>,
+
+        /// Only keep flavors with at least the given amount of memory, in megabytes
+        #[clap(long = "min-ram")]
+        min_ram: Option<i64>,
+
+        /// Only keep flavors with at least the given number of virtual cpus
+        #[clap(long = "min-vcpus")]
+        min_vcpus: Option<i64>,
+    },
+}
+
+#[async_trait]
+impl Execute for Flavor {
+    type Error = Box<dyn Error + Send + Sync>;
+
+    #[tracing::instrument]
+    async fn execute(&self, ctx: &ExecContext) -> Result<(), Self::Error> {
+        let config = ctx.config.to_owned();
+        match self {
+            Self::List {
+                tenant,
+                output,
+                min_ram,
+                min_vcpus,
+            } => {
+                let tenant = config.tenant(tenant)?;
+                cloud::list_flavors(
+                    ctx,
+                    &tenant,
+                    &ctx.output(output, Kind::Short)?,
+                    min_ram,
+                    min_vcpus,
+                )
+                .await
+            }
+        }
+    }
+}
+
+/// Manage regions
+#[derive(Subcommand, Clone, Debug)]
+pub enum Region {
+    /// List regions of the tenant along with the services available in them
+    #[clap(name = "list", alias = "l")]
+    List {
+        /// Tenant to use, the default one if omitted
+        #[clap(name = "tenant")]
+        tenant: Option<String>,
+
+        /// Choose the output format, 'short' by default
+        #[clap(short = 'o', long = "output")]
+        output: Option<Kind>,
+    },
+}
+
+#[async_trait]
+impl Execute for Region {
+    type Error = Box<dyn Error + Send + Sync>;
+
+    #[tracing::instrument]
+    async fn execute(&self, ctx: &ExecContext) -> Result<(), Self::Error> {
+        let config = ctx.config.to_owned();
+        match self {
+            Self::List { tenant, output } => {
+                let tenant = config.tenant(tenant)?;
+                cloud::list_regions(ctx, &tenant, &ctx.output(output, Kind::Short)?).await
+            }
+        }
+    }
+}
+
+/// Manage credentials of the ovh api
+#[derive(Subcommand, Clone, Debug)]
+pub enum Auth {
+    /// Request a new consumer key granted with the given scopes
+    #[clap(name = "refresh")]
+    Refresh {
+        /// Parts of the api to grant access to, e.g. 'cloud,domain' or 'all'
+        #[clap(
+            short = 's',
+            long = "scope",
+            value_delimiter = ',',
+            default_value = "all"
+        )]
+        scopes: Vec<String>,
+
+        /// Write the new consumer key in the configuration file, once it has
+        /// been validated
+        #[clap(long = "save")]
+        save: bool,
+
+        /// Number of seconds to wait for the validation of the consumer key
+        /// before saving it
+        #[clap(long = "wait-timeout", default_value = "300")]
+        timeout: u64,
+    },
+
+    /// Show the status, expiration and access rules of the current consumer key
+    #[clap(name = "current", alias = "whoami")]
+    Current {
+        /// Choose the output format, 'short' by default
+        #[clap(short = 'o', long = "output")]
+        output: Option<Kind>,
+    },
+}
+
+#[async_trait]
+impl Execute for Auth {
+    type Error = Box<dyn Error + Send + Sync>;
+
+    #[tracing::instrument]
+    async fn execute(&self, ctx: &ExecContext) -> Result<(), Self::Error> {
+        let config = ctx.config.to_owned();
+        match self {
+            Self::Refresh {
+                scopes,
+                save,
+                timeout,
+            } => auth::refresh(config, scopes, *save, *timeout).await,
+            Self::Current { output } => auth::current(ctx, &ctx.output(output, Kind::Short)?).await,
+        }
+    }
+}
+
+/// Manage ip addresses across the ovh api
+#[derive(Subcommand, Clone, Debug)]
+pub enum Ip {
+    /// Route an additional ip to another service
+    #[clap(name = "move", alias = "m")]
+    Move {
+        /// Ip or block to move, e.g. '192.0.2.1/32'
+        #[clap(name = "ip")]
+        ip: String,
+
+        /// Name of the service to which the ip is routed
+        #[clap(short = 't', long = "to")]
+        to: String,
+
+        /// Next hop of the ip on the destination service
+        #[clap(long = "nexthop")]
+        nexthop: Option<String>,
+
+        /// Do not wait for the end of the task
+        #[clap(long = "no-wait")]
+        no_wait: bool,
+
+        /// Choose the output format, 'short' by default
+        #[clap(short = 'o', long = "output")]
+        output: Option<Kind>,
+    },
+}
+
+#[async_trait]
+impl Execute for Ip {
+    type Error = Box<dyn Error + Send + Sync>;
+
+    #[tracing::instrument]
+    async fn execute(&self, ctx: &ExecContext) -> Result<(), Self::Error> {
+        match self {
+            Self::Move {
+                ip,
+                to,
+                nexthop,
+                no_wait,
+                output,
+            } => {
+                ip::move_ip(
+                    ctx,
+                    ip,
+                    to,
+                    nexthop,
+                    *no_wait,
+                    &ctx.output(output, Kind::Short)?,
+                )
+                .await
+            }
         }
     }
 }
@@ -279,6 +1577,26 @@ pub enum Cloud {
     /// Manage load balancer
     #[clap(name = "loadbalancer", alias = "l", subcommand)]
     LoadBalancer(LoadBalancer),
+
+    /// Manage block storage volumes
+    #[clap(name = "volume", alias = "v", subcommand)]
+    Volume(Volume),
+
+    /// Manage object storage containers
+    #[clap(name = "storage", alias = "s", subcommand)]
+    Storage(Storage),
+
+    /// Manage images
+    #[clap(name = "image", subcommand)]
+    Image(Image),
+
+    /// Manage flavors
+    #[clap(name = "flavor", alias = "f", subcommand)]
+    Flavor(Flavor),
+
+    /// Manage regions
+    #[clap(name = "region", alias = "r", subcommand)]
+    Region(Region),
 }
 
 #[async_trait]
@@ -286,11 +1604,16 @@ impl Execute for Cloud {
     type Error = Box<dyn Error + Send + Sync>;
 
     #[tracing::instrument]
-    async fn execute(&self, config: Arc<Configuration>) -> Result<(), Self::Error> {
+    async fn execute(&self, ctx: &ExecContext) -> Result<(), Self::Error> {
         match self {
-            Self::Tenant(cmd) => cmd.execute(config).await,
-            Self::Instance(cmd) => cmd.execute(config).await,
-            Self::LoadBalancer(cmd) => cmd.execute(config).await,
+            Self::Tenant(cmd) => cmd.execute(ctx).await,
+            Self::Instance(cmd) => cmd.execute(ctx).await,
+            Self::LoadBalancer(cmd) => cmd.execute(ctx).await,
+            Self::Volume(cmd) => cmd.execute(ctx).await,
+            Self::Storage(cmd) => cmd.execute(ctx).await,
+            Self::Image(cmd) => cmd.execute(ctx).await,
+            Self::Flavor(cmd) => cmd.execute(ctx).await,
+            Self::Region(cmd) => cmd.execute(ctx).await,
         }
     }
 }
@@ -301,9 +1624,93 @@ pub enum Server {
     /// List servers
     #[clap(name = "list", alias = "l")]
     List {
-        /// Choose the output format
-        #[clap(short = 'o', long = "output", default_value = "short")]
-        output: Kind,
+        /// Choose the output format, 'short' by default
+        #[clap(short = 'o', long = "output")]
+        output: Option<Kind>,
+    },
+
+    /// List operating system templates available to install servers
+    #[clap(name = "templates")]
+    Templates {
+        /// Choose the output format, 'short' by default
+        #[clap(short = 'o', long = "output")]
+        output: Option<Kind>,
+    },
+
+    /// Enable or disable the monitoring of a server by ovh
+    #[clap(name = "monitoring")]
+    Monitoring {
+        /// Name of the server, e.g. 'ns1234567.ip-203-0-113.eu'
+        #[clap(name = "server")]
+        name: String,
+
+        /// Enable the monitoring
+        #[clap(
+            long = "enable",
+            conflicts_with = "disable",
+            required_unless_present = "disable"
+        )]
+        enable: bool,
+
+        /// Disable the monitoring
+        #[clap(long = "disable")]
+        disable: bool,
+
+        /// Choose the output format, 'short' by default
+        #[clap(short = 'o', long = "output")]
+        output: Option<Kind>,
+    },
+
+    /// List tasks of a server, e.g. reboots and installations, the latest first
+    #[clap(name = "tasks")]
+    Tasks {
+        /// Name of the server, e.g. 'ns1234567.ip-203-0-113.eu'
+        #[clap(name = "server")]
+        name: String,
+
+        /// Choose the output format, 'short' by default
+        #[clap(short = 'o', long = "output")]
+        output: Option<Kind>,
+    },
+
+    /// Show a task of a server
+    #[clap(name = "task")]
+    Task {
+        /// Name of the server, e.g. 'ns1234567.ip-203-0-113.eu'
+        #[clap(name = "server")]
+        name: String,
+
+        /// Identifier of the task
+        #[clap(name = "task")]
+        id: i64,
+
+        /// Choose the output format, 'wide' by default
+        #[clap(short = 'o', long = "output")]
+        output: Option<Kind>,
+    },
+
+    /// Reinstall the operating system of a server, which wipes its disks
+    #[clap(name = "reinstall")]
+    Reinstall {
+        /// Name of the server, e.g. 'ns1234567.ip-203-0-113.eu'
+        #[clap(name = "server")]
+        name: String,
+
+        /// Name of the installation template
+        #[clap(short = 't', long = "template")]
+        template: String,
+
+        /// Name of the ssh key of the account to install on the server
+        #[clap(short = 'k', long = "ssh-key")]
+        ssh_key: Option<String>,
+
+        /// Wait for the installation to be finished
+        #[clap(short = 'w', long = "wait")]
+        wait: bool,
+
+        /// Choose the output format, 'short' by default
+        #[clap(short = 'o', long = "output")]
+        output: Option<Kind>,
     },
 }
 
@@ -312,9 +1719,45 @@ impl Execute for Server {
     type Error = Box<dyn Error + Send + Sync>;
 
     #[tracing::instrument]
-    async fn execute(&self, config: Arc<Configuration>) -> Result<(), Self::Error> {
+    async fn execute(&self, ctx: &ExecContext) -> Result<(), Self::Error> {
         match self {
-            Self::List { output } => server::list_servers(config, output).await,
+            Self::List { output } => {
+                server::list_servers(ctx, &ctx.output(output, Kind::Short)?).await
+            }
+            Self::Templates { output } => {
+                server::list_templates(ctx, &ctx.output(output, Kind::Short)?).await
+            }
+            Self::Monitoring {
+                name,
+                enable,
+                output,
+                ..
+            } => {
+                server::set_monitoring(ctx, name, *enable, &ctx.output(output, Kind::Short)?).await
+            }
+            Self::Tasks { name, output } => {
+                server::list_tasks(ctx, name, &ctx.output(output, Kind::Short)?).await
+            }
+            Self::Task { name, id, output } => {
+                server::get_task(ctx, name, id, &ctx.output(output, Kind::Wide)?).await
+            }
+            Self::Reinstall {
+                name,
+                template,
+                ssh_key,
+                wait,
+                output,
+            } => {
+                server::reinstall_server(
+                    ctx,
+                    name,
+                    template,
+                    ssh_key,
+                    *wait,
+                    &ctx.output(output, Kind::Short)?,
+                )
+                .await
+            }
         }
     }
 }
@@ -332,9 +1775,9 @@ impl Execute for Dedicated {
     type Error = Box<dyn Error + Send + Sync>;
 
     #[tracing::instrument]
-    async fn execute(&self, config: Arc<Configuration>) -> Result<(), Self::Error> {
+    async fn execute(&self, ctx: &ExecContext) -> Result<(), Self::Error> {
         match self {
-            Self::Server(cmd) => cmd.execute(config).await,
+            Self::Server(cmd) => cmd.execute(ctx).await,
         }
     }
 }
@@ -354,9 +1797,76 @@ pub enum Command {
     #[clap(name = "cloud", alias = "c", subcommand)]
     Cloud(Cloud),
 
+    /// Manage ip addresses across the ovh api
+    #[clap(name = "ip", subcommand)]
+    Ip(Ip),
+
     /// Login to the ovh api
     #[clap(name = "connect")]
-    Connect,
+    Connect {
+        /// Print the url on which to create an application for the configured endpoint
+        #[clap(long = "bootstrap", conflicts_with_all = &["wait", "save"])]
+        bootstrap: bool,
+
+        /// Request a consumer key on the endpoint of each configured profile at once
+        #[clap(
+            long = "all-endpoints",
+            conflicts_with_all = &["bootstrap", "wait", "save"]
+        )]
+        all_endpoints: bool,
+
+        /// Wait until the consumer key has been validated on the printed url
+        #[clap(short = 'w', long = "wait")]
+        wait: bool,
+
+        /// Number of seconds to wait for the validation of the consumer key
+        #[clap(long = "wait-timeout", default_value = "300")]
+        timeout: u64,
+
+        /// Write the new consumer key in the configuration file
+        #[clap(long = "save")]
+        save: bool,
+
+        /// Listen on the given address, e.g. '127.0.0.1:8765', to which the
+        /// browser is redirected once the consumer key is validated, then wait
+        /// until it is validated
+        #[clap(long = "listen", conflicts_with_all = &["bootstrap", "all_endpoints"])]
+        listen: Option<SocketAddr>,
+    },
+
+    /// Manage credentials of the ovh api
+    #[clap(name = "auth", subcommand)]
+    Auth(Auth),
+
+    /// Diagnose the configuration, the endpoint, the credentials and the clock
+    #[clap(name = "doctor")]
+    Doctor,
+
+    /// Measure the latency of the endpoint using requests to its time endpoint
+    #[clap(name = "ping")]
+    Ping {
+        /// Number of requests to send
+        #[clap(short = 'n', long = "count", default_value = "4")]
+        count: usize,
+
+        /// Choose the output format, 'short' by default
+        #[clap(short = 'o', long = "output")]
+        output: Option<Kind>,
+    },
+
+    /// Check whether a newer release has been published, which requires a
+    /// network access to github
+    #[clap(name = "self-update")]
+    SelfUpdate {
+        /// Only report whether a newer release exists, reserved as the binary is
+        /// never replaced, so that checking is the default
+        #[clap(long = "check")]
+        check: bool,
+
+        /// Choose the output format, 'short' by default
+        #[clap(short = 'o', long = "output")]
+        output: Option<Kind>,
+    },
 }
 
 #[async_trait]
@@ -364,82 +1874,267 @@ impl Execute for Command {
     type Error = Box<dyn Error + Send + Sync>;
 
     #[tracing::instrument]
-    async fn execute(&self, config: Arc<Configuration>) -> Result<(), Self::Error> {
+    async fn execute(&self, ctx: &ExecContext) -> Result<(), Self::Error> {
+        let config = ctx.config.to_owned();
         match self {
-            Self::Dedicated(cmd) => cmd.execute(config).await,
-            Self::Domain(cmd) => cmd.execute(config).await,
-            Self::Cloud(cmd) => cmd.execute(config).await,
-            Self::Connect => connect(config).await,
+            Self::Dedicated(cmd) => cmd.execute(ctx).await,
+            Self::Domain(cmd) => cmd.execute(ctx).await,
+            Self::Cloud(cmd) => cmd.execute(ctx).await,
+            Self::Ip(cmd) => cmd.execute(ctx).await,
+            Self::Connect {
+                all_endpoints: true,
+                ..
+            } => auth::connect_all(ctx).await,
+            Self::Connect {
+                bootstrap: false,
+                wait,
+                timeout,
+                save,
+                listen,
+                ..
+            } => auth::connect(config, *wait, *timeout, *save, listen).await,
+            Self::Connect {
+                bootstrap: true, ..
+            } => auth::bootstrap(config).await,
+            Self::Auth(cmd) => cmd.execute(ctx).await,
+            Self::Doctor => doctor::doctor(config).await,
+            Self::Ping { count, output } => {
+                ping::ping(ctx, *count, &ctx.output(output, Kind::Short)?).await
+            }
+            Self::SelfUpdate { output, .. } => {
+                update::check(ctx, &ctx.output(output, Kind::Short)?).await
+            }
         }
     }
 }
 
-#[tracing::instrument]
-async fn connect(config: Arc<Configuration>) -> Result<(), Box<dyn Error + Send + Sync>> {
-    let client = Client::from(ClientConfiguration::try_from(config).map_err(|err| {
-        format!(
-            "could not create ovh client configuration from the current configuration, {}",
-            err
-        )
-    })?);
-
-    let credentials: auth::CredentialValidation = client.post_unauthenticated(
-        "auth/credential",
-        &auth::Credential {
-            access_rules: vec![
-                auth::Rule {
-                    method: "GET".into(),
-                    path: "/*".into(),
-                },
-                auth::Rule {
-                    method: "POST".into(),
-                    path: "/*".into(),
-                },
-                auth::Rule {
-                    method: "PUT".into(),
-                    path: "/*".into(),
-                },
-                auth::Rule {
-                    method: "DELETE".into(),
-                    path: "/*".into(),
-                },
-            ],
-            redirection: "https://upload.wikimedia.org/wikipedia/commons/thumb/f/f3/Emojione_1F4AA.svg/768px-Emojione_1F4AA.svg.png".into(),
-        },
-    ).await?;
-
-    println!(
-        "Please login on this url '{}' before going further",
-        credentials.validation_url
-    );
-    println!(
-        "Then, please add the following credentials '{}' as consumer key in configuration",
-        credentials.consumer_key
-    );
-
-    Ok(())
-}
-
 /// Arguments parsed from the command line
 #[derive(Parser, Clone, Debug)]
 #[clap(author, version, about)]
 pub struct Args {
     /// Increase log verbosity
     #[clap(short = 'v', global = true, action = ArgAction::Count)]
-    pub verbose: usize,
+    pub verbose: u8,
 
     /// Validate the configuration
     #[clap(short = 't')]
     pub check: bool,
 
-    /// Path to the configuration file
+    /// Validate the configuration and the credentials against the api
+    #[clap(long = "config-check-remote")]
+    pub check_remote: bool,
+
+    /// Write a commented starter configuration in the given file and exit
+    #[clap(long = "config-write")]
+    pub config_write: Option<PathBuf>,
+
+    /// Overwrite the file given to '--config-write' if it exists
+    #[clap(long = "force", requires = "config_write")]
+    pub force: bool,
+
+    /// Path to the configuration file, could be repeated to merge several
+    /// files, the later ones override the former
     #[clap(short = 'c', global = true, long = "config")]
-    pub config: Option<PathBuf>,
+    pub config: Vec<PathBuf>,
+
+    /// Select the endpoint by its alias, e.g. 'ovh-eu' or 'ovh-ca', instead of
+    /// the one of the configuration, takes precedence over 'OVHCTL_REGION'
+    #[clap(global = true, long = "endpoint-region")]
+    pub endpoint_region: Option<String>,
+
+    /// Replace the api version at the end of the endpoint, e.g. '1.0' or 'v2'
+    #[clap(global = true, long = "api-version")]
+    pub api_version: Option<String>,
+
+    /// Tenant used by cloud commands when they are given none
+    #[clap(global = true, long = "tenant")]
+    pub default_tenant: Option<String>,
+
+    /// Identity to which requests are scoped, sent in the header named by
+    /// 'ovh.delegate-header' in configuration
+    #[clap(global = true, long = "delegate")]
+    pub delegate: Option<String>,
+
+    #[clap(flatten)]
+    pub credentials: Credentials,
+
+    /// Skip resources which could not be retrieved when listing instead of failing
+    #[clap(global = true, long = "best-effort")]
+    pub best_effort: bool,
+
+    /// Default non-critical fields missing from api responses instead of failing
+    #[clap(global = true, long = "deserialize-lenient")]
+    pub deserialize_lenient: bool,
+
+    /// Print the signed curl command line equivalent to each request on the standard error
+    #[clap(global = true, long = "print-curl")]
+    pub print_curl: bool,
+
+    /// Append a json line with the method, path, status and duration of each
+    /// request to the api in the given file, e.g. for audits
+    #[clap(global = true, long = "api-call-log")]
+    pub api_call_log: Option<PathBuf>,
+
+    /// Statuses of the responses on which requests are retried, e.g. '429,503',
+    /// replaces the ones of the configuration
+    #[clap(global = true, long = "retry-on", value_delimiter = ',', value_parser = parse_status)]
+    pub retry_on: Vec<u16>,
+
+    /// Style of tables, either 'default', 'compact' or 'markdown'
+    #[clap(global = true, long = "table-style", default_value = "default")]
+    pub table_style: TableStyle,
+
+    /// Style of booleans in tables, either 'true-false', 'yes-no' or 'symbol'
+    #[clap(global = true, long = "bool-style", default_value = "true-false")]
+    pub bool_style: BoolStyle,
+
+    /// Truncate table cells longer than the given number of characters
+    #[clap(global = true, long = "max-col-width")]
+    pub max_col_width: Option<usize>,
+
+    /// Only print failures and a final summary of bulk operations, e.g. 'export-all'
+    #[clap(global = true, long = "only-errors")]
+    pub only_errors: bool,
+
+    /// Exit with code 3 when a listing has no result, e.g. for health checks
+    #[clap(global = true, long = "fail-on-empty")]
+    pub fail_on_empty: bool,
+
+    /// Maximum number of items written by listings, once they are filtered and
+    /// sorted, e.g. using '--sort-by-ip'
+    #[clap(global = true, long = "limit")]
+    pub limit: Option<usize>,
+
+    /// Number of items of listings skipped before the written ones, e.g. to
+    /// write the next page along with '--limit'
+    #[clap(global = true, long = "offset", default_value = "0")]
+    pub offset: usize,
+
+    /// Write sizes and prices in a human readable way in tables, e.g. '7 GB'
+    #[clap(global = true, long = "human")]
+    pub human: bool,
+
+    /// Append a row with the sums of numeric columns to tables, e.g. the size of
+    /// volumes, which is left out of json and yaml documents
+    #[clap(global = true, long = "totals")]
+    pub totals: bool,
+
+    /// Headers of the columns kept in tables, in the given order, e.g.
+    /// 'name,status', takes precedence over '--fields-file'
+    #[clap(global = true, long = "columns", value_delimiter = ',')]
+    pub columns: Vec<String>,
+
+    /// File listing the headers of the columns kept in tables, one per line,
+    /// takes precedence over 'output.fields' in configuration
+    #[clap(global = true, long = "fields-file")]
+    pub fields_file: Option<PathBuf>,
+
+    /// Write collections as a yaml document per item, separated by '---', instead
+    /// of a single document holding a list
+    #[clap(global = true, long = "yaml-multidoc")]
+    pub yaml_multidoc: bool,
+
+    /// Wrap json documents in an object holding them in 'data' and the number
+    /// and the duration of the requests sent to the api in 'meta'
+    #[clap(global = true, long = "with-meta")]
+    pub with_meta: bool,
+
+    /// Version of the json and yaml documents, e.g. 'v1', reserved as documents
+    /// are written as is while 'v1' is the only version
+    #[clap(global = true, long = "format-version")]
+    pub format_version: Option<FormatVersion>,
+
+    /// Print the values matched by the given jsonpath query, one per line,
+    /// instead of the json, yaml or ndjson document, e.g. '$[*].id'
+    #[clap(global = true, long = "select")]
+    pub select: Option<Select>,
+
+    /// When destructive operations ask for a confirmation, either 'always',
+    /// 'never' or 'tty' to only ask when the standard input is a terminal
+    #[clap(global = true, long = "confirm-destructive", default_value = "tty")]
+    pub confirm_destructive: prompt::Policy,
+
+    /// Disable colors in the output
+    #[clap(global = true, long = "no-color")]
+    pub no_color: bool,
+
+    /// Print errors as a json object on the standard error
+    #[clap(global = true, long = "json-errors")]
+    pub json_errors: bool,
+
+    /// Use the endpoint given by the 'OVHCTL_TEST_ENDPOINT' environment variable
+    /// with canned credentials instead of the configuration, for integration tests
+    #[clap(global = true, long = "endpoint-test", hide = true)]
+    pub endpoint_test: bool,
 
     #[clap(subcommand)]
     pub cmd: Option<Command>,
 }
 
+/// Credentials given on the command line, which take precedence over the ones
+/// of the configuration and of the environment
+#[derive(clap::Args, Clone, Default)]
+pub struct Credentials {
+    /// Key of the application, replaces 'ovh.application-key' in configuration
+    #[clap(global = true, long = "application-key", value_name = "KEY")]
+    pub application_key: Option<String>,
+
+    /// Secret of the application, replaces 'ovh.application-secret' in
+    /// configuration, sensitive as it may end up in the shell history
+    #[clap(global = true, long = "application-secret", value_name = "SECRET")]
+    pub application_secret: Option<String>,
+
+    /// Consumer key, replaces 'ovh.consumer-key' in configuration, sensitive as
+    /// it may end up in the shell history
+    #[clap(global = true, long = "consumer-key", value_name = "SECRET")]
+    pub consumer_key: Option<String>,
+}
+
+impl std::fmt::Debug for Credentials {
+    /// Hides the secrets given on the command line
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Credentials")
+            .field("application_key", &self.application_key)
+            .field(
+                "application_secret",
+                &self.application_secret.as_ref().map(|_| cfg::REDACTED),
+            )
+            .field(
+                "consumer_key",
+                &self.consumer_key.as_ref().map(|_| cfg::REDACTED),
+            )
+            .finish()
+    }
+}
+
+impl Credentials {
+    /// Replace the credentials of the configuration by the given ones, if any
+    #[tracing::instrument(skip(ovh))]
+    pub fn apply(&self, ovh: &mut Ovh) {
+        if let Some(application_key) = &self.application_key {
+            ovh.application_key = application_key.to_owned();
+        }
+
+        if let Some(application_secret) = &self.application_secret {
+            ovh.application_secret = application_secret.to_owned();
+        }
+
+        if let Some(consumer_key) = &self.consumer_key {
+            ovh.consumer_key = Some(consumer_key.to_owned());
+        }
+    }
+}
+
+/// Parse a status on which requests are retried, which must be a plausible http
+/// status
+fn parse_status(s: &str) -> Result<u16, Box<dyn Error + Send + Sync>> {
+    let status = s
+        .parse()
+        .map_err(|err| format!("could not parse status '{}', {}", s, err))?;
+
+    cfg::validate_status(status)
+}
+
 impl paw::ParseArgs for Args {
     type Error = std::io::Error;
 
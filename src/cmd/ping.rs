@@ -0,0 +1,157 @@
+//! # Ping module
+//!
+//! This module provide controller to measure the latency of the endpoint
+use std::error::Error;
+use std::time::Instant;
+
+use prettytable::{Cell, Row};
+use serde::Serialize;
+use tracing::warn;
+
+use crate::cmd::fmt::{self, Formatter, Json, Kind, Short, TableJson, Wide, Yaml};
+use crate::cmd::ExecContext;
+use crate::ovh::{Client, ClientConfiguration, UnauthenticatedRestClient};
+use crate::util::types::Result;
+
+/// Latencies of the requests sent to the endpoint, in milliseconds
+#[derive(Serialize, Clone, Debug)]
+pub struct Latency {
+    #[serde(rename = "endpoint")]
+    pub endpoint: String,
+    #[serde(rename = "sent")]
+    pub sent: usize,
+    #[serde(rename = "received")]
+    pub received: usize,
+    #[serde(rename = "min")]
+    pub min: f64,
+    #[serde(rename = "avg")]
+    pub avg: f64,
+    #[serde(rename = "max")]
+    pub max: f64,
+}
+
+impl Latency {
+    /// Compute the latencies of the given samples, in milliseconds, out of the
+    /// number of requests sent
+    #[tracing::instrument]
+    pub fn new(endpoint: &str, sent: usize, samples: &[f64]) -> Self {
+        let min = samples.iter().copied().fold(f64::INFINITY, f64::min);
+        let max = samples.iter().copied().fold(0.0, f64::max);
+
+        Self {
+            endpoint: endpoint.to_string(),
+            sent,
+            received: samples.len(),
+            min,
+            avg: samples.iter().sum::<f64>() / samples.len() as f64,
+            max,
+        }
+    }
+}
+
+impl Short for Latency {
+    type Error = Box<dyn Error + Send + Sync>;
+
+    #[tracing::instrument]
+    fn short(&self, options: &fmt::Options) -> std::result::Result<String, Self::Error> {
+        let rows = vec![
+            Row::new(vec![
+                Cell::new("Endpoint"),
+                Cell::new("Min (ms)"),
+                Cell::new("Avg (ms)"),
+                Cell::new("Max (ms)"),
+            ]),
+            Row::new(vec![
+                Cell::new(&self.endpoint),
+                Cell::new(&format!("{:.1}", self.min)),
+                Cell::new(&format!("{:.1}", self.avg)),
+                Cell::new(&format!("{:.1}", self.max)),
+            ]),
+        ];
+
+        Ok(fmt::table(rows, options))
+    }
+}
+
+impl Wide for Latency {
+    type Error = Box<dyn Error + Send + Sync>;
+
+    #[tracing::instrument]
+    fn wide(&self, options: &fmt::Options) -> std::result::Result<String, Self::Error> {
+        let rows = vec![
+            Row::new(vec![
+                Cell::new("Endpoint"),
+                Cell::new("Sent"),
+                Cell::new("Received"),
+                Cell::new("Min (ms)"),
+                Cell::new("Avg (ms)"),
+                Cell::new("Max (ms)"),
+            ]),
+            Row::new(vec![
+                Cell::new(&self.endpoint),
+                Cell::new(&self.sent.to_string()),
+                Cell::new(&self.received.to_string()),
+                Cell::new(&format!("{:.1}", self.min)),
+                Cell::new(&format!("{:.1}", self.avg)),
+                Cell::new(&format!("{:.1}", self.max)),
+            ]),
+        ];
+
+        Ok(fmt::table(rows, options))
+    }
+}
+
+/// Send requests to the time endpoint of the api, one after the other, and
+/// print the minimum, average and maximum latencies
+#[tracing::instrument]
+pub async fn ping(ctx: &ExecContext, count: usize, output: &Kind) -> Result<()> {
+    if 0 == count {
+        return Err("count must be at least 1".into());
+    }
+
+    // the time endpoint is not authenticated, so that neither the application
+    // nor the consumer key is needed
+    let client = Client::from(
+        ClientConfiguration::unauthenticated(ctx.config.ovh.to_owned()).map_err(|err| {
+            format!(
+                "could not create ovh client configuration from the current configuration, {}",
+                err
+            )
+        })?,
+    );
+
+    let mut samples = vec![];
+    for _ in 0..count {
+        let begin = Instant::now();
+        match client.get_unauthenticated::<i64>("auth/time").await {
+            Ok(_) => samples.push(begin.elapsed().as_secs_f64() * 1e3),
+            Err(err) => warn!(
+                "could not reach endpoint '{}', {}",
+                ctx.config.ovh.endpoint, err
+            ),
+        }
+    }
+
+    if samples.is_empty() {
+        return Err(format!(
+            "could not reach endpoint '{}' after {} request(s)",
+            ctx.config.ovh.endpoint, count
+        )
+        .into());
+    }
+
+    let latency = Latency::new(&ctx.config.ovh.endpoint, count, &samples);
+    let formatter = Formatter::new(latency.to_owned(), &ctx.render);
+    let o = match output {
+        Kind::Short => latency.short(&ctx.render)?,
+        Kind::Wide => latency.wide(&ctx.render)?,
+        Kind::Json => formatter.json()?,
+        Kind::Yaml => formatter.yaml()?,
+        Kind::TableJson => formatter.table_json()?,
+        Kind::Prometheus | Kind::Ndjson => return Err(fmt::unsupported(output)),
+    };
+
+    println!("{}", o);
+
+    Ok(())
+}
@@ -0,0 +1,122 @@
+//! # Report module
+//!
+//! This module provide utilities to report the outcome of bulk operations
+use std::fmt::Display;
+use std::io::{self, Write};
+
+use pbr::ProgressBar;
+use tracing::error;
+
+use crate::util::progress::{Progress, Spinner};
+use crate::util::types::Result;
+
+/// Create a progress bar, which is not displayed in summary mode
+#[tracing::instrument]
+pub fn progress(total: u64, only_errors: bool) -> Progress {
+    if only_errors {
+        return Progress::hidden(total);
+    }
+
+    let output: Box<dyn Write + Send> = Box::new(io::stdout());
+    Progress::from(ProgressBar::on(output, total))
+}
+
+/// Create a spinner drawn on the standard error, so that it is not mixed with
+/// the result written on the standard output, which is not displayed in summary
+/// mode
+#[tracing::instrument]
+pub fn spinner(only_errors: bool) -> Spinner {
+    if only_errors {
+        return Spinner::hidden();
+    }
+
+    let output: Box<dyn Write + Send> = Box::new(io::stderr());
+    Spinner::from(ProgressBar::on(output, 0))
+}
+
+/// Outcome of the items processed by a bulk operation
+#[derive(Debug)]
+pub struct Report {
+    action: String,
+    noun: String,
+    succeeded: usize,
+    failures: Vec<(String, String)>,
+    only_errors: bool,
+}
+
+impl Report {
+    /// Create a report for the given action on items, e.g. 'export' and 'zone',
+    /// which only prints failures and a final summary in summary mode
+    pub fn new<A, N>(action: A, noun: N, only_errors: bool) -> Self
+    where
+        A: Into<String>,
+        N: Into<String>,
+    {
+        Self {
+            action: action.into(),
+            noun: noun.into(),
+            succeeded: 0,
+            failures: vec![],
+            only_errors,
+        }
+    }
+
+    /// Record an item which has been processed
+    pub fn success(&mut self) {
+        self.succeeded += 1;
+    }
+
+    /// Record an item which could not be processed
+    pub fn failure<I, E>(&mut self, item: I, err: E)
+    where
+        I: Display,
+        E: Display,
+    {
+        self.failures.push((item.to_string(), err.to_string()));
+    }
+
+    /// Returns the number of items which could not be processed
+    pub fn failed(&self) -> usize {
+        self.failures.len()
+    }
+
+    /// Print failures, along with a summary in summary mode, and returns an
+    /// error if any item could not be processed
+    #[tracing::instrument]
+    pub fn finish(self) -> Result<()> {
+        let total = self.succeeded + self.failures.len();
+        let only_errors = self.only_errors;
+        for (item, err) in &self.failures {
+            if only_errors {
+                println!(
+                    "FAILED: could not {} {} '{}', {}",
+                    self.action, self.noun, item, err
+                );
+            } else {
+                error!(
+                    "could not {} {} '{}', {}",
+                    self.action, self.noun, item, err
+                );
+            }
+        }
+
+        if only_errors {
+            match self.failures.len() {
+                0 => println!("OK: {} {}(s)", total, self.noun),
+                failed => println!("FAILED: {} of {} {}(s)", failed, total, self.noun),
+            }
+        }
+
+        if self.failures.is_empty() {
+            return Ok(());
+        }
+
+        Err(format!(
+            "could not {} {} {}(s)",
+            self.action,
+            self.failures.len(),
+            self.noun
+        )
+        .into())
+    }
+}
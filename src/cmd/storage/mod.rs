@@ -0,0 +1,64 @@
+//! # storage module
+//!
+//! This module provide handlers to manage object storage containers
+use std::convert::TryFrom;
+
+use futures::{stream, StreamExt};
+
+use crate::cmd::fmt::{self, Formatter, Json, Kind, Ndjson, Short, TableJson, Wide, Yaml};
+use crate::cmd::ExecContext;
+use crate::ovh::cloud::storage;
+use crate::ovh::{Client, ClientConfiguration, DETAIL_CONCURRENCY};
+use crate::util::types;
+
+/// List the containers of the tenant, their visibility costs a request per
+/// container, so that it is only retrieved for outputs other than the short one
+#[tracing::instrument]
+pub async fn list(ctx: &ExecContext, output: &Kind, tenant: &str) -> types::Result<()> {
+    let client = Client::from(
+        ClientConfiguration::try_from(ctx.config.to_owned()).map_err(|err| {
+            format!(
+                "could not create internal client configuration from the current configuration, {}",
+                err
+            )
+        })?,
+    );
+
+    let mut containers = storage::list_containers(&client, tenant).await?;
+    if !matches!(output, Kind::Short) {
+        let ids: Vec<_> = containers
+            .iter()
+            .map(|container| container.id.to_owned())
+            .collect();
+
+        let visibilities: Vec<_> = stream::iter(ids)
+            .map(|id| {
+                let client = &client;
+                async move { storage::is_public(client, tenant, &id).await }
+            })
+            .buffered(DETAIL_CONCURRENCY)
+            .collect()
+            .await;
+
+        for (container, public) in containers.iter_mut().zip(visibilities) {
+            container.public = client.tolerate(public)?;
+        }
+    }
+
+    fmt::check_empty(containers.len(), "container", &ctx.render)?;
+    let containers = fmt::paginate(containers, &ctx.render);
+    let formatter = Formatter::new(containers.to_owned(), &ctx.render);
+    let o = match output {
+        Kind::Short => containers.short(&ctx.render)?,
+        Kind::Wide => containers.wide(&ctx.render)?,
+        Kind::Json => formatter.json()?,
+        Kind::Yaml => formatter.yaml()?,
+        Kind::TableJson => formatter.table_json()?,
+        Kind::Ndjson => formatter.ndjson()?,
+        Kind::Prometheus => return Err(fmt::unsupported(output)),
+    };
+
+    println!("{}", o);
+
+    Ok(())
+}
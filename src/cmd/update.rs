@@ -0,0 +1,192 @@
+//! # Update module
+//!
+//! This module provide controller to check whether a newer release of the
+//! command line interface has been published
+use std::env;
+use std::error::Error;
+use std::time::Duration;
+
+use hyper::header::{ACCEPT, USER_AGENT};
+use hyper::{Body, Client, Method, Request};
+use hyper_tls::HttpsConnector;
+use prettytable::{Cell, Row};
+use serde::{Deserialize, Serialize};
+
+use crate::cmd::fmt::{self, Formatter, Json, Kind, Short, TableJson, Wide, Yaml};
+use crate::cmd::ExecContext;
+use crate::util::types::Result;
+
+/// Latest release of the project on github
+pub const RELEASES_URL: &str =
+    "https://api.github.com/repos/FlorentinDUBOIS/ovhctl/releases/latest";
+
+/// Environment variable overriding the url of the latest release, e.g. to point
+/// to a mock
+pub const RELEASES_URL_OVERRIDE: &str = "OVHCTL_RELEASES_URL";
+
+/// Delay after which the releases are considered unreachable, e.g. when offline
+const RELEASES_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct Release {
+    #[serde(rename = "tag_name")]
+    pub tag_name: String,
+    #[serde(rename = "html_url")]
+    pub html_url: String,
+}
+
+/// Version of the command line interface compared to the latest release
+#[derive(Serialize, Clone, Debug)]
+pub struct Update {
+    #[serde(rename = "current")]
+    pub current: String,
+    #[serde(rename = "latest")]
+    pub latest: String,
+    #[serde(rename = "outdated")]
+    pub outdated: bool,
+    #[serde(rename = "url")]
+    pub url: String,
+}
+
+impl Short for Update {
+    type Error = Box<dyn Error + Send + Sync>;
+
+    #[tracing::instrument]
+    fn short(&self, options: &fmt::Options) -> std::result::Result<String, Self::Error> {
+        let rows = vec![
+            Row::new(vec![
+                Cell::new("Current"),
+                Cell::new("Latest"),
+                Cell::new("Outdated"),
+            ]),
+            Row::new(vec![
+                Cell::new(&self.current),
+                Cell::new(&self.latest),
+                fmt::boolean(self.outdated, options),
+            ]),
+        ];
+
+        Ok(fmt::table(rows, options))
+    }
+}
+
+impl Wide for Update {
+    type Error = Box<dyn Error + Send + Sync>;
+
+    #[tracing::instrument]
+    fn wide(&self, options: &fmt::Options) -> std::result::Result<String, Self::Error> {
+        let rows = vec![
+            Row::new(vec![
+                Cell::new("Current"),
+                Cell::new("Latest"),
+                Cell::new("Outdated"),
+                Cell::new("Url"),
+            ]),
+            Row::new(vec![
+                Cell::new(&self.current),
+                Cell::new(&self.latest),
+                fmt::boolean(self.outdated, options),
+                Cell::new(&self.url),
+            ]),
+        ];
+
+        Ok(fmt::table(rows, options))
+    }
+}
+
+/// Returns the numbers of a version, e.g. [0, 1, 8] for 'v0.1.8', a pre-release
+/// suffix is ignored
+#[tracing::instrument]
+fn numbers(version: &str) -> Vec<u64> {
+    version
+        .trim()
+        .trim_start_matches('v')
+        .split(['-', '+'])
+        .next()
+        .unwrap_or_default()
+        .split('.')
+        .map(|number| number.parse().unwrap_or(0))
+        .collect()
+}
+
+/// Returns if the latest version is newer than the current one, missing numbers
+/// are zeros, so that '1.0' and '1.0.0' are the same version
+#[tracing::instrument]
+fn is_newer(latest: &str, current: &str) -> bool {
+    let (mut latest, mut current) = (numbers(latest), numbers(current));
+    let len = latest.len().max(current.len());
+    latest.resize(len, 0);
+    current.resize(len, 0);
+
+    latest > current
+}
+
+/// Retrieve the latest release of the project, fails with a hint when the
+/// releases could not be reached, e.g. when offline
+#[tracing::instrument]
+async fn latest_release(url: &str) -> Result<Release> {
+    let client = Client::builder().build::<_, Body>(HttpsConnector::new());
+    let request = Request::builder()
+        .method(Method::GET)
+        .uri(url)
+        .header(USER_AGENT, env!("CARGO_PKG_NAME"))
+        .header(ACCEPT, "application/vnd.github+json")
+        .body(Body::empty())
+        .map_err(|err| format!("could not create request, {}", err))?;
+
+    let unreachable = |err: String| {
+        format!(
+            "could not reach the releases on '{}', check the network access, {}",
+            url, err
+        )
+    };
+
+    let response = tokio::time::timeout(RELEASES_TIMEOUT, client.request(request))
+        .await
+        .map_err(|_| unreachable("request timed out".into()))?
+        .map_err(|err| unreachable(err.to_string()))?;
+
+    let status = response.status();
+    let body = hyper::body::to_bytes(response.into_body())
+        .await
+        .map_err(|err| unreachable(err.to_string()))?;
+
+    if !status.is_success() {
+        return Err(format!(
+            "could not retrieve the latest release on '{}', got status '{}'",
+            url, status
+        )
+        .into());
+    }
+
+    Ok(serde_json::from_slice(&body)
+        .map_err(|err| format!("could not deserialize the latest release, {}", err))?)
+}
+
+/// Compare the version of the command line interface to the latest release
+#[tracing::instrument]
+pub async fn check(ctx: &ExecContext, output: &Kind) -> Result<()> {
+    let url = env::var(RELEASES_URL_OVERRIDE).unwrap_or_else(|_| RELEASES_URL.to_string());
+    let release = latest_release(&url).await?;
+    let current = env!("CARGO_PKG_VERSION");
+    let update = Update {
+        current: current.to_string(),
+        latest: release.tag_name.trim_start_matches('v').to_string(),
+        outdated: is_newer(&release.tag_name, current),
+        url: release.html_url,
+    };
+
+    let formatter = Formatter::new(update.to_owned(), &ctx.render);
+    let o = match output {
+        Kind::Short => update.short(&ctx.render)?,
+        Kind::Wide => update.wide(&ctx.render)?,
+        Kind::Json => formatter.json()?,
+        Kind::Yaml => formatter.yaml()?,
+        Kind::TableJson => formatter.table_json()?,
+        Kind::Prometheus | Kind::Ndjson => return Err(fmt::unsupported(output)),
+    };
+
+    println!("{}", o);
+
+    Ok(())
+}
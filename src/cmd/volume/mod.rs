@@ -0,0 +1,116 @@
+//! # volume module
+//!
+//! This module provide handlers to manage block storage volumes
+use std::convert::TryFrom;
+
+use crate::cmd::fmt::{self, Formatter, Json, Kind, Ndjson, Short, TableJson, Wide, Yaml};
+use crate::cmd::ExecContext;
+use crate::ovh::cloud::volume::{self, Volume, VolumeCreation};
+use crate::ovh::{Client, ClientConfiguration};
+use crate::util::types;
+
+#[tracing::instrument]
+fn print(ctx: &ExecContext, volumes: Vec<Volume>, output: &Kind) -> types::Result<()> {
+    let formatter = Formatter::new(volumes.to_owned(), &ctx.render);
+    let o = match output {
+        Kind::Short => volumes.short(&ctx.render)?,
+        Kind::Wide => volumes.wide(&ctx.render)?,
+        Kind::Json => formatter.json()?,
+        Kind::Yaml => formatter.yaml()?,
+        Kind::TableJson => formatter.table_json()?,
+        Kind::Ndjson => formatter.ndjson()?,
+        Kind::Prometheus => return Err(fmt::unsupported(output)),
+    };
+
+    println!("{}", o);
+
+    Ok(())
+}
+
+#[tracing::instrument]
+pub async fn list(ctx: &ExecContext, output: &Kind, tenant: &str) -> types::Result<()> {
+    let client = Client::from(
+        ClientConfiguration::try_from(ctx.config.to_owned()).map_err(|err| {
+            format!(
+                "could not create internal client configuration from the current configuration, {}",
+                err
+            )
+        })?,
+    );
+
+    let volumes = volume::list(&client, tenant).await?;
+    fmt::check_empty(volumes.len(), "volume", &ctx.render)?;
+    let volumes = fmt::paginate(volumes, &ctx.render);
+    print(ctx, volumes, output)
+}
+
+#[tracing::instrument]
+pub async fn create(
+    ctx: &ExecContext,
+    output: &Kind,
+    tenant: &str,
+    opts: &VolumeCreation,
+) -> types::Result<()> {
+    let client = Client::from(
+        ClientConfiguration::try_from(ctx.config.to_owned()).map_err(|err| {
+            format!(
+                "could not create internal client configuration from the current configuration, {}",
+                err
+            )
+        })?,
+    );
+
+    print(
+        ctx,
+        vec![volume::create(&client, tenant, opts).await?],
+        output,
+    )
+}
+
+#[tracing::instrument]
+pub async fn attach(
+    ctx: &ExecContext,
+    output: &Kind,
+    tenant: &str,
+    id: &str,
+    instance: &str,
+) -> types::Result<()> {
+    let client = Client::from(
+        ClientConfiguration::try_from(ctx.config.to_owned()).map_err(|err| {
+            format!(
+                "could not create internal client configuration from the current configuration, {}",
+                err
+            )
+        })?,
+    );
+
+    print(
+        ctx,
+        vec![volume::attach(&client, tenant, id, instance).await?],
+        output,
+    )
+}
+
+#[tracing::instrument]
+pub async fn detach(
+    ctx: &ExecContext,
+    output: &Kind,
+    tenant: &str,
+    id: &str,
+    instance: &str,
+) -> types::Result<()> {
+    let client = Client::from(
+        ClientConfiguration::try_from(ctx.config.to_owned()).map_err(|err| {
+            format!(
+                "could not create internal client configuration from the current configuration, {}",
+                err
+            )
+        })?,
+    );
+
+    print(
+        ctx,
+        vec![volume::detach(&client, tenant, id, instance).await?],
+        output,
+    )
+}
@@ -3,11 +3,17 @@
 //! A command line interface to improve our life at ovh
 use std::{convert::TryFrom, error::Error as StdError, sync::Arc};
 
+use serde_json::json;
 use tracing::{debug, error, info, warn};
 
 use crate::{
     cfg::Configuration,
-    cmd::{Args, Execute},
+    cmd::{Args, Command, ExecContext, Execute},
+    ovh::{
+        auth, ApiError, Client, ClientConfiguration, QueuedTask, RestClient,
+        UnauthenticatedRestClient,
+    },
+    util::types::{self, Empty},
 };
 
 // library module should be declare first as it expose macros used by other modules
@@ -34,6 +40,73 @@ pub enum Error {
     ParseArgs(std::io::Error),
 }
 
+/// Exit code of a listing which has no result when `--fail-on-empty` is given
+pub const EMPTY_EXIT_CODE: i32 = 3;
+/// Exit code of a request rejected because the api is in maintenance
+pub const MAINTENANCE_EXIT_CODE: i32 = 4;
+/// Exit code of a request rejected because the consumer key has expired or has
+/// been revoked
+pub const CREDENTIAL_EXIT_CODE: i32 = 5;
+
+impl Error {
+    /// Returns the exit code of the process, a listing with no result has a
+    /// dedicated one to be told apart from failures by monitors
+    pub fn code(&self) -> i32 {
+        match self {
+            Self::Command(err) if types::find::<Empty>(err.as_ref()).is_some() => EMPTY_EXIT_CODE,
+            Self::Command(err)
+                if types::find::<ApiError>(err.as_ref())
+                    .map_or(false, ApiError::is_maintenance) =>
+            {
+                MAINTENANCE_EXIT_CODE
+            }
+            Self::Command(err)
+                if types::find::<ApiError>(err.as_ref())
+                    .map_or(false, ApiError::is_invalid_credential) =>
+            {
+                CREDENTIAL_EXIT_CODE
+            }
+            _ => 1,
+        }
+    }
+
+    pub const fn category(&self) -> &'static str {
+        match self {
+            Self::LoggingSystem(_) => "logging",
+            Self::Configuration(_) => "configuration",
+            Self::Command(_) => "command",
+            Self::ParseArgs(_) => "arguments",
+        }
+    }
+
+    /// Serialize the error as a json object, including the status and class of
+    /// the api error that has caused it, if any
+    pub fn json(&self) -> serde_json::Value {
+        let source: Option<&(dyn StdError + 'static)> = match self {
+            Self::Configuration(err) | Self::Command(err) => Some(err.as_ref()),
+            _ => None,
+        };
+
+        let mut value = json!({
+            "category": self.category(),
+            "message": self.to_string(),
+        });
+
+        if let Some(err) = source.and_then(types::find::<ApiError>) {
+            value["status"] = json!(err.status);
+            value["class"] = json!(err.class);
+            value["maintenance"] = json!(err.is_maintenance());
+            value["invalid_credential"] = json!(err.is_invalid_credential());
+        }
+
+        if let Some(err) = source.and_then(types::find::<QueuedTask>) {
+            value["task"] = json!(err.id);
+        }
+
+        value
+    }
+}
+
 impl From<std::io::Error> for Error {
     fn from(err: std::io::Error) -> Self {
         Self::ParseArgs(err)
@@ -46,23 +119,129 @@ impl From<std::io::Error> for Error {
 #[paw::main]
 #[tokio::main(flavor = "current_thread")]
 async fn main(args: Args) -> Result<(), Error> {
-    logging::initialize(args.verbose).map_err(Error::LoggingSystem)?;
-    let config = match args.config.to_owned() {
-        Some(path) => Configuration::try_from(path),
-        None => Configuration::try_new(),
+    let json_errors = args.json_errors;
+    match run(args).await {
+        Err(err) if json_errors => {
+            eprintln!("{}", err.json());
+            std::process::exit(err.code());
+        }
+        Err(err) if MAINTENANCE_EXIT_CODE == err.code() => {
+            eprintln!("Error: the ovh api is in maintenance, try later, {}", err);
+            std::process::exit(err.code());
+        }
+        Err(err) if CREDENTIAL_EXIT_CODE == err.code() => {
+            eprintln!(
+                "Error: the consumer key has expired or has been revoked, run '{} connect --wait --save' to get a new one, {}",
+                env!("CARGO_PKG_NAME"),
+                err
+            );
+            std::process::exit(err.code());
+        }
+        Err(err) if 1 != err.code() => {
+            eprintln!("Error: {}", err);
+            std::process::exit(err.code());
+        }
+        result => result,
+    }
+}
+
+async fn run(args: Args) -> Result<(), Error> {
+    logging::initialize(args.verbose as usize).map_err(Error::LoggingSystem)?;
+
+    // the configuration is not loaded, as it may not exist yet
+    if let Some(path) = &args.config_write {
+        let url = ovh::create_app_url(cfg::DEFAULT_ENDPOINT).map_err(Error::Configuration)?;
+        cfg::write_template(path, url, args.force).map_err(Error::Configuration)?;
+
+        println!(
+            "Configuration written in '{}', please create an application on '{}', fill 'application-key' and 'application-secret' with the given values, and run '{} connect --wait --save -c {}'",
+            path.display(),
+            url,
+            env!("CARGO_PKG_NAME"),
+            path.display()
+        );
+
+        return Ok(());
+    }
+
+    let config = if args.endpoint_test {
+        Configuration::try_endpoint_test()
+    } else {
+        let mut paths = args.config.to_owned();
+        if paths.is_empty() {
+            paths.extend(cfg::path_from_env());
+        }
+
+        match paths.is_empty() {
+            true => Configuration::try_new(),
+            false => Configuration::try_from(paths),
+        }
     };
 
     let config = match config {
-        Ok(config) => Arc::new(config),
+        Ok(mut config) => {
+            // the endpoint of the test mode is the one of the mock
+            if !args.endpoint_test {
+                let region = args
+                    .endpoint_region
+                    .to_owned()
+                    .or_else(cfg::region_from_env);
+                if let Some(region) = region {
+                    config.ovh.endpoint = ovh::endpoint(&region)
+                        .map_err(|err| {
+                            Error::Configuration(
+                                format!("could not select endpoint, {}", err).into(),
+                            )
+                        })?
+                        .to_string();
+                }
+            }
+
+            if let Some(version) = args.api_version.to_owned() {
+                config.ovh.endpoint = cfg::with_api_version(&config.ovh.endpoint, &version)
+                    .map_err(Error::Configuration)?;
+            }
+
+            if let Some(tenant) = args.default_tenant.to_owned() {
+                config.ovh.default_tenant = Some(tenant);
+            }
+
+            if let Some(delegate) = args.delegate.to_owned() {
+                config.ovh.delegate = Some(delegate);
+            }
+
+            args.credentials.apply(&mut config.ovh);
+
+            if args.best_effort {
+                config.ovh.best_effort = true;
+            }
+
+            config.ovh.print_curl = args.print_curl;
+            config.ovh.deserialize_lenient = args.deserialize_lenient;
+            config.ovh.api_call_log = args.api_call_log.to_owned();
+            if !args.retry_on.is_empty() {
+                config.ovh.retry.on = args.retry_on.to_owned();
+            }
+
+            Arc::new(config)
+        }
         Err(err) => {
+            if let Some(Command::Doctor) = args.cmd {
+                println!("[fail] configuration could not be loaded, {}", err);
+            }
+
             error!("could not load configuration, {}", err);
             return Err(Error::Configuration(err));
         }
     };
 
-    if args.check {
+    if args.check || args.check_remote {
         debug!("Arguments: {:#?}", args);
         debug!("Configuration: {:#?}", config);
+        if args.check_remote {
+            check_remote(config).await.map_err(Error::Configuration)?;
+        }
+
         info!("Configuration is healthy!");
         return Ok(());
     }
@@ -74,11 +253,58 @@ async fn main(args: Args) -> Result<(), Error> {
         );
     }
 
+    let ctx = ExecContext::new(config, &args).map_err(Error::Configuration)?;
+
     if let Some(cmd) = args.cmd {
-        if let Err(err) = cmd.execute(config).await {
+        if let Err(err) = cmd.execute(&ctx).await {
             error!("could not execute command, {}", err);
             return Err(Error::Command(err));
         }
+
+        if 0 != ovh::skipped() {
+            eprintln!(
+                "note: {} resource(s) could not be retrieved and have been skipped",
+                ovh::skipped()
+            );
+        }
+    }
+
+    Ok(())
+}
+
+#[tracing::instrument]
+async fn check_remote(config: Arc<Configuration>) -> Result<(), Box<dyn StdError + Send + Sync>> {
+    let client = Client::from(
+        ClientConfiguration::try_from(config.to_owned()).map_err(|err| {
+            format!(
+                "could not create ovh client configuration from the current configuration, {}",
+                err
+            )
+        })?,
+    );
+
+    let mut failures = 0;
+    match client.get_unauthenticated::<i64>("auth/time").await {
+        Ok(_) => println!("[pass] endpoint '{}' is reachable", config.ovh.endpoint),
+        Err(err) => {
+            failures += 1;
+            println!(
+                "[fail] endpoint '{}' is not reachable, {}",
+                config.ovh.endpoint, err
+            );
+        }
+    }
+
+    match client.get::<auth::Me>("me").await {
+        Ok(me) => println!("[pass] credentials are valid for '{}'", me.nic_handle),
+        Err(err) => {
+            failures += 1;
+            println!("[fail] credentials are not valid, {}", err);
+        }
+    }
+
+    if 0 != failures {
+        return Err(format!("{} remote check(s) failed", failures).into());
     }
 
     Ok(())
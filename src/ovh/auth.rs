@@ -1,8 +1,16 @@
 //! # Authentication module
 //!
 //! This module provide structure to interact with the authentication api
+use std::error::Error;
+
+use glob::Pattern;
+use prettytable::{Cell, Row};
 use serde::{Deserialize, Serialize};
 
+use crate::cmd::fmt::{self, Short, Wide};
+use crate::ovh::{Client, RestClient};
+use crate::util::types::{self, Context};
+
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct Rule {
     #[serde(rename = "method")]
@@ -11,6 +19,22 @@ pub struct Rule {
     pub path: String,
 }
 
+impl Rule {
+    /// Returns if the rule grants the given method on the path, rules could use
+    /// '*' as wildcard in their path, e.g. '/domain/*'
+    #[tracing::instrument]
+    pub fn allows(&self, method: &str, path: &str) -> bool {
+        if !self.method.eq_ignore_ascii_case(method) {
+            return false;
+        }
+
+        match Pattern::new(&self.path) {
+            Ok(pattern) => pattern.matches(path),
+            Err(_) => self.path == path,
+        }
+    }
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct Credential {
     #[serde(rename = "accessRules")]
@@ -28,3 +52,153 @@ pub struct CredentialValidation {
     #[serde(rename = "state")]
     pub state: String,
 }
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Currency {
+    #[serde(rename = "code")]
+    pub code: String,
+    #[serde(rename = "symbol")]
+    pub symbol: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Me {
+    #[serde(rename = "nichandle")]
+    pub nic_handle: String,
+    #[serde(rename = "email", default = "crate::ovh::missing")]
+    pub email: String,
+    #[serde(rename = "currency", default)]
+    pub currency: Option<Currency>,
+}
+
+/// Credential used to authenticate requests, i.e. the one of the consumer key
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct CurrentCredential {
+    #[serde(rename = "credentialId")]
+    pub id: i64,
+    #[serde(rename = "status")]
+    pub status: String,
+    #[serde(rename = "expiration", default)]
+    pub expiration: Option<String>,
+    #[serde(rename = "applicationId", default = "crate::ovh::missing")]
+    pub application_id: i64,
+    #[serde(rename = "creation", default = "crate::ovh::missing")]
+    pub creation: String,
+    #[serde(rename = "lastUse", default)]
+    pub last_use: Option<String>,
+    /// Access rules granted to the credential, the api rejects requests which
+    /// match none of them with a '403 forbidden'
+    #[serde(rename = "rules", default = "crate::ovh::missing")]
+    pub rules: Vec<Rule>,
+}
+
+impl CurrentCredential {
+    /// Returns the table of the access rules of the credential
+    #[tracing::instrument]
+    fn rules_table(&self, options: &fmt::Options) -> String {
+        let mut rows = vec![Row::new(vec![Cell::new("Method"), Cell::new("Path")])];
+        for rule in &self.rules {
+            rows.push(Row::new(vec![
+                Cell::new(&rule.method),
+                Cell::new(&rule.path),
+            ]));
+        }
+
+        fmt::table(rows, options)
+    }
+}
+
+impl Short for CurrentCredential {
+    type Error = Box<dyn Error + Send + Sync>;
+
+    #[tracing::instrument]
+    fn short(&self, options: &fmt::Options) -> Result<String, Self::Error> {
+        let expiration = match self.expiration.to_owned() {
+            Some(expiration) => expiration,
+            None => "<never>".into(),
+        };
+
+        let rows = vec![
+            Row::new(vec![
+                Cell::new("Identifier"),
+                Cell::new("Status"),
+                Cell::new("Expiration"),
+            ]),
+            Row::new(vec![
+                Cell::new(&self.id.to_string()),
+                fmt::status(&self.status, options),
+                Cell::new(&expiration),
+            ]),
+        ];
+
+        Ok(format!(
+            "{}\n{}",
+            fmt::table(rows, options),
+            self.rules_table(options)
+        ))
+    }
+}
+
+impl Wide for CurrentCredential {
+    type Error = Box<dyn Error + Send + Sync>;
+
+    #[tracing::instrument]
+    fn wide(&self, options: &fmt::Options) -> Result<String, Self::Error> {
+        let expiration = match self.expiration.to_owned() {
+            Some(expiration) => expiration,
+            None => "<never>".into(),
+        };
+
+        let last_use = match self.last_use.to_owned() {
+            Some(last_use) => last_use,
+            None => "<never>".into(),
+        };
+
+        let rows = vec![
+            Row::new(vec![
+                Cell::new("Identifier"),
+                Cell::new("Application"),
+                Cell::new("Status"),
+                Cell::new("Creation"),
+                Cell::new("Last use"),
+                Cell::new("Expiration"),
+            ]),
+            Row::new(vec![
+                Cell::new(&self.id.to_string()),
+                Cell::new(&self.application_id.to_string()),
+                fmt::status(&self.status, options),
+                Cell::new(&self.creation),
+                Cell::new(&last_use),
+                Cell::new(&expiration),
+            ]),
+        ];
+
+        Ok(format!(
+            "{}\n{}",
+            fmt::table(rows, options),
+            self.rules_table(options)
+        ))
+    }
+}
+
+impl CurrentCredential {
+    /// Returns if the credential has been validated by the user
+    #[tracing::instrument]
+    pub fn is_validated(&self) -> bool {
+        "validated" == self.status
+    }
+
+    /// Returns if the credential could no longer be validated
+    #[tracing::instrument]
+    pub fn is_rejected(&self) -> bool {
+        matches!(self.status.as_str(), "expired" | "refused")
+    }
+}
+
+#[tracing::instrument(skip(client))]
+pub async fn current_credential(client: &Client) -> types::Result<CurrentCredential> {
+    Ok(client
+        .get("auth/currentCredential")
+        .await
+        .map_err(|err| Context::new("could not retrieve the current credential", err))?)
+}
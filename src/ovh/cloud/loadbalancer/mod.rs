@@ -3,12 +3,13 @@
 //! This module provide structure to interact with the loadbalancer api
 use std::error::Error;
 
-use prettytable::{Cell, Row, Table};
+use prettytable::{Cell, Row};
 use serde::{Deserialize, Serialize};
+use tracing::debug;
 
-use crate::cmd::fmt::{Short, Wide};
+use crate::cmd::fmt::{self, Short, Wide};
 use crate::ovh::{Client, RestClient};
-use crate::util::types;
+use crate::util::types::{self, Context, Validation};
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct ConfigurationState {
@@ -48,7 +49,7 @@ impl Short for Vec<LoadBalancer> {
     type Error = Box<dyn Error + Send + Sync>;
 
     #[tracing::instrument]
-    fn short(&self) -> Result<String, Self::Error> {
+    fn short(&self, options: &fmt::Options) -> Result<String, Self::Error> {
         let mut rows = vec![Row::new(vec![
             Cell::new("Identifier"),
             Cell::new("Name"),
@@ -79,14 +80,14 @@ impl Short for Vec<LoadBalancer> {
                 Cell::new(&name),
                 Cell::new(&description),
                 Cell::new(&loadbalancer.region),
-                Cell::new(&loadbalancer.status),
+                fmt::status(&loadbalancer.status, options),
                 Cell::new(&loadbalancer.address.ip_v4),
             ]);
 
             rows.push(row);
         }
 
-        Ok(format!("{}", Table::init(rows)))
+        Ok(fmt::table(rows, options))
     }
 }
 
@@ -94,7 +95,7 @@ impl Wide for Vec<LoadBalancer> {
     type Error = Box<dyn Error + Send + Sync>;
 
     #[tracing::instrument]
-    fn wide(&self) -> Result<String, Self::Error> {
+    fn wide(&self, options: &fmt::Options) -> Result<String, Self::Error> {
         let mut rows = vec![Row::new(vec![
             Cell::new("Identifier"),
             Cell::new("Name"),
@@ -133,7 +134,7 @@ impl Wide for Vec<LoadBalancer> {
                 Cell::new(&name),
                 Cell::new(&description),
                 Cell::new(&loadbalancer.region),
-                Cell::new(&loadbalancer.status),
+                fmt::status(&loadbalancer.status, options),
                 Cell::new(&loadbalancer.address.ip_v4),
                 Cell::new(&ip_v6),
                 Cell::new(&format!("{}", loadbalancer.configuration.applied)),
@@ -143,7 +144,143 @@ impl Wide for Vec<LoadBalancer> {
             rows.push(row);
         }
 
-        Ok(format!("{}", Table::init(rows)))
+        Ok(fmt::table(rows, options))
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Pool {
+    #[serde(rename = "id")]
+    pub id: String,
+    #[serde(rename = "name")]
+    pub name: String,
+    #[serde(rename = "protocol")]
+    pub protocol: String,
+    #[serde(rename = "algorithm", default = "crate::ovh::missing")]
+    pub algorithm: String,
+    #[serde(rename = "status")]
+    pub status: String,
+}
+
+impl Short for Vec<Pool> {
+    type Error = Box<dyn Error + Send + Sync>;
+
+    #[tracing::instrument]
+    fn short(&self, options: &fmt::Options) -> Result<String, Self::Error> {
+        let mut rows = vec![Row::new(vec![
+            Cell::new("Identifier"),
+            Cell::new("Name"),
+            Cell::new("Protocol"),
+            Cell::new("Status"),
+        ])];
+
+        for pool in self {
+            rows.push(Row::new(vec![
+                Cell::new(&pool.id),
+                Cell::new(&pool.name),
+                Cell::new(&pool.protocol),
+                fmt::status(&pool.status, options),
+            ]));
+        }
+
+        Ok(fmt::table(rows, options))
+    }
+}
+
+impl Wide for Vec<Pool> {
+    type Error = Box<dyn Error + Send + Sync>;
+
+    #[tracing::instrument]
+    fn wide(&self, options: &fmt::Options) -> Result<String, Self::Error> {
+        let mut rows = vec![Row::new(vec![
+            Cell::new("Identifier"),
+            Cell::new("Name"),
+            Cell::new("Protocol"),
+            Cell::new("Algorithm"),
+            Cell::new("Status"),
+        ])];
+
+        for pool in self {
+            rows.push(Row::new(vec![
+                Cell::new(&pool.id),
+                Cell::new(&pool.name),
+                Cell::new(&pool.protocol),
+                Cell::new(&pool.algorithm),
+                fmt::status(&pool.status, options),
+            ]));
+        }
+
+        Ok(fmt::table(rows, options))
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Member {
+    #[serde(rename = "id")]
+    pub id: String,
+    #[serde(rename = "name", default = "crate::ovh::missing")]
+    pub name: String,
+    #[serde(rename = "address")]
+    pub address: String,
+    #[serde(rename = "port")]
+    pub port: i64,
+    #[serde(rename = "weight", default = "crate::ovh::missing")]
+    pub weight: i64,
+    #[serde(rename = "status")]
+    pub status: String,
+}
+
+impl Short for Vec<Member> {
+    type Error = Box<dyn Error + Send + Sync>;
+
+    #[tracing::instrument]
+    fn short(&self, options: &fmt::Options) -> Result<String, Self::Error> {
+        let mut rows = vec![Row::new(vec![
+            Cell::new("Identifier"),
+            Cell::new("Name"),
+            Cell::new("Address"),
+            Cell::new("Status"),
+        ])];
+
+        for member in self {
+            rows.push(Row::new(vec![
+                Cell::new(&member.id),
+                Cell::new(&member.name),
+                Cell::new(&format!("{}:{}", member.address, member.port)),
+                fmt::status(&member.status, options),
+            ]));
+        }
+
+        Ok(fmt::table(rows, options))
+    }
+}
+
+impl Wide for Vec<Member> {
+    type Error = Box<dyn Error + Send + Sync>;
+
+    #[tracing::instrument]
+    fn wide(&self, options: &fmt::Options) -> Result<String, Self::Error> {
+        let mut rows = vec![Row::new(vec![
+            Cell::new("Identifier"),
+            Cell::new("Name"),
+            Cell::new("Address"),
+            Cell::new("Port"),
+            Cell::new("Weight"),
+            Cell::new("Status"),
+        ])];
+
+        for member in self {
+            rows.push(Row::new(vec![
+                Cell::new(&member.id),
+                Cell::new(&member.name),
+                Cell::new(&member.address),
+                Cell::new(&format!("{}", member.port)),
+                Cell::new(&format!("{}", member.weight)),
+                fmt::status(&member.status, options),
+            ]));
+        }
+
+        Ok(fmt::table(rows, options))
     }
 }
 
@@ -151,52 +288,115 @@ impl Wide for Vec<LoadBalancer> {
 pub struct LoadBalancerCreation {
     #[serde(rename = "region")]
     pub region: String,
+    #[serde(rename = "name", skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(rename = "description", skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+}
+
+impl LoadBalancerCreation {
+    /// Check the creation request before sending it to the api, returns every
+    /// problem found at once
+    #[tracing::instrument]
+    pub fn validate(&self) -> Result<(), Validation> {
+        let mut validation = Validation::new("loadbalancer creation");
+
+        if self.region.is_empty() {
+            validation.push("region must not be empty");
+        } else if !self
+            .region
+            .chars()
+            .all(|c| c.is_ascii_uppercase() || c.is_ascii_digit() || c == '-')
+        {
+            validation.push(format!(
+                "region '{}' is not a region code, e.g. 'GRA7' or 'BHS5'",
+                self.region
+            ));
+        }
+
+        validation.check()
+    }
 }
 
 impl From<&str> for LoadBalancerCreation {
     #[tracing::instrument]
     fn from(region: &str) -> Self {
-        Self {
-            region: region.to_string(),
-        }
+        Self::from(region.to_string())
     }
 }
 
 impl From<String> for LoadBalancerCreation {
     #[tracing::instrument]
     fn from(region: String) -> Self {
-        Self { region }
+        Self {
+            region,
+            name: None,
+            description: None,
+        }
+    }
+}
+
+/// Fields of a load balancer which could be changed after its creation, the
+/// omitted ones are left untouched
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct LoadBalancerUpdate {
+    #[serde(rename = "name", skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(rename = "description", skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+}
+
+impl LoadBalancerUpdate {
+    /// Check the update request before sending it to the api, returns every
+    /// problem found at once
+    #[tracing::instrument]
+    pub fn validate(&self) -> Result<(), Validation> {
+        let mut validation = Validation::new("loadbalancer update");
+
+        if self.name.is_none() && self.description.is_none() {
+            validation.push("at least one of name or description must be given");
+        }
+
+        if let Some(name) = &self.name {
+            if name.trim().is_empty() {
+                validation.push("name must not be empty");
+            }
+        }
+
+        validation.check()
     }
 }
 
 #[tracing::instrument(skip(client))]
 pub async fn list(client: &Client, tenant: &str) -> types::Result<Vec<LoadBalancer>> {
     let ids: Vec<String> = client
-        .get(&format!("cloud/project/{}/loadbalancer", tenant))
+        .get_ids(&format!("cloud/project/{}/loadbalancer", tenant))
         .await
         .map_err(|err| {
-            format!(
-                "could not list loadbalancer on tenant '{}', {}",
-                tenant, err
+            Context::new(
+                format!("could not list loadbalancer on tenant '{}'", tenant),
+                err,
             )
         })?;
 
-    let mut loadbalancers = vec![];
-    for id in ids {
-        loadbalancers.push(
-            client
-                .get(&format!("cloud/project/{}/loadbalancer/{}", tenant, &id))
-                .await
-                .map_err(|err| {
-                    format!(
-                        "could not get loadbalancer '{}' on tenant '{}', {}",
-                        id, tenant, err
-                    )
-                })?,
-        );
+    match client
+        .get_batch(&format!("cloud/project/{}/loadbalancer", tenant), &ids)
+        .await
+    {
+        Ok(items) => return Ok(items),
+        Err(err) => debug!(
+            "could not use a batch request, fallback on a request per item, {}",
+            err
+        ),
     }
 
-    Ok(loadbalancers)
+    client
+        .get_each(
+            &format!("cloud/project/{}/loadbalancer", tenant),
+            &ids,
+            |id| format!("could not get loadbalancer '{}' on tenant '{}'", id, tenant),
+        )
+        .await
 }
 
 #[tracing::instrument(skip(client))]
@@ -205,10 +405,62 @@ pub async fn create(
     tenant: &str,
     opts: &LoadBalancerCreation,
 ) -> types::Result<LoadBalancer> {
+    opts.validate()?;
+
     Ok(client
         .post(&format!("cloud/project/{}/loadbalancer", tenant), opts)
         .await
-        .map_err(|err| format!("could not create loadbalancer, {}", err))?)
+        .map_err(|err| Context::new("could not create loadbalancer", err))?)
+}
+
+#[tracing::instrument(skip(client))]
+pub async fn get(client: &Client, tenant: &str, id: &str) -> types::Result<LoadBalancer> {
+    Ok(client
+        .get(&format!("cloud/project/{}/loadbalancer/{}", tenant, id))
+        .await
+        .map_err(|err| {
+            Context::new(
+                format!(
+                    "could not retrieve loadbalancer '{}' on tenant '{}'",
+                    id, tenant
+                ),
+                err,
+            )
+        })?)
+}
+
+/// Apply the given fields on the load balancer, the other ones are kept as they
+/// are, and returns the updated load balancer
+#[tracing::instrument(skip(client))]
+pub async fn update(
+    client: &Client,
+    tenant: &str,
+    id: &str,
+    name: &Option<String>,
+    description: &Option<String>,
+) -> types::Result<LoadBalancer> {
+    let opts = LoadBalancerUpdate {
+        name: name.to_owned(),
+        description: description.to_owned(),
+    };
+
+    opts.validate()?;
+
+    let current = get(client, tenant, id).await?;
+    let opts = LoadBalancerUpdate {
+        name: opts.name.or(current.name),
+        description: opts.description.or(current.description),
+    };
+
+    client
+        .put::<_, ()>(
+            &format!("cloud/project/{}/loadbalancer/{}", tenant, id),
+            &opts,
+        )
+        .await
+        .map_err(|err| Context::new(format!("could not update loadbalancer '{}'", id), err))?;
+
+    get(client, tenant, id).await
 }
 
 #[tracing::instrument(skip(client))]
@@ -216,5 +468,62 @@ pub async fn delete(client: &Client, tenant: &str, id: &str) -> types::Result<()
     Ok(client
         .delete(&format!("cloud/project/{}/loadbalancer/{}", tenant, id))
         .await
-        .map_err(|err| format!("could not delete loadbalancer '{}', {}", id, err))?)
+        .map_err(|err| Context::new(format!("could not delete loadbalancer '{}'", id), err))?)
+}
+
+#[tracing::instrument(skip(client))]
+pub async fn list_pools(client: &Client, tenant: &str, id: &str) -> types::Result<Vec<Pool>> {
+    let path = format!("cloud/project/{}/loadbalancer/{}/pool", tenant, id);
+    let ids: Vec<String> = client.get_ids(&path).await.map_err(|err| {
+        Context::new(
+            format!("could not list pools of loadbalancer '{}'", id),
+            err,
+        )
+    })?;
+
+    match client.get_batch(&path, &ids).await {
+        Ok(items) => return Ok(items),
+        Err(err) => debug!(
+            "could not use a batch request, fallback on a request per item, {}",
+            err
+        ),
+    }
+
+    client
+        .get_each(&path, &ids, |pool| {
+            format!("could not get pool '{}' of loadbalancer '{}'", pool, id)
+        })
+        .await
+}
+
+#[tracing::instrument(skip(client))]
+pub async fn list_members(
+    client: &Client,
+    tenant: &str,
+    id: &str,
+    pool: &str,
+) -> types::Result<Vec<Member>> {
+    let path = format!(
+        "cloud/project/{}/loadbalancer/{}/pool/{}/member",
+        tenant, id, pool
+    );
+
+    let ids: Vec<String> = client
+        .get_ids(&path)
+        .await
+        .map_err(|err| Context::new(format!("could not list members of pool '{}'", pool), err))?;
+
+    match client.get_batch(&path, &ids).await {
+        Ok(items) => return Ok(items),
+        Err(err) => debug!(
+            "could not use a batch request, fallback on a request per item, {}",
+            err
+        ),
+    }
+
+    client
+        .get_each(&path, &ids, |member| {
+            format!("could not get member '{}' of pool '{}'", member, pool)
+        })
+        .await
 }
@@ -1,31 +1,39 @@
 //! # Cloud module
 //!
 //! This module provide structure to interact with the cloud api
+use std::collections::BTreeMap;
 use std::error::Error;
+use std::fmt::{self as stdfmt, Display, Formatter};
 use std::net::IpAddr;
+use std::str::FromStr;
 
-use prettytable::{Cell, Row, Table};
+use prettytable::{Cell, Row};
 use serde::{Deserialize, Serialize};
+use tracing::debug;
 
-use crate::cmd::fmt::{Short, Wide};
-use crate::ovh::{Client, RestClient};
-use crate::util::types;
+use crate::cmd::fmt::{self, Prometheus, Short, Wide};
+use crate::ovh::task::Task;
+use crate::ovh::{ApiError, Client, RestClient};
+use crate::util::human::{self, GB, MB};
+use crate::util::types::{self, Context, Validation};
 
 pub mod loadbalancer;
+pub mod storage;
+pub mod volume;
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct Tenant {
     #[serde(rename = "project_id")]
     pub project_id: String,
-    #[serde(rename = "description")]
+    #[serde(rename = "description", default = "crate::ovh::missing")]
     pub description: String,
-    #[serde(rename = "planCode")]
+    #[serde(rename = "planCode", default = "crate::ovh::missing")]
     pub plan_code: String,
-    #[serde(rename = "unleash")]
+    #[serde(rename = "unleash", default = "crate::ovh::missing")]
     pub unleash: bool,
     #[serde(rename = "status")]
     pub status: String,
-    #[serde(rename = "access")]
+    #[serde(rename = "access", default = "crate::ovh::missing")]
     pub access: String,
 }
 
@@ -33,7 +41,7 @@ impl Short for Vec<Tenant> {
     type Error = Box<dyn Error + Send + Sync>;
 
     #[tracing::instrument]
-    fn short(&self) -> Result<String, Self::Error> {
+    fn short(&self, options: &fmt::Options) -> Result<String, Self::Error> {
         let mut rows = vec![Row::new(vec![
             Cell::new("Tenant"),
             Cell::new("Status"),
@@ -46,17 +54,17 @@ impl Short for Vec<Tenant> {
         for tenant in self {
             let row = Row::new(vec![
                 Cell::new(&tenant.project_id),
-                Cell::new(&tenant.status),
+                fmt::status(&tenant.status, options),
                 Cell::new(&tenant.description),
                 Cell::new(&tenant.plan_code),
-                Cell::new(&format!("{}", tenant.unleash)),
+                fmt::boolean(tenant.unleash, options),
                 Cell::new(&tenant.access),
             ]);
 
             rows.push(row);
         }
 
-        Ok(format!("{}", Table::init(rows)))
+        Ok(fmt::table(rows, options))
     }
 }
 
@@ -64,7 +72,7 @@ impl Wide for Vec<Tenant> {
     type Error = Box<dyn Error + Send + Sync>;
 
     #[tracing::instrument]
-    fn wide(&self) -> Result<String, Self::Error> {
+    fn wide(&self, options: &fmt::Options) -> Result<String, Self::Error> {
         let mut rows = vec![Row::new(vec![
             Cell::new("Tenant"),
             Cell::new("Status"),
@@ -77,17 +85,17 @@ impl Wide for Vec<Tenant> {
         for tenant in self {
             let row = Row::new(vec![
                 Cell::new(&tenant.project_id),
-                Cell::new(&tenant.status),
+                fmt::status(&tenant.status, options),
                 Cell::new(&tenant.description),
                 Cell::new(&tenant.plan_code),
-                Cell::new(&format!("{}", tenant.unleash)),
+                fmt::boolean(tenant.unleash, options),
                 Cell::new(&tenant.access),
             ]);
 
             rows.push(row);
         }
 
-        Ok(format!("{}", Table::init(rows)))
+        Ok(fmt::table(rows, options))
     }
 }
 
@@ -109,7 +117,7 @@ impl Short for Vec<IpAddress> {
     type Error = Box<dyn Error + Send + Sync>;
 
     #[tracing::instrument]
-    fn short(&self) -> Result<String, Self::Error> {
+    fn short(&self, options: &fmt::Options) -> Result<String, Self::Error> {
         let mut rows = vec![Row::new(vec![
             Cell::new("Identifier"),
             Cell::new("IP"),
@@ -135,7 +143,7 @@ impl Short for Vec<IpAddress> {
             rows.push(row);
         }
 
-        Ok(format!("{}", Table::init(rows)))
+        Ok(fmt::table(rows, options))
     }
 }
 
@@ -143,7 +151,7 @@ impl Wide for Vec<IpAddress> {
     type Error = Box<dyn Error + Send + Sync>;
 
     #[tracing::instrument]
-    fn wide(&self) -> Result<String, Self::Error> {
+    fn wide(&self, options: &fmt::Options) -> Result<String, Self::Error> {
         let mut rows = vec![Row::new(vec![
             Cell::new("Identifier"),
             Cell::new("IP"),
@@ -169,7 +177,7 @@ impl Wide for Vec<IpAddress> {
             rows.push(row);
         }
 
-        Ok(format!("{}", Table::init(rows)))
+        Ok(fmt::table(rows, options))
     }
 }
 
@@ -181,23 +189,118 @@ pub struct Instance {
     pub name: String,
     #[serde(rename = "ipAddresses")]
     pub ip_addresses: Vec<IpAddress>,
-    #[serde(rename = "flavorId")]
+    #[serde(rename = "flavorId", default = "crate::ovh::missing")]
     pub flavor_id: String,
-    #[serde(rename = "imageId")]
+    #[serde(rename = "imageId", default = "crate::ovh::missing")]
     pub image_id: String,
     #[serde(rename = "region")]
     pub region: String,
     #[serde(rename = "status")]
     pub status: String,
-    #[serde(rename = "planCode")]
+    #[serde(rename = "planCode", default = "crate::ovh::missing")]
     pub plan_code: String,
+    #[serde(rename = "created")]
+    pub created: Option<String>,
+}
+
+impl Task for Instance {
+    #[tracing::instrument]
+    fn id(&self) -> String {
+        self.id.to_owned()
+    }
+
+    #[tracing::instrument]
+    fn status(&self) -> &str {
+        &self.status
+    }
+
+    /// An instance in error does not reach any other status by itself
+    #[tracing::instrument]
+    fn is_finished(&self) -> bool {
+        self.status.eq_ignore_ascii_case("error")
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct InstanceRename {
+    #[serde(rename = "instanceName")]
+    pub instance_name: String,
+}
+
+impl InstanceRename {
+    /// Check the name of the instance, which is also used as dns sub domain when
+    /// synchronising records, returns every problem found at once
+    #[tracing::instrument]
+    pub fn validate(&self) -> Result<(), Validation> {
+        let mut validation = Validation::new(format!("instance name '{}'", self.instance_name));
+
+        validate_name(&mut validation, &self.instance_name);
+        validation.check()
+    }
+}
+
+/// Record the problems of the given instance name, which is also used as dns sub
+/// domain when synchronising records
+fn validate_name(validation: &mut Validation, name: &str) {
+    if name.is_empty() || name.len() > 255 {
+        validation.push("name must contain between 1 and 255 characters");
+    }
+
+    if name
+        .chars()
+        .any(|c| !c.is_ascii_alphanumeric() && c != '-' && c != '_' && c != '.')
+    {
+        validation.push("name must only contain letters, digits, '-', '_' or '.'");
+    }
+
+    if name.starts_with(['-', '.']) || name.ends_with(['-', '.']) {
+        validation.push("name must not start or end with '-' or '.'");
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct InstanceCreation {
+    #[serde(rename = "name")]
+    pub name: String,
+    #[serde(rename = "flavorId")]
+    pub flavor_id: String,
+    #[serde(rename = "imageId")]
+    pub image_id: String,
+    #[serde(rename = "region")]
+    pub region: String,
+    #[serde(rename = "sshKeyId", skip_serializing_if = "Option::is_none")]
+    pub ssh_key_id: Option<String>,
+}
+
+impl InstanceCreation {
+    /// Check the creation request before sending it to the api, returns every
+    /// problem found at once
+    #[tracing::instrument]
+    pub fn validate(&self) -> Result<(), Validation> {
+        let mut validation = Validation::new(format!("instance creation '{}'", self.name));
+
+        validate_name(&mut validation, &self.name);
+        if self.flavor_id.is_empty() {
+            validation.push("flavor must not be empty");
+        }
+
+        if self.image_id.is_empty() {
+            validation.push("image must not be empty");
+        }
+
+        if self.region.is_empty() {
+            validation.push("region must not be empty");
+        }
+
+        validation.check()
+    }
 }
 
 impl Short for Vec<Instance> {
     type Error = Box<dyn Error + Send + Sync>;
 
     #[tracing::instrument]
-    fn short(&self) -> Result<String, Self::Error> {
+    fn short(&self, options: &fmt::Options) -> Result<String, Self::Error> {
         let mut rows = vec![Row::new(vec![
             Cell::new("Identifier"),
             Cell::new("Name"),
@@ -211,14 +314,14 @@ impl Short for Vec<Instance> {
                 Cell::new(&instance.id),
                 Cell::new(&instance.name),
                 Cell::new(&instance.region),
-                Cell::new(&instance.status),
+                fmt::status(&instance.status, options),
                 Cell::new(instance.plan_code.trim_end_matches(".consumption")),
             ]);
 
             rows.push(row);
         }
 
-        Ok(format!("{}", Table::init(rows)))
+        Ok(fmt::table(rows, options))
     }
 }
 
@@ -226,7 +329,7 @@ impl Wide for Vec<Instance> {
     type Error = Box<dyn Error + Send + Sync>;
 
     #[tracing::instrument]
-    fn wide(&self) -> Result<String, Self::Error> {
+    fn wide(&self, options: &fmt::Options) -> Result<String, Self::Error> {
         let mut rows = vec![Row::new(vec![
             Cell::new("Identifier"),
             Cell::new("Name"),
@@ -242,7 +345,7 @@ impl Wide for Vec<Instance> {
                 Cell::new(&instance.id),
                 Cell::new(&instance.name),
                 Cell::new(&instance.region),
-                Cell::new(&instance.status),
+                fmt::status(&instance.status, options),
                 Cell::new(&instance.flavor_id),
                 Cell::new(&instance.image_id),
                 Cell::new(&instance.plan_code),
@@ -251,28 +354,591 @@ impl Wide for Vec<Instance> {
             rows.push(row);
         }
 
-        Ok(format!("{}", Table::init(rows)))
+        Ok(fmt::table(rows, options))
+    }
+}
+
+impl Prometheus for Vec<Instance> {
+    type Error = Box<dyn Error + Send + Sync>;
+
+    #[tracing::instrument]
+    fn prometheus(&self) -> Result<String, Self::Error> {
+        let mut lines = vec![
+            String::from("# HELP ovhctl_instance Public cloud instance, labelled by its status"),
+            String::from("# TYPE ovhctl_instance gauge"),
+        ];
+
+        for instance in self {
+            lines.push(fmt::sample(
+                "ovhctl_instance",
+                &[
+                    ("id", &instance.id),
+                    ("name", &instance.name),
+                    ("region", &instance.region),
+                    ("status", &instance.status),
+                    ("flavor", &instance.flavor_id),
+                ],
+                1.0,
+            ));
+        }
+
+        Ok(lines.join("\n"))
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Image {
+    #[serde(rename = "id")]
+    pub id: String,
+    #[serde(rename = "name")]
+    pub name: String,
+    #[serde(rename = "region")]
+    pub region: String,
+    #[serde(rename = "type", default = "crate::ovh::missing")]
+    pub os_type: String,
+    #[serde(rename = "visibility", default = "crate::ovh::missing")]
+    pub visibility: String,
+    #[serde(rename = "status")]
+    pub status: String,
+    #[serde(rename = "user", default = "crate::ovh::missing")]
+    pub user: String,
+    #[serde(rename = "minDisk", default = "crate::ovh::missing")]
+    pub min_disk: i64,
+    #[serde(rename = "minRam", default = "crate::ovh::missing")]
+    pub min_ram: i64,
+    #[serde(rename = "size", default = "crate::ovh::missing")]
+    pub size: f64,
+    #[serde(rename = "creationDate", default = "crate::ovh::missing")]
+    pub creation_date: String,
+}
+
+impl Short for Vec<Image> {
+    type Error = Box<dyn Error + Send + Sync>;
+
+    #[tracing::instrument]
+    fn short(&self, options: &fmt::Options) -> Result<String, Self::Error> {
+        let mut rows = vec![Row::new(vec![
+            Cell::new("Identifier"),
+            Cell::new("Name"),
+            Cell::new("Region"),
+            Cell::new("OS"),
+            Cell::new("Status"),
+        ])];
+
+        for image in self {
+            rows.push(Row::new(vec![
+                Cell::new(&image.id),
+                Cell::new(&image.name),
+                Cell::new(&image.region),
+                Cell::new(&image.os_type),
+                fmt::status(&image.status, options),
+            ]));
+        }
+
+        Ok(fmt::table(rows, options))
+    }
+}
+
+impl Wide for Vec<Image> {
+    type Error = Box<dyn Error + Send + Sync>;
+
+    #[tracing::instrument]
+    fn wide(&self, options: &fmt::Options) -> Result<String, Self::Error> {
+        let mut rows = vec![Row::new(vec![
+            Cell::new("Identifier"),
+            Cell::new("Name"),
+            Cell::new("Region"),
+            Cell::new("OS"),
+            Cell::new("Status"),
+            Cell::new("Visibility"),
+            Cell::new("User"),
+            Cell::new("Min disk"),
+            Cell::new("Min RAM"),
+            Cell::new("Size"),
+            Cell::new("Creation date"),
+        ])];
+
+        for image in self {
+            rows.push(Row::new(vec![
+                Cell::new(&image.id),
+                Cell::new(&image.name),
+                Cell::new(&image.region),
+                Cell::new(&image.os_type),
+                fmt::status(&image.status, options),
+                Cell::new(&image.visibility),
+                Cell::new(&image.user),
+                Cell::new(&human::size(image.min_disk as f64, GB, options.human)),
+                Cell::new(&human::size(image.min_ram as f64, MB, options.human)),
+                Cell::new(&human::size(image.size, GB, options.human)),
+                Cell::new(&image.creation_date),
+            ]));
+        }
+
+        Ok(fmt::table(rows, options))
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Flavor {
+    #[serde(rename = "id")]
+    pub id: String,
+    #[serde(rename = "name")]
+    pub name: String,
+    #[serde(rename = "region")]
+    pub region: String,
+    #[serde(rename = "type", default = "crate::ovh::missing")]
+    pub kind: String,
+    #[serde(rename = "osType", default = "crate::ovh::missing")]
+    pub os_type: String,
+    #[serde(rename = "vcpus", default = "crate::ovh::missing")]
+    pub vcpus: i64,
+    #[serde(rename = "ram", default = "crate::ovh::missing")]
+    pub ram: i64,
+    #[serde(rename = "disk", default = "crate::ovh::missing")]
+    pub disk: i64,
+    #[serde(rename = "available")]
+    pub available: bool,
+    #[serde(rename = "inboundBandwidth")]
+    pub inbound_bandwidth: Option<i64>,
+    #[serde(rename = "outboundBandwidth")]
+    pub outbound_bandwidth: Option<i64>,
+}
+
+impl Short for Vec<Flavor> {
+    type Error = Box<dyn Error + Send + Sync>;
+
+    #[tracing::instrument]
+    fn short(&self, options: &fmt::Options) -> Result<String, Self::Error> {
+        let mut rows = vec![Row::new(vec![
+            Cell::new("Identifier"),
+            Cell::new("Name"),
+            Cell::new("Region"),
+            Cell::new("vCPUs"),
+            Cell::new("RAM"),
+            Cell::new("Disk"),
+        ])];
+
+        for flavor in self {
+            rows.push(Row::new(vec![
+                Cell::new(&flavor.id),
+                Cell::new(&flavor.name),
+                Cell::new(&flavor.region),
+                Cell::new(&format!("{}", flavor.vcpus)),
+                Cell::new(&human::size(flavor.ram as f64, MB, options.human)),
+                Cell::new(&human::size(flavor.disk as f64, GB, options.human)),
+            ]));
+        }
+
+        Ok(fmt::table(rows, options))
+    }
+}
+
+impl Wide for Vec<Flavor> {
+    type Error = Box<dyn Error + Send + Sync>;
+
+    #[tracing::instrument]
+    fn wide(&self, options: &fmt::Options) -> Result<String, Self::Error> {
+        let mut rows = vec![Row::new(vec![
+            Cell::new("Identifier"),
+            Cell::new("Name"),
+            Cell::new("Region"),
+            Cell::new("vCPUs"),
+            Cell::new("RAM"),
+            Cell::new("Disk"),
+            Cell::new("Type"),
+            Cell::new("OS"),
+            Cell::new("Available"),
+            Cell::new("Inbound bandwidth"),
+            Cell::new("Outbound bandwidth"),
+        ])];
+
+        for flavor in self {
+            let inbound_bandwidth = match flavor.inbound_bandwidth {
+                Some(bandwidth) => format!("{}", bandwidth),
+                None => String::from("<none>"),
+            };
+
+            let outbound_bandwidth = match flavor.outbound_bandwidth {
+                Some(bandwidth) => format!("{}", bandwidth),
+                None => String::from("<none>"),
+            };
+
+            rows.push(Row::new(vec![
+                Cell::new(&flavor.id),
+                Cell::new(&flavor.name),
+                Cell::new(&flavor.region),
+                Cell::new(&format!("{}", flavor.vcpus)),
+                Cell::new(&human::size(flavor.ram as f64, MB, options.human)),
+                Cell::new(&human::size(flavor.disk as f64, GB, options.human)),
+                Cell::new(&flavor.kind),
+                Cell::new(&flavor.os_type),
+                fmt::boolean(flavor.available, options),
+                Cell::new(&inbound_bandwidth),
+                Cell::new(&outbound_bandwidth),
+            ]));
+        }
+
+        Ok(fmt::table(rows, options))
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct RegionService {
+    #[serde(rename = "name")]
+    pub name: String,
+    #[serde(rename = "status")]
+    pub status: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Region {
+    #[serde(rename = "name")]
+    pub name: String,
+    #[serde(rename = "type", default = "crate::ovh::missing")]
+    pub kind: String,
+    #[serde(rename = "status")]
+    pub status: String,
+    #[serde(rename = "continentCode", default = "crate::ovh::missing")]
+    pub continent_code: String,
+    #[serde(rename = "datacenterLocation", default = "crate::ovh::missing")]
+    pub datacenter_location: String,
+    #[serde(rename = "services", default = "crate::ovh::missing")]
+    pub services: Vec<RegionService>,
+}
+
+impl Region {
+    /// Returns the names of the services which are available in the region
+    #[tracing::instrument]
+    pub fn available_services(&self) -> Vec<&str> {
+        self.services
+            .iter()
+            .filter(|service| "UP" == service.status)
+            .map(|service| service.name.as_str())
+            .collect()
+    }
+}
+
+impl Short for Vec<Region> {
+    type Error = Box<dyn Error + Send + Sync>;
+
+    #[tracing::instrument]
+    fn short(&self, options: &fmt::Options) -> Result<String, Self::Error> {
+        let mut rows = vec![Row::new(vec![
+            Cell::new("Name"),
+            Cell::new("Datacenter"),
+            Cell::new("Status"),
+            Cell::new("Services"),
+        ])];
+
+        for region in self {
+            rows.push(Row::new(vec![
+                Cell::new(&region.name),
+                Cell::new(&region.datacenter_location),
+                fmt::status(&region.status, options),
+                Cell::new(&region.available_services().join(", ")),
+            ]));
+        }
+
+        Ok(fmt::table(rows, options))
+    }
+}
+
+impl Wide for Vec<Region> {
+    type Error = Box<dyn Error + Send + Sync>;
+
+    #[tracing::instrument]
+    fn wide(&self, options: &fmt::Options) -> Result<String, Self::Error> {
+        let mut rows = vec![Row::new(vec![
+            Cell::new("Name"),
+            Cell::new("Type"),
+            Cell::new("Continent"),
+            Cell::new("Datacenter"),
+            Cell::new("Status"),
+            Cell::new("Services"),
+        ])];
+
+        for region in self {
+            let services: Vec<String> = region
+                .services
+                .iter()
+                .map(|service| format!("{} ({})", service.name, service.status))
+                .collect();
+
+            rows.push(Row::new(vec![
+                Cell::new(&region.name),
+                Cell::new(&region.kind),
+                Cell::new(&region.continent_code),
+                Cell::new(&region.datacenter_location),
+                fmt::status(&region.status, options),
+                Cell::new(&services.join(", ")),
+            ]));
+        }
+
+        Ok(fmt::table(rows, options))
+    }
+}
+
+#[derive(Clone, Debug)]
+pub enum Period {
+    Current,
+    Forecast,
+}
+
+impl FromStr for Period {
+    type Err = Box<dyn Error + Send + Sync>;
+
+    #[tracing::instrument]
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "current" => Ok(Self::Current),
+            "forecast" => Ok(Self::Forecast),
+            _ => Err(format!("'{}' is not allowed, only 'current' or 'forecast'", s).into()),
+        }
+    }
+}
+
+/// Instances which could be created in a region and the ones already created
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct InstanceQuota {
+    #[serde(rename = "maxInstances")]
+    pub max_instances: u64,
+    #[serde(rename = "usedInstances")]
+    pub used_instances: u64,
+    #[serde(rename = "maxCores", default)]
+    pub max_cores: u64,
+    #[serde(rename = "usedCores", default)]
+    pub used_cores: u64,
+    #[serde(rename = "maxRam", default)]
+    pub max_ram: u64,
+    #[serde(rename = "usedRAM", default)]
+    pub used_ram: u64,
+}
+
+impl InstanceQuota {
+    /// Returns if no instance could be created anymore
+    #[tracing::instrument]
+    pub fn is_reached(&self) -> bool {
+        self.used_instances >= self.max_instances
+    }
+}
+
+/// Quotas of a tenant in a region
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Quota {
+    #[serde(rename = "region")]
+    pub region: String,
+    #[serde(rename = "instance", default)]
+    pub instance: Option<InstanceQuota>,
+}
+
+impl Display for Period {
+    #[tracing::instrument(skip(f))]
+    fn fmt(&self, f: &mut Formatter<'_>) -> stdfmt::Result {
+        match self {
+            Self::Current => write!(f, "current"),
+            Self::Forecast => write!(f, "forecast"),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Quantity {
+    #[serde(rename = "unit")]
+    pub unit: String,
+    #[serde(rename = "value")]
+    pub value: f64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct UsageItem {
+    #[serde(rename = "region", default)]
+    pub region: Option<String>,
+    #[serde(rename = "reference", default)]
+    pub reference: Option<String>,
+    #[serde(rename = "quantity", default)]
+    pub quantity: Option<Quantity>,
+    #[serde(rename = "totalPrice", default)]
+    pub total_price: f64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct UsagePeriod {
+    #[serde(rename = "from")]
+    pub from: String,
+    #[serde(rename = "to")]
+    pub to: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Usage {
+    #[serde(rename = "period")]
+    pub period: UsagePeriod,
+    #[serde(rename = "hourlyUsage", default)]
+    pub hourly_usage: Option<BTreeMap<String, Vec<UsageItem>>>,
+    #[serde(rename = "monthlyUsage", default)]
+    pub monthly_usage: Option<BTreeMap<String, Vec<UsageItem>>>,
+    /// Code of the currency of the account, e.g. 'EUR', only used to write
+    /// human readable prices
+    #[serde(skip)]
+    pub currency: Option<String>,
+}
+
+impl Usage {
+    /// Returns usage items along with their resource type, e.g. 'hourly/instance'
+    #[tracing::instrument]
+    pub fn items(&self) -> Vec<(String, &UsageItem)> {
+        let mut items = vec![];
+        for (billing, usage) in [
+            ("hourly", &self.hourly_usage),
+            ("monthly", &self.monthly_usage),
+        ] {
+            if let Some(usage) = usage {
+                for (kind, resources) in usage {
+                    for item in resources {
+                        items.push((format!("{}/{}", billing, kind), item));
+                    }
+                }
+            }
+        }
+
+        items
+    }
+
+    /// Returns the estimated cost of the period
+    #[tracing::instrument]
+    pub fn total(&self) -> f64 {
+        self.items().iter().map(|(_, item)| item.total_price).sum()
+    }
+}
+
+impl Short for Usage {
+    type Error = Box<dyn Error + Send + Sync>;
+
+    #[tracing::instrument]
+    fn short(&self, options: &fmt::Options) -> Result<String, Self::Error> {
+        let mut rows = vec![Row::new(vec![
+            Cell::new("Type"),
+            Cell::new("Quantity"),
+            Cell::new("Cost"),
+        ])];
+
+        let mut kinds: BTreeMap<String, (f64, String, f64)> = BTreeMap::new();
+        for (kind, item) in self.items() {
+            let entry = kinds.entry(kind).or_insert((0.0, String::new(), 0.0));
+            if let Some(quantity) = &item.quantity {
+                entry.0 += quantity.value;
+                entry.1 = quantity.unit.to_owned();
+            }
+
+            entry.2 += item.total_price;
+        }
+
+        for (kind, (quantity, unit, cost)) in kinds {
+            rows.push(Row::new(vec![
+                Cell::new(&kind),
+                Cell::new(&format!("{:.2} {}", quantity, unit)),
+                Cell::new(&human::price(cost, self.currency.as_deref(), options.human)),
+            ]));
+        }
+
+        rows.push(Row::new(vec![
+            Cell::new("Total"),
+            Cell::new(""),
+            Cell::new(&human::price(
+                self.total(),
+                self.currency.as_deref(),
+                options.human,
+            )),
+        ]));
+
+        Ok(fmt::table(rows, options))
+    }
+}
+
+impl Wide for Usage {
+    type Error = Box<dyn Error + Send + Sync>;
+
+    #[tracing::instrument]
+    fn wide(&self, options: &fmt::Options) -> Result<String, Self::Error> {
+        let mut rows = vec![Row::new(vec![
+            Cell::new("Type"),
+            Cell::new("Region"),
+            Cell::new("Reference"),
+            Cell::new("Quantity"),
+            Cell::new("Cost"),
+        ])];
+
+        for (kind, item) in self.items() {
+            let region = match item.region.to_owned() {
+                Some(region) => region,
+                None => "<none>".into(),
+            };
+
+            let reference = match item.reference.to_owned() {
+                Some(reference) => reference,
+                None => "<none>".into(),
+            };
+
+            let quantity = match &item.quantity {
+                Some(quantity) => format!("{:.2} {}", quantity.value, quantity.unit),
+                None => "<none>".into(),
+            };
+
+            rows.push(Row::new(vec![
+                Cell::new(&kind),
+                Cell::new(&region),
+                Cell::new(&reference),
+                Cell::new(&quantity),
+                Cell::new(&human::price(
+                    item.total_price,
+                    self.currency.as_deref(),
+                    options.human,
+                )),
+            ]));
+        }
+
+        rows.push(Row::new(vec![
+            Cell::new("Total"),
+            Cell::new(""),
+            Cell::new(""),
+            Cell::new(""),
+            Cell::new(&human::price(
+                self.total(),
+                self.currency.as_deref(),
+                options.human,
+            )),
+        ]));
+
+        Ok(fmt::table(rows, options))
     }
 }
 
 #[tracing::instrument(skip(client))]
 pub async fn list_tenants(client: &Client) -> types::Result<Vec<Tenant>> {
     let ids: Vec<String> = client
-        .get("cloud/project")
+        .get_ids("cloud/project")
         .await
-        .map_err(|err| format!("could not retrieve tenants, {}", err))?;
-
-    let mut tenants = vec![];
-    for id in ids {
-        tenants.push(
-            client
-                .get(&format!("cloud/project/{}", id))
-                .await
-                .map_err(|err| format!("could not retrieve tenant '{}', {}", id, err))?,
-        );
+        .map_err(|err| Context::new("could not retrieve tenants", err))?;
+
+    match client.get_batch("cloud/project", &ids).await {
+        Ok(items) => return Ok(items),
+        Err(err)
+            if types::find::<ApiError>(err.as_ref())
+                .map_or(false, ApiError::is_batch_unsupported) =>
+        {
+            debug!(
+                "could not use a batch request, fallback on a request per item, {}",
+                err
+            )
+        }
+        Err(err) => return Err(Context::new("could not retrieve tenants", err).into()),
     }
 
-    Ok(tenants)
+    client
+        .get_each("cloud/project", &ids, |id| {
+            format!("could not retrieve tenant '{}'", id)
+        })
+        .await
 }
 
 #[tracing::instrument(skip(client))]
@@ -281,9 +947,166 @@ pub async fn list_instances(client: &Client, tenant: &str) -> types::Result<Vec<
         .get(&format!("cloud/project/{}/instance", tenant))
         .await
         .map_err(|err| {
-            format!(
-                "could not retrieve instance for tenant '{}', {}",
-                tenant, err
+            Context::new(
+                format!("could not retrieve instance for tenant '{}'", tenant),
+                err,
+            )
+        })?)
+}
+
+/// Returns the path of an instance of the tenant
+#[tracing::instrument]
+pub fn instance_path(tenant: &str, id: &str) -> String {
+    format!("cloud/project/{}/instance/{}", tenant, id)
+}
+
+#[tracing::instrument(skip(client))]
+pub async fn get_instance(client: &Client, tenant: &str, id: &str) -> types::Result<Instance> {
+    Ok(client
+        .get(&instance_path(tenant, id))
+        .await
+        .map_err(|err| {
+            Context::new(
+                format!(
+                    "could not retrieve instance '{}' of tenant '{}'",
+                    id, tenant
+                ),
+                err,
+            )
+        })?)
+}
+
+#[tracing::instrument(skip(client))]
+pub async fn create_instance(
+    client: &Client,
+    tenant: &str,
+    opts: &InstanceCreation,
+) -> types::Result<Instance> {
+    opts.validate()?;
+
+    Ok(client
+        .post(&format!("cloud/project/{}/instance", tenant), opts)
+        .await
+        .map_err(|err| {
+            Context::new(
+                format!(
+                    "could not create instance '{}' on tenant '{}'",
+                    opts.name, tenant
+                ),
+                err,
+            )
+        })?)
+}
+
+#[tracing::instrument(skip(client))]
+pub async fn rename_instance(
+    client: &Client,
+    tenant: &str,
+    id: &str,
+    name: &str,
+) -> types::Result<()> {
+    let opts = InstanceRename {
+        instance_name: name.to_string(),
+    };
+
+    opts.validate()?;
+
+    Ok(client
+        .put(&format!("cloud/project/{}/instance/{}", tenant, id), &opts)
+        .await
+        .map_err(|err| {
+            Context::new(
+                format!("could not rename instance '{}' of tenant '{}'", id, tenant),
+                err,
             )
         })?)
 }
+
+#[tracing::instrument(skip(client))]
+pub async fn list_images(client: &Client, tenant: &str) -> types::Result<Vec<Image>> {
+    Ok(client
+        .get(&format!("cloud/project/{}/image", tenant))
+        .await
+        .map_err(|err| {
+            Context::new(
+                format!("could not retrieve images for tenant '{}'", tenant),
+                err,
+            )
+        })?)
+}
+
+#[tracing::instrument(skip(client))]
+pub async fn list_flavors(client: &Client, tenant: &str) -> types::Result<Vec<Flavor>> {
+    Ok(client
+        .get(&format!("cloud/project/{}/flavor", tenant))
+        .await
+        .map_err(|err| {
+            Context::new(
+                format!("could not retrieve flavors for tenant '{}'", tenant),
+                err,
+            )
+        })?)
+}
+
+#[tracing::instrument(skip(client))]
+pub async fn get_usage(client: &Client, tenant: &str, period: &Period) -> types::Result<Usage> {
+    Ok(client
+        .get(&format!("cloud/project/{}/usage/{}", tenant, period))
+        .await
+        .map_err(|err| {
+            Context::new(
+                format!("could not retrieve {} usage of tenant '{}'", period, tenant),
+                err,
+            )
+        })?)
+}
+
+#[tracing::instrument(skip(client))]
+pub async fn list_quotas(client: &Client, tenant: &str) -> types::Result<Vec<Quota>> {
+    Ok(client
+        .get(&format!("cloud/project/{}/quota", tenant))
+        .await
+        .map_err(|err| {
+            Context::new(
+                format!("could not retrieve quotas of tenant '{}'", tenant),
+                err,
+            )
+        })?)
+}
+
+#[tracing::instrument(skip(client))]
+pub async fn list_regions(client: &Client, tenant: &str) -> types::Result<Vec<Region>> {
+    let path = format!("cloud/project/{}/region", tenant);
+    let names: Vec<String> = client.get_ids(&path).await.map_err(|err| {
+        Context::new(
+            format!("could not retrieve regions of tenant '{}'", tenant),
+            err,
+        )
+    })?;
+
+    match client.get_batch(&path, &names).await {
+        Ok(items) => return Ok(items),
+        Err(err)
+            if types::find::<ApiError>(err.as_ref())
+                .map_or(false, ApiError::is_batch_unsupported) =>
+        {
+            debug!(
+                "could not use a batch request, fallback on a request per item, {}",
+                err
+            )
+        }
+        Err(err) => {
+            return Err(Context::new(
+                format!("could not retrieve regions of tenant '{}'", tenant),
+                err,
+            )
+            .into())
+        }
+    }
+
+    client
+        .get_each(&path, &names, |name| {
+            format!("could not retrieve region '{}'", name)
+        })
+        .await
+}
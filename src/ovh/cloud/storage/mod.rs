@@ -0,0 +1,140 @@
+//! # Storage module
+//!
+//! This module provide structure to interact with the object storage api
+use std::error::Error;
+
+use prettytable::{Cell, Row};
+use serde::{Deserialize, Serialize};
+
+use crate::cmd::fmt::{self, Short, Wide};
+use crate::ovh::{Client, RestClient};
+use crate::util::human;
+use crate::util::types::{self, Context};
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Container {
+    #[serde(rename = "id")]
+    pub id: String,
+    #[serde(rename = "name")]
+    pub name: String,
+    #[serde(rename = "region")]
+    pub region: String,
+    #[serde(rename = "storedObjects")]
+    pub stored_objects: i64,
+    #[serde(rename = "storedBytes")]
+    pub stored_bytes: i64,
+    /// Whether the objects could be read without authentication, it is not
+    /// part of the listing, so it is only retrieved when asked for
+    #[serde(rename = "public", skip_deserializing)]
+    pub public: Option<bool>,
+}
+
+impl Short for Vec<Container> {
+    type Error = Box<dyn Error + Send + Sync>;
+
+    #[tracing::instrument]
+    fn short(&self, options: &fmt::Options) -> Result<String, Self::Error> {
+        let mut rows = vec![Row::new(vec![
+            Cell::new("Name"),
+            Cell::new("Region"),
+            Cell::new("Objects"),
+            Cell::new("Size"),
+        ])];
+
+        for container in self {
+            rows.push(Row::new(vec![
+                Cell::new(&container.name),
+                Cell::new(&container.region),
+                Cell::new(&container.stored_objects.to_string()),
+                Cell::new(&human::size(
+                    container.stored_bytes as f64,
+                    1.0,
+                    options.human,
+                )),
+            ]));
+        }
+
+        Ok(fmt::table(rows, options))
+    }
+}
+
+impl Wide for Vec<Container> {
+    type Error = Box<dyn Error + Send + Sync>;
+
+    #[tracing::instrument]
+    fn wide(&self, options: &fmt::Options) -> Result<String, Self::Error> {
+        let mut rows = vec![Row::new(vec![
+            Cell::new("Identifier"),
+            Cell::new("Name"),
+            Cell::new("Region"),
+            Cell::new("Objects"),
+            Cell::new("Size"),
+            Cell::new("Stored bytes"),
+            Cell::new("Visibility"),
+        ])];
+
+        for container in self {
+            let visibility = match container.public {
+                Some(true) => "public",
+                Some(false) => "private",
+                None => "<unknown>",
+            };
+
+            rows.push(Row::new(vec![
+                Cell::new(&container.id),
+                Cell::new(&container.name),
+                Cell::new(&container.region),
+                Cell::new(&container.stored_objects.to_string()),
+                Cell::new(&human::size(
+                    container.stored_bytes as f64,
+                    1.0,
+                    options.human,
+                )),
+                Cell::new(&container.stored_bytes.to_string()),
+                Cell::new(visibility),
+            ]));
+        }
+
+        Ok(fmt::table(rows, options))
+    }
+}
+
+/// Details of a container, of which only the visibility is used
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ContainerDetails {
+    #[serde(rename = "public")]
+    pub public: bool,
+}
+
+#[tracing::instrument(skip(client))]
+pub async fn list_containers(client: &Client, tenant: &str) -> types::Result<Vec<Container>> {
+    Ok(client
+        .get(&format!("cloud/project/{}/storage", tenant))
+        .await
+        .map_err(|err| {
+            Context::new(
+                format!("could not list containers on tenant '{}'", tenant),
+                err,
+            )
+        })?)
+}
+
+/// Returns whether the objects of the container could be read without
+/// authentication
+#[tracing::instrument(skip(client))]
+pub async fn is_public(client: &Client, tenant: &str, id: &str) -> types::Result<bool> {
+    let details: ContainerDetails = client
+        .get(&format!("cloud/project/{}/storage/{}", tenant, id))
+        .await
+        .map_err(|err| {
+            Context::new(
+                format!(
+                    "could not retrieve container '{}' on tenant '{}'",
+                    id, tenant
+                ),
+                err,
+            )
+        })?;
+
+    Ok(details.public)
+}
@@ -0,0 +1,248 @@
+//! # Volume module
+//!
+//! This module provide structure to interact with the block storage api
+use std::error::Error;
+
+use prettytable::{Cell, Row};
+use serde::{Deserialize, Serialize};
+
+use crate::cmd::fmt::{self, Short, Wide};
+use crate::ovh::{Client, RestClient};
+use crate::util::human::{self, GB};
+use crate::util::types::{self, Context, Validation};
+
+/// Types of volume accepted by the api
+pub const VOLUME_TYPES: [&str; 3] = ["classic", "high-speed", "high-speed-gen2"];
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Volume {
+    #[serde(rename = "id")]
+    pub id: String,
+    #[serde(rename = "name", default)]
+    pub name: Option<String>,
+    #[serde(rename = "description", default)]
+    pub description: Option<String>,
+    #[serde(rename = "region")]
+    pub region: String,
+    #[serde(rename = "type")]
+    pub kind: String,
+    /// Size of the volume, in gigabytes
+    #[serde(rename = "size")]
+    pub size: i64,
+    #[serde(rename = "status")]
+    pub status: String,
+    /// Identifiers of the instances to which the volume is attached
+    #[serde(rename = "attachedTo", default = "crate::ovh::missing")]
+    pub attached_to: Vec<String>,
+    #[serde(rename = "bootable", default = "crate::ovh::missing")]
+    pub bootable: bool,
+    #[serde(rename = "creationDate", default = "crate::ovh::missing")]
+    pub creation_date: String,
+}
+
+impl Short for Vec<Volume> {
+    type Error = Box<dyn Error + Send + Sync>;
+
+    #[tracing::instrument]
+    fn short(&self, options: &fmt::Options) -> Result<String, Self::Error> {
+        let mut rows = vec![Row::new(vec![
+            Cell::new("Identifier"),
+            Cell::new("Name"),
+            Cell::new("Region"),
+            Cell::new("Size"),
+            Cell::new("Status"),
+        ])];
+
+        for volume in self {
+            let name = match volume.name.to_owned() {
+                Some(name) => name,
+                None => "<none>".into(),
+            };
+
+            rows.push(Row::new(vec![
+                Cell::new(&volume.id),
+                Cell::new(&name),
+                Cell::new(&volume.region),
+                Cell::new(&human::size(volume.size as f64, GB, options.human)),
+                fmt::status(&volume.status, options),
+            ]));
+        }
+
+        Ok(fmt::table(rows, options))
+    }
+}
+
+impl Wide for Vec<Volume> {
+    type Error = Box<dyn Error + Send + Sync>;
+
+    #[tracing::instrument]
+    fn wide(&self, options: &fmt::Options) -> Result<String, Self::Error> {
+        let mut rows = vec![Row::new(vec![
+            Cell::new("Identifier"),
+            Cell::new("Name"),
+            Cell::new("Description"),
+            Cell::new("Region"),
+            Cell::new("Type"),
+            Cell::new("Size"),
+            Cell::new("Status"),
+            Cell::new("Attached to"),
+            Cell::new("Bootable"),
+            Cell::new("Creation date"),
+        ])];
+
+        for volume in self {
+            let name = match volume.name.to_owned() {
+                Some(name) => name,
+                None => "<none>".into(),
+            };
+
+            let description = match volume.description.to_owned() {
+                Some(description) => description,
+                None => "<none>".into(),
+            };
+
+            let attached_to = match volume.attached_to.is_empty() {
+                true => "<none>".into(),
+                false => volume.attached_to.join(", "),
+            };
+
+            rows.push(Row::new(vec![
+                Cell::new(&volume.id),
+                Cell::new(&name),
+                Cell::new(&description),
+                Cell::new(&volume.region),
+                Cell::new(&volume.kind),
+                Cell::new(&human::size(volume.size as f64, GB, options.human)),
+                fmt::status(&volume.status, options),
+                Cell::new(&attached_to),
+                fmt::boolean(volume.bootable, options),
+                Cell::new(&volume.creation_date),
+            ]));
+        }
+
+        Ok(fmt::table(rows, options))
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct VolumeCreation {
+    #[serde(rename = "region")]
+    pub region: String,
+    /// Size of the volume, in gigabytes
+    #[serde(rename = "size")]
+    pub size: i64,
+    #[serde(rename = "type")]
+    pub kind: String,
+    #[serde(rename = "name", skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(rename = "description", skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+}
+
+impl VolumeCreation {
+    /// Check the creation request before sending it to the api, returns every
+    /// problem found at once
+    #[tracing::instrument]
+    pub fn validate(&self) -> Result<(), Validation> {
+        let mut validation = Validation::new("volume creation");
+
+        if self.region.is_empty() {
+            validation.push("region must not be empty");
+        }
+
+        if self.size < 1 {
+            validation.push(format!("size '{}' must be at least 1 gigabyte", self.size));
+        }
+
+        if !VOLUME_TYPES.contains(&self.kind.as_str()) {
+            validation.push(format!(
+                "unknown volume type '{}', expect one of {}",
+                self.kind,
+                VOLUME_TYPES.join(", ")
+            ));
+        }
+
+        validation.check()
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Attachment {
+    #[serde(rename = "instanceId")]
+    pub instance_id: String,
+}
+
+#[tracing::instrument(skip(client))]
+pub async fn list(client: &Client, tenant: &str) -> types::Result<Vec<Volume>> {
+    Ok(client
+        .get(&format!("cloud/project/{}/volume", tenant))
+        .await
+        .map_err(|err| {
+            Context::new(
+                format!("could not list volumes on tenant '{}'", tenant),
+                err,
+            )
+        })?)
+}
+
+#[tracing::instrument(skip(client))]
+pub async fn create(client: &Client, tenant: &str, opts: &VolumeCreation) -> types::Result<Volume> {
+    opts.validate()?;
+
+    Ok(client
+        .post(&format!("cloud/project/{}/volume", tenant), opts)
+        .await
+        .map_err(|err| Context::new("could not create volume", err))?)
+}
+
+#[tracing::instrument(skip(client))]
+pub async fn attach(
+    client: &Client,
+    tenant: &str,
+    volume_id: &str,
+    instance_id: &str,
+) -> types::Result<Volume> {
+    Ok(client
+        .post(
+            &format!("cloud/project/{}/volume/{}/attach", tenant, volume_id),
+            &Attachment {
+                instance_id: instance_id.to_string(),
+            },
+        )
+        .await
+        .map_err(|err| {
+            Context::new(
+                format!(
+                    "could not attach volume '{}' to instance '{}'",
+                    volume_id, instance_id
+                ),
+                err,
+            )
+        })?)
+}
+
+#[tracing::instrument(skip(client))]
+pub async fn detach(
+    client: &Client,
+    tenant: &str,
+    volume_id: &str,
+    instance_id: &str,
+) -> types::Result<Volume> {
+    Ok(client
+        .post(
+            &format!("cloud/project/{}/volume/{}/detach", tenant, volume_id),
+            &Attachment {
+                instance_id: instance_id.to_string(),
+            },
+        )
+        .await
+        .map_err(|err| {
+            Context::new(
+                format!(
+                    "could not detach volume '{}' from instance '{}'",
+                    volume_id, instance_id
+                ),
+                err,
+            )
+        })?)
+}
@@ -3,30 +3,31 @@
 //! This module provide structure to interact with the server api
 use std::error::Error;
 
-use prettytable::{Cell, Row, Table};
+use prettytable::{Cell, Row};
 use serde::{Deserialize, Serialize};
+use tracing::debug;
 
-use crate::cmd::fmt::{Short, Wide};
-use crate::ovh::{Client, RestClient};
-use crate::util::types;
+use crate::cmd::fmt::{self, Prometheus, Short, Wide};
+use crate::ovh::{task, Client, RestClient};
+use crate::util::types::{self, Context};
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct Server {
-    #[serde(rename = "reverse")]
+    #[serde(rename = "reverse", default = "crate::ovh::missing")]
     pub reverse: String,
     #[serde(rename = "state")]
     pub state: String,
     #[serde(rename = "monitoring")]
     pub monitoring: bool,
-    #[serde(rename = "datacenter")]
+    #[serde(rename = "datacenter", default = "crate::ovh::missing")]
     pub data_center: String,
-    #[serde(rename = "rack")]
+    #[serde(rename = "rack", default = "crate::ovh::missing")]
     pub rack: String,
-    #[serde(rename = "os")]
+    #[serde(rename = "os", default = "crate::ovh::missing")]
     pub os: String,
     #[serde(rename = "name")]
     pub name: String,
-    #[serde(rename = "linkSpeed")]
+    #[serde(rename = "linkSpeed", default = "crate::ovh::missing")]
     pub link_speed: i64,
     #[serde(rename = "ip")]
     pub ip: String,
@@ -34,11 +35,17 @@ pub struct Server {
     pub server_id: i64,
 }
 
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Monitoring {
+    #[serde(rename = "monitoring")]
+    pub monitoring: bool,
+}
+
 impl Short for Vec<Server> {
     type Error = Box<dyn Error + Send + Sync>;
 
     #[tracing::instrument]
-    fn short(&self) -> Result<String, Self::Error> {
+    fn short(&self, options: &fmt::Options) -> Result<String, Self::Error> {
         let mut rows = vec![Row::new(vec![
             Cell::new("Identifier"),
             Cell::new("Name"),
@@ -52,12 +59,12 @@ impl Short for Vec<Server> {
                 Cell::new(&format!("{}", server.server_id)),
                 Cell::new(&server.name),
                 Cell::new(&server.ip),
-                Cell::new(&server.state),
+                fmt::status(&server.state, options),
                 Cell::new(&server.reverse),
             ]));
         }
 
-        Ok(format!("{}", Table::init(rows)))
+        Ok(fmt::table(rows, options))
     }
 }
 
@@ -65,7 +72,7 @@ impl Wide for Vec<Server> {
     type Error = Box<dyn Error + Send + Sync>;
 
     #[tracing::instrument]
-    fn wide(&self) -> Result<String, Self::Error> {
+    fn wide(&self, options: &fmt::Options) -> Result<String, Self::Error> {
         let mut rows = vec![Row::new(vec![
             Cell::new("Identifier"),
             Cell::new("Name"),
@@ -84,9 +91,9 @@ impl Wide for Vec<Server> {
                 Cell::new(&format!("{}", server.server_id)),
                 Cell::new(&server.name),
                 Cell::new(&server.ip),
-                Cell::new(&server.state),
+                fmt::status(&server.state, options),
                 Cell::new(&server.reverse),
-                Cell::new(&format!("{}", server.monitoring)),
+                fmt::boolean(server.monitoring, options),
                 Cell::new(&server.os),
                 Cell::new(&server.data_center),
                 Cell::new(&server.rack),
@@ -94,26 +101,361 @@ impl Wide for Vec<Server> {
             ]));
         }
 
-        Ok(format!("{}", Table::init(rows)))
+        Ok(fmt::table(rows, options))
+    }
+}
+
+impl Prometheus for Vec<Server> {
+    type Error = Box<dyn Error + Send + Sync>;
+
+    #[tracing::instrument]
+    fn prometheus(&self) -> Result<String, Self::Error> {
+        let mut lines = vec![
+            String::from("# HELP ovhctl_dedicated_server Dedicated server, labelled by its state"),
+            String::from("# TYPE ovhctl_dedicated_server gauge"),
+        ];
+
+        for server in self {
+            lines.push(fmt::sample(
+                "ovhctl_dedicated_server",
+                &[
+                    ("name", &server.name),
+                    ("datacenter", &server.data_center),
+                    ("state", &server.state),
+                    ("os", &server.os),
+                ],
+                1.0,
+            ));
+        }
+
+        Ok(lines.join("\n"))
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Template {
+    #[serde(rename = "templateName")]
+    pub name: String,
+    #[serde(rename = "family", default = "crate::ovh::missing")]
+    pub family: String,
+    #[serde(rename = "distribution", default = "crate::ovh::missing")]
+    pub distribution: String,
+    #[serde(rename = "bitFormat", default = "crate::ovh::missing")]
+    pub bit_format: i64,
+    #[serde(rename = "description", default = "crate::ovh::missing")]
+    pub description: String,
+}
+
+impl Short for Vec<Template> {
+    type Error = Box<dyn Error + Send + Sync>;
+
+    #[tracing::instrument]
+    fn short(&self, options: &fmt::Options) -> Result<String, Self::Error> {
+        let mut rows = vec![Row::new(vec![
+            Cell::new("Name"),
+            Cell::new("Family"),
+            Cell::new("Distribution"),
+        ])];
+
+        for template in self {
+            rows.push(Row::new(vec![
+                Cell::new(&template.name),
+                Cell::new(&template.family),
+                Cell::new(&template.distribution),
+            ]));
+        }
+
+        Ok(fmt::table(rows, options))
+    }
+}
+
+impl Wide for Vec<Template> {
+    type Error = Box<dyn Error + Send + Sync>;
+
+    #[tracing::instrument]
+    fn wide(&self, options: &fmt::Options) -> Result<String, Self::Error> {
+        let mut rows = vec![Row::new(vec![
+            Cell::new("Name"),
+            Cell::new("Family"),
+            Cell::new("Distribution"),
+            Cell::new("Bit format"),
+            Cell::new("Description"),
+        ])];
+
+        for template in self {
+            rows.push(Row::new(vec![
+                Cell::new(&template.name),
+                Cell::new(&template.family),
+                Cell::new(&template.distribution),
+                Cell::new(&format!("{}", template.bit_format)),
+                Cell::new(&template.description),
+            ]));
+        }
+
+        Ok(fmt::table(rows, options))
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct InstallationDetails {
+    #[serde(rename = "sshKeyName", skip_serializing_if = "Option::is_none")]
+    pub ssh_key_name: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Installation {
+    #[serde(rename = "templateName")]
+    pub template_name: String,
+    #[serde(rename = "details")]
+    pub details: InstallationDetails,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Task {
+    #[serde(rename = "taskId")]
+    pub task_id: i64,
+    #[serde(rename = "function")]
+    pub function: String,
+    #[serde(rename = "status")]
+    pub status: String,
+    #[serde(rename = "startDate")]
+    pub start_date: Option<String>,
+    #[serde(rename = "doneDate")]
+    pub done_date: Option<String>,
+    #[serde(rename = "comment")]
+    pub comment: Option<String>,
+}
+
+impl Task {
+    /// Returns if the task has been successfully executed
+    #[tracing::instrument]
+    pub fn is_done(&self) -> bool {
+        "done" == self.status
+    }
+}
+
+impl task::Task for Task {
+    #[tracing::instrument]
+    fn id(&self) -> String {
+        self.task_id.to_string()
+    }
+
+    #[tracing::instrument]
+    fn status(&self) -> &str {
+        &self.status
+    }
+
+    #[tracing::instrument]
+    fn is_finished(&self) -> bool {
+        matches!(
+            self.status.as_str(),
+            "done" | "cancelled" | "customerError" | "ovhError"
+        )
+    }
+}
+
+impl Short for Vec<Task> {
+    type Error = Box<dyn Error + Send + Sync>;
+
+    #[tracing::instrument]
+    fn short(&self, options: &fmt::Options) -> Result<String, Self::Error> {
+        let mut rows = vec![Row::new(vec![
+            Cell::new("Identifier"),
+            Cell::new("Function"),
+            Cell::new("Status"),
+        ])];
+
+        for task in self {
+            rows.push(Row::new(vec![
+                Cell::new(&format!("{}", task.task_id)),
+                Cell::new(&task.function),
+                fmt::status(&task.status, options),
+            ]));
+        }
+
+        Ok(fmt::table(rows, options))
+    }
+}
+
+impl Wide for Vec<Task> {
+    type Error = Box<dyn Error + Send + Sync>;
+
+    #[tracing::instrument]
+    fn wide(&self, options: &fmt::Options) -> Result<String, Self::Error> {
+        let mut rows = vec![Row::new(vec![
+            Cell::new("Identifier"),
+            Cell::new("Function"),
+            Cell::new("Status"),
+            Cell::new("Start date"),
+            Cell::new("Done date"),
+            Cell::new("Comment"),
+        ])];
+
+        for task in self {
+            let start_date = match task.start_date.to_owned() {
+                Some(start_date) => start_date,
+                None => "<none>".into(),
+            };
+
+            let done_date = match task.done_date.to_owned() {
+                Some(done_date) => done_date,
+                None => "<none>".into(),
+            };
+
+            let comment = match task.comment.to_owned() {
+                Some(comment) => comment,
+                None => "<none>".into(),
+            };
+
+            rows.push(Row::new(vec![
+                Cell::new(&format!("{}", task.task_id)),
+                Cell::new(&task.function),
+                fmt::status(&task.status, options),
+                Cell::new(&start_date),
+                Cell::new(&done_date),
+                Cell::new(&comment),
+            ]));
+        }
+
+        Ok(fmt::table(rows, options))
     }
 }
 
 #[tracing::instrument(skip(client))]
 pub async fn list_servers(client: &Client) -> types::Result<Vec<Server>> {
     let ids: Vec<String> = client
-        .get("dedicated/server")
+        .get_ids("dedicated/server")
+        .await
+        .map_err(|err| Context::new("could not retrieve list of server", err))?;
+
+    match client.get_batch("dedicated/server", &ids).await {
+        Ok(items) => return Ok(items),
+        Err(err) => debug!(
+            "could not use a batch request, fallback on a request per item, {}",
+            err
+        ),
+    }
+
+    client
+        .get_each("dedicated/server", &ids, |id| {
+            format!("could not retrieve server '{}'", id)
+        })
+        .await
+}
+
+#[tracing::instrument(skip(client))]
+pub async fn get_server(client: &Client, name: &str) -> types::Result<Server> {
+    Ok(client
+        .get(&format!("dedicated/server/{}", name))
+        .await
+        .map_err(|err| Context::new(format!("could not retrieve server '{}'", name), err))?)
+}
+
+/// Enable or disable the monitoring of the server by ovh, which is notified and
+/// intervenes when the server stops answering
+#[tracing::instrument(skip(client))]
+pub async fn set_monitoring(client: &Client, name: &str, enabled: bool) -> types::Result<()> {
+    Ok(client
+        .put(
+            &format!("dedicated/server/{}", name),
+            &Monitoring {
+                monitoring: enabled,
+            },
+        )
+        .await
+        .map_err(|err| {
+            Context::new(
+                format!("could not set monitoring of server '{}'", name),
+                err,
+            )
+        })?)
+}
+
+#[tracing::instrument(skip(client))]
+pub async fn list_templates(client: &Client) -> types::Result<Vec<Template>> {
+    let names: Vec<String> = client
+        .get_ids("dedicated/installationTemplate")
+        .await
+        .map_err(|err| Context::new("could not retrieve list of installation templates", err))?;
+
+    match client
+        .get_batch("dedicated/installationTemplate", &names)
         .await
-        .map_err(|err| format!("could not retrieve list of server, {}", err))?;
-
-    let mut servers = vec![];
-    for id in ids {
-        servers.push(
-            client
-                .get(&format!("dedicated/server/{}", id))
-                .await
-                .map_err(|err| format!("could not retrieve server '{}', {}", id, err))?,
-        );
+    {
+        Ok(items) => return Ok(items),
+        Err(err) => debug!(
+            "could not use a batch request, fallback on a request per item, {}",
+            err
+        ),
     }
 
-    Ok(servers)
+    client
+        .get_each("dedicated/installationTemplate", &names, |name| {
+            format!("could not retrieve installation template '{}'", name)
+        })
+        .await
+}
+
+#[tracing::instrument(skip(client))]
+pub async fn install(
+    client: &Client,
+    name: &str,
+    installation: &Installation,
+) -> types::Result<Task> {
+    Ok(client
+        .post(
+            &format!("dedicated/server/{}/install/start", name),
+            installation,
+        )
+        .await
+        .map_err(|err| {
+            Context::new(
+                format!(
+                    "could not install template '{}' on server '{}'",
+                    installation.template_name, name
+                ),
+                err,
+            )
+        })?)
+}
+
+#[tracing::instrument(skip(client))]
+pub async fn list_tasks(client: &Client, name: &str) -> types::Result<Vec<Task>> {
+    let path = format!("dedicated/server/{}/task", name);
+    let ids: Vec<i64> = client.get_ids(&path).await.map_err(|err| {
+        Context::new(
+            format!("could not retrieve list of task of server '{}'", name),
+            err,
+        )
+    })?;
+
+    match client.get_batch(&path, &ids).await {
+        Ok(items) => return Ok(items),
+        Err(err) => debug!(
+            "could not use a batch request, fallback on a request per item, {}",
+            err
+        ),
+    }
+
+    client
+        .get_each(&path, &ids, |id| {
+            format!("could not retrieve task '{}' of server '{}'", id, name)
+        })
+        .await
+}
+
+/// Returns the path of a task of the server
+#[tracing::instrument]
+pub fn task_path(name: &str, id: &i64) -> String {
+    format!("dedicated/server/{}/task/{}", name, id)
+}
+
+#[tracing::instrument(skip(client))]
+pub async fn get_task(client: &Client, name: &str, id: &i64) -> types::Result<Task> {
+    Ok(client.get(&task_path(name, id)).await.map_err(|err| {
+        Context::new(
+            format!("could not retrieve task '{}' of server '{}'", id, name),
+            err,
+        )
+    })?)
 }
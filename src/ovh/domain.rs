@@ -1,33 +1,65 @@
 //! # Domain module
 //!
 //! This module provide structure to interact with the domain api
+use std::collections::BTreeSet;
 use std::error::Error;
-use std::net::IpAddr;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
 
-use prettytable::{Cell, Row, Table};
+use prettytable::{Cell, Row};
 use serde::{Deserialize, Serialize};
+use tracing::debug;
 
-use crate::cmd::fmt::{Short, Wide};
-use crate::ovh::{Client, RestClient};
-use crate::util::types;
+use crate::cmd::fmt::{self, Short, Wide};
+use crate::ovh::{ApiError, Client, RestClient};
+use crate::util::types::{self, Context, Validation};
+
+/// Record types accepted by the ovh api
+pub const FIELD_TYPES: [&str; 17] = [
+    "A", "AAAA", "CAA", "CNAME", "DKIM", "DMARC", "DNAME", "LOC", "MX", "NAPTR", "NS", "PTR",
+    "SPF", "SRV", "SSHFP", "TLSA", "TXT",
+];
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct Zone {
     #[serde(rename = "name")]
     pub name: String,
-    #[serde(rename = "dnssecSupported")]
+    #[serde(rename = "dnssecSupported", default = "crate::ovh::missing")]
     pub dnssec_supported: bool,
-    #[serde(rename = "hasDnsAnycast")]
+    #[serde(rename = "hasDnsAnycast", default = "crate::ovh::missing")]
     pub has_dns_anycast: bool,
-    #[serde(rename = "nameServers")]
+    #[serde(rename = "nameServers", default = "crate::ovh::missing")]
     pub name_servers: Vec<String>,
+    /// Record count and serial of the zone, only retrieved when asked for as it
+    /// costs requests for each zone
+    #[serde(
+        rename = "health",
+        skip_deserializing,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub health: Option<ZoneHealth>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct ZoneHealth {
+    /// Number of records in the zone, none if it could not be retrieved
+    #[serde(rename = "records")]
+    pub records: Option<usize>,
+    /// Serial of the start of authority record, none if it could not be retrieved
+    #[serde(rename = "serial")]
+    pub serial: Option<i64>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Soa {
+    #[serde(rename = "serial")]
+    pub serial: i64,
 }
 
 impl Short for Vec<Zone> {
     type Error = Box<dyn Error + Send + Sync>;
 
     #[tracing::instrument]
-    fn short(&self) -> Result<String, Self::Error> {
+    fn short(&self, options: &fmt::Options) -> Result<String, Self::Error> {
         let mut rows = vec![Row::new(vec![
             Cell::new("Name"),
             Cell::new("DNS Sec"),
@@ -38,15 +70,15 @@ impl Short for Vec<Zone> {
         for zone in self {
             let row = Row::new(vec![
                 Cell::new(&zone.name),
-                Cell::new(&format!("{}", zone.dnssec_supported)),
-                Cell::new(&format!("{}", zone.has_dns_anycast)),
+                fmt::boolean(zone.dnssec_supported, options),
+                fmt::boolean(zone.has_dns_anycast, options),
                 Cell::new(&zone.name_servers.join(", ")),
             ]);
 
             rows.push(row);
         }
 
-        Ok(format!("{}", Table::init(rows)))
+        Ok(fmt::table(rows, options))
     }
 }
 
@@ -54,7 +86,7 @@ impl Wide for Vec<Zone> {
     type Error = Box<dyn Error + Send + Sync>;
 
     #[tracing::instrument]
-    fn wide(&self) -> Result<String, Self::Error> {
+    fn wide(&self, options: &fmt::Options) -> Result<String, Self::Error> {
         let mut rows = vec![Row::new(vec![
             Cell::new("Name"),
             Cell::new("DNS Sec"),
@@ -62,18 +94,44 @@ impl Wide for Vec<Zone> {
             Cell::new("Servers"),
         ])];
 
+        // the columns are only written when the health of zones has been retrieved
+        let with_health = self.iter().any(|zone| zone.health.is_some());
+        if with_health {
+            rows[0].add_cell(Cell::new("Records"));
+            rows[0].add_cell(Cell::new("Serial"));
+        }
+
         for zone in self {
-            let row = Row::new(vec![
+            let mut row = Row::new(vec![
                 Cell::new(&zone.name),
-                Cell::new(&format!("{}", zone.dnssec_supported)),
-                Cell::new(&format!("{}", zone.has_dns_anycast)),
+                fmt::boolean(zone.dnssec_supported, options),
+                fmt::boolean(zone.has_dns_anycast, options),
                 Cell::new(&zone.name_servers.join(", ")),
             ]);
 
+            if with_health {
+                let health = zone.health.to_owned().unwrap_or_default();
+                row.add_cell(Cell::new(&optional(health.records)));
+                row.add_cell(Cell::new(&optional(health.serial)));
+            }
+
             rows.push(row);
         }
 
-        Ok(format!("{}", Table::init(rows)))
+        Ok(fmt::table(rows, options))
+    }
+}
+
+/// Format a value retrieved with a separate request, which is written as
+/// '<error>' when the request failed in best effort mode
+#[tracing::instrument]
+fn optional<T>(value: Option<T>) -> String
+where
+    T: std::fmt::Display + std::fmt::Debug,
+{
+    match value {
+        Some(value) => value.to_string(),
+        None => "<error>".into(),
     }
 }
 
@@ -87,12 +145,204 @@ pub struct Record {
     pub sub_domain: String,
     #[serde(rename = "ttl", skip_serializing_if = "Option::is_none")]
     pub ttl: Option<i64>,
-    #[serde(rename = "zone", skip_serializing)]
+    #[serde(rename = "zone", skip_serializing, default)]
     pub zone: String,
     #[serde(rename = "target")]
     pub target: String,
 }
 
+impl Record {
+    /// Check the record before sending it to the api, returns every problem
+    /// found at once
+    #[tracing::instrument]
+    pub fn validate(&self) -> Result<(), Validation> {
+        let mut validation = Validation::new(format!(
+            "record '{}' of type '{}'",
+            self.sub_domain, self.field_type
+        ));
+
+        if !FIELD_TYPES.contains(&self.field_type.as_str()) {
+            validation.push(format!(
+                "unknown field type '{}', expect one of {}",
+                self.field_type,
+                FIELD_TYPES.join(", ")
+            ));
+        }
+
+        if !self.sub_domain.is_empty() && !is_hostname(&self.sub_domain) {
+            validation.push(format!("sub domain '{}' is not valid", self.sub_domain));
+        }
+
+        if let Some(ttl) = self.ttl {
+            if ttl < 0 {
+                validation.push(format!("ttl '{}' must be positive", ttl));
+            }
+        }
+
+        if self.target.is_empty() {
+            validation.push("target must not be empty");
+            return validation.check();
+        }
+
+        let fields: Vec<&str> = self.target.split_whitespace().collect();
+        match self.field_type.as_str() {
+            "A" if self.target.parse::<Ipv4Addr>().is_err() => {
+                validation.push(format!("target '{}' is not an ipv4 address", self.target))
+            }
+            "AAAA" if self.target.parse::<Ipv6Addr>().is_err() => {
+                validation.push(format!("target '{}' is not an ipv6 address", self.target))
+            }
+            "CNAME" | "DNAME" | "NS" | "PTR" if !is_hostname(&self.target) => {
+                validation.push(format!("target '{}' is not a hostname", self.target))
+            }
+            "MX" if fields.len() != 2
+                || fields[0].parse::<u16>().is_err()
+                || !is_hostname(fields[1]) =>
+            {
+                validation.push(format!(
+                    "target '{}' is not formatted as '<priority> <hostname>'",
+                    self.target
+                ))
+            }
+            "SRV"
+                if fields.len() != 4
+                    || fields[..3]
+                        .iter()
+                        .any(|field| field.parse::<u16>().is_err())
+                    || !is_hostname(fields[3]) =>
+            {
+                validation.push(format!(
+                    "target '{}' is not formatted as '<priority> <weight> <port> <hostname>'",
+                    self.target
+                ))
+            }
+            _ => {}
+        }
+
+        validation.check()
+    }
+
+    /// Trim the values of the record, write its field type in upper case and
+    /// addresses in their canonical form, then check it. Unlike [`Record::validate`],
+    /// hostname targets must be fully qualified, i.e. end with a dot, as the api
+    /// would otherwise resolve them relative to the zone
+    #[tracing::instrument]
+    pub fn normalize(&mut self) -> Result<(), Validation> {
+        self.field_type = self.field_type.trim().to_uppercase();
+        self.sub_domain = self.sub_domain.trim().to_string();
+        self.target = self.target.trim().to_string();
+
+        if let ("A" | "AAAA", Ok(ip)) = (self.field_type.as_str(), self.target.parse::<IpAddr>()) {
+            self.target = ip.to_string();
+        }
+
+        let mut validation = match self.validate() {
+            Ok(()) => Validation::new(format!(
+                "record '{}' of type '{}'",
+                self.sub_domain, self.field_type
+            )),
+            Err(validation) => validation,
+        };
+
+        let hostname = match self.field_type.as_str() {
+            "CNAME" | "NS" => Some(self.target.as_str()),
+            "MX" => self.target.split_whitespace().last(),
+            _ => None,
+        };
+
+        if let Some(hostname) = hostname {
+            if !hostname.is_empty() && !hostname.ends_with('.') {
+                validation.push(format!(
+                    "target '{}' must be a fully qualified hostname ending with a dot, e.g. '{}.'",
+                    self.target, self.target
+                ));
+            }
+        }
+
+        validation.check()
+    }
+
+    /// Returns the record written as a line of a zone file
+    #[tracing::instrument]
+    pub fn line(&self) -> String {
+        let name = if self.sub_domain.is_empty() {
+            "@"
+        } else {
+            &self.sub_domain
+        };
+
+        match self.ttl {
+            Some(ttl) => format!("{} {} IN {} {}", name, ttl, self.field_type, self.target),
+            None => format!("{} IN {} {}", name, self.field_type, self.target),
+        }
+    }
+}
+
+/// Returns if the given value is a valid hostname, wildcard and underscore
+/// labels are allowed as they are common in dns records
+#[tracing::instrument]
+pub fn is_hostname(value: &str) -> bool {
+    let value = value.strip_suffix('.').unwrap_or(value);
+    if value.is_empty() || value.len() > 253 {
+        return false;
+    }
+
+    value.split('.').all(|label| {
+        !label.is_empty()
+            && label.len() <= 63
+            && (label == "*"
+                || label
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_'))
+    })
+}
+
+/// Record as written by exports, unlike [`Record`] it keeps the zone, so that
+/// the file is self-contained and is read back losslessly
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ExportedRecord {
+    #[serde(rename = "id", skip_serializing_if = "Option::is_none", default)]
+    pub id: Option<i64>,
+    #[serde(rename = "zone", default)]
+    pub zone: String,
+    #[serde(rename = "fieldType")]
+    pub field_type: String,
+    #[serde(rename = "subDomain")]
+    pub sub_domain: String,
+    #[serde(rename = "ttl", skip_serializing_if = "Option::is_none", default)]
+    pub ttl: Option<i64>,
+    #[serde(rename = "target")]
+    pub target: String,
+}
+
+impl From<Record> for ExportedRecord {
+    #[tracing::instrument]
+    fn from(record: Record) -> Self {
+        Self {
+            id: record.id,
+            zone: record.zone,
+            field_type: record.field_type,
+            sub_domain: record.sub_domain,
+            ttl: record.ttl,
+            target: record.target,
+        }
+    }
+}
+
+impl From<ExportedRecord> for Record {
+    #[tracing::instrument]
+    fn from(record: ExportedRecord) -> Self {
+        Self {
+            id: record.id,
+            field_type: record.field_type,
+            sub_domain: record.sub_domain,
+            ttl: record.ttl,
+            zone: record.zone,
+            target: record.target,
+        }
+    }
+}
+
 impl PartialEq for Record {
     #[tracing::instrument]
     fn eq(&self, other: &Self) -> bool {
@@ -107,7 +357,7 @@ impl Short for Vec<Record> {
     type Error = Box<dyn Error + Send + Sync>;
 
     #[tracing::instrument]
-    fn short(&self) -> Result<String, Self::Error> {
+    fn short(&self, options: &fmt::Options) -> Result<String, Self::Error> {
         let mut rows = vec![Row::new(vec![
             Cell::new("Identifier"),
             Cell::new("Zone"),
@@ -123,7 +373,7 @@ impl Short for Vec<Record> {
                 None => String::from("<none>"),
             };
 
-            let ttl = match record.id {
+            let ttl = match record.ttl {
                 Some(ttl) => format!("{}", ttl),
                 None => String::from("<none>"),
             };
@@ -140,7 +390,7 @@ impl Short for Vec<Record> {
             rows.push(row);
         }
 
-        Ok(format!("{}", Table::init(rows)))
+        Ok(fmt::table(rows, options))
     }
 }
 
@@ -148,7 +398,7 @@ impl Wide for Vec<Record> {
     type Error = Box<dyn Error + Send + Sync>;
 
     #[tracing::instrument]
-    fn wide(&self) -> Result<String, Self::Error> {
+    fn wide(&self, options: &fmt::Options) -> Result<String, Self::Error> {
         let mut rows = vec![Row::new(vec![
             Cell::new("Identifier"),
             Cell::new("Zone"),
@@ -164,7 +414,7 @@ impl Wide for Vec<Record> {
                 None => String::from("<none>"),
             };
 
-            let ttl = match record.id {
+            let ttl = match record.ttl {
                 Some(ttl) => format!("{}", ttl),
                 None => String::from("<none>"),
             };
@@ -181,61 +431,284 @@ impl Wide for Vec<Record> {
             rows.push(row);
         }
 
-        Ok(format!("{}", Table::init(rows)))
+        Ok(fmt::table(rows, options))
+    }
+}
+
+/// Change to apply on live records to reach a desired state
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub enum Change {
+    #[serde(rename = "create")]
+    Create(Record),
+    #[serde(rename = "delete")]
+    Delete(Record),
+    #[serde(rename = "update")]
+    Update {
+        #[serde(rename = "from")]
+        from: Record,
+        #[serde(rename = "to")]
+        to: Record,
+    },
+}
+
+impl Short for Vec<Change> {
+    type Error = Box<dyn Error + Send + Sync>;
+
+    #[tracing::instrument]
+    fn short(&self, options: &fmt::Options) -> Result<String, Self::Error> {
+        if self.is_empty() {
+            return Ok(String::from("No changes"));
+        }
+
+        let mut lines = vec![];
+        for change in self {
+            match change {
+                Change::Create(record) => lines.push(fmt::added(&record.line(), options)),
+                Change::Delete(record) => lines.push(fmt::removed(&record.line(), options)),
+                Change::Update { from, to } => {
+                    lines.push(fmt::removed(&from.line(), options));
+                    lines.push(fmt::added(&to.line(), options));
+                }
+            }
+        }
+
+        Ok(lines.join("\n"))
+    }
+}
+
+impl Wide for Vec<Change> {
+    type Error = Box<dyn Error + Send + Sync>;
+
+    #[tracing::instrument]
+    fn wide(&self, options: &fmt::Options) -> Result<String, Self::Error> {
+        if self.is_empty() {
+            return Ok(String::from("No changes"));
+        }
+
+        let id = |record: &Record| match record.id {
+            Some(id) => format!("{}", id),
+            None => String::from("<none>"),
+        };
+
+        let mut lines = vec![];
+        for change in self {
+            match change {
+                Change::Create(record) => lines.push(fmt::added(&record.line(), options)),
+                Change::Delete(record) => lines.push(fmt::removed(
+                    &format!("{} ; {}", record.line(), id(record)),
+                    options,
+                )),
+                Change::Update { from, to } => {
+                    lines.push(fmt::removed(
+                        &format!("{} ; {}", from.line(), id(from)),
+                        options,
+                    ));
+                    lines.push(fmt::added(&format!("{} ; {}", to.line(), id(to)), options));
+                }
+            }
+        }
+
+        Ok(lines.join("\n"))
+    }
+}
+
+/// Compute the changes to apply on the live records to reach the desired ones.
+/// Records are matched on their type and sub domain, a single record replaced
+/// by another one or whose ttl differs is an update.
+#[tracing::instrument]
+pub fn diff(live: &[Record], desired: &[Record]) -> Vec<Change> {
+    let keys: BTreeSet<_> = live
+        .iter()
+        .chain(desired)
+        .map(|record| (record.sub_domain.to_owned(), record.field_type.to_owned()))
+        .collect();
+
+    let mut changes = vec![];
+    for (sub_domain, field_type) in keys {
+        let matches =
+            |record: &&Record| record.sub_domain == sub_domain && record.field_type == field_type;
+
+        let live: Vec<_> = live.iter().filter(matches).collect();
+        let desired: Vec<_> = desired.iter().filter(matches).collect();
+
+        let removed: Vec<_> = live.iter().filter(|r| !desired.contains(r)).collect();
+        let added: Vec<_> = desired.iter().filter(|r| !live.contains(r)).collect();
+
+        for from in &live {
+            if let Some(to) = desired.iter().find(|to| *to == from) {
+                if to.ttl.is_some() && to.ttl != from.ttl {
+                    changes.push(Change::Update {
+                        from: (*from).to_owned(),
+                        to: Record {
+                            id: from.id,
+                            ..(*to).to_owned()
+                        },
+                    });
+                }
+            }
+        }
+
+        if 1 == removed.len() && 1 == added.len() {
+            changes.push(Change::Update {
+                from: (**removed[0]).to_owned(),
+                to: Record {
+                    id: removed[0].id,
+                    ..(**added[0]).to_owned()
+                },
+            });
+
+            continue;
+        }
+
+        for record in removed {
+            changes.push(Change::Delete((**record).to_owned()));
+        }
+
+        for record in added {
+            changes.push(Change::Create((**record).to_owned()));
+        }
     }
+
+    changes
 }
 
 #[tracing::instrument(skip(client))]
 pub async fn list_zones(client: &Client) -> types::Result<Vec<Zone>> {
-    let ids: Vec<String> = client
-        .get("domain/zone")
-        .await
-        .map_err(|err| format!("could not retrieve zones, {}", err))?;
-
-    let mut zones = vec![];
-    for id in ids {
-        zones.push(
-            client
-                .get(&format!("domain/zone/{}", id))
-                .await
-                .map_err(|err| format!("could not retrieve zone '{}', {}", id, err))?,
-        );
+    let ids = list_zone_names(client).await?;
+
+    match client.get_batch("domain/zone", &ids).await {
+        Ok(items) => return Ok(items),
+        Err(err)
+            if types::find::<ApiError>(err.as_ref())
+                .map_or(false, ApiError::is_batch_unsupported) =>
+        {
+            debug!(
+                "could not use a batch request, fallback on a request per item, {}",
+                err
+            )
+        }
+        Err(err) => return Err(Context::new("could not retrieve zones", err).into()),
     }
 
-    Ok(zones)
+    client
+        .get_each("domain/zone", &ids, |id| {
+            format!("could not retrieve zone '{}'", id)
+        })
+        .await
+}
+
+#[tracing::instrument(skip(client))]
+pub async fn list_zone_names(client: &Client) -> types::Result<Vec<String>> {
+    Ok(client
+        .get_ids("domain/zone")
+        .await
+        .map_err(|err| Context::new("could not retrieve zones", err))?)
+}
+
+#[tracing::instrument(skip(client))]
+pub async fn get_soa(client: &Client, zone: &str) -> types::Result<Soa> {
+    Ok(client
+        .get(&format!("domain/zone/{}/soa", zone))
+        .await
+        .map_err(|err| {
+            Context::new(
+                format!("could not retrieve soa record of zone '{}'", zone),
+                err,
+            )
+        })?)
+}
+
+#[tracing::instrument(skip(client))]
+pub async fn count_records(client: &Client, zone: &str) -> types::Result<usize> {
+    let ids: Vec<i64> = client
+        .get_ids(&format!("domain/zone/{}/record", zone))
+        .await
+        .map_err(|err| {
+            Context::new(
+                format!("could not retrieve records in zone '{}'", zone),
+                err,
+            )
+        })?;
+
+    Ok(ids.len())
+}
+
+/// Retrieve the record count and the serial of the zone, in best effort mode a
+/// value which could not be retrieved is left empty instead of failing
+#[tracing::instrument(skip(client))]
+pub async fn get_zone_health(client: &Client, zone: &str) -> types::Result<ZoneHealth> {
+    let records = client.tolerate(count_records(client, zone).await)?;
+    let serial = client.tolerate(get_soa(client, zone).await.map(|soa| soa.serial))?;
+
+    Ok(ZoneHealth { records, serial })
+}
+
+#[tracing::instrument(skip(client))]
+pub async fn export_zone(client: &Client, zone: &str) -> types::Result<String> {
+    Ok(client
+        .get(&format!("domain/zone/{}/export", zone))
+        .await
+        .map_err(|err| Context::new(format!("could not export zone '{}'", zone), err))?)
 }
 
 #[tracing::instrument(skip(client))]
 pub async fn list_records(client: &Client, zone: &str) -> types::Result<Vec<Record>> {
     let ids: Vec<i64> = client
-        .get(&format!("domain/zone/{}/record", zone))
+        .get_ids(&format!("domain/zone/{}/record", zone))
+        .await
+        .map_err(|err| {
+            Context::new(
+                format!("could not retrieve records in zone '{}'", zone),
+                err,
+            )
+        })?;
+
+    match client
+        .get_batch(&format!("domain/zone/{}/record", zone), &ids)
         .await
-        .map_err(|err| format!("could not retrieve records in zone '{}', {}", zone, err))?;
-
-    // todo(florentin.dubois): increase performance by using `futures::join_all`
-    let mut zones = vec![];
-    for id in ids {
-        zones.push(
-            client
-                .get(&format!("domain/zone/{}/record/{}", zone, id))
-                .await
-                .map_err(|err| {
-                    format!(
-                        "could not retrieve record '{}' in zone '{}', {}",
-                        id, zone, err
-                    )
-                })?,
-        );
+    {
+        Ok(items) => return Ok(items),
+        Err(err)
+            if types::find::<ApiError>(err.as_ref())
+                .map_or(false, ApiError::is_batch_unsupported) =>
+        {
+            debug!(
+                "could not use a batch request, fallback on a request per item, {}",
+                err
+            )
+        }
+        Err(err) => {
+            return Err(Context::new(
+                format!("could not retrieve records in zone '{}'", zone),
+                err,
+            )
+            .into())
+        }
     }
 
-    Ok(zones)
+    client
+        .get_each(&format!("domain/zone/{}/record", zone), &ids, |id| {
+            format!("could not retrieve record '{}' in zone '{}'", id, zone)
+        })
+        .await
 }
 
 #[tracing::instrument(skip(client))]
 pub async fn create_record(client: &Client, zone: &str, record: &Record) -> types::Result<Record> {
+    let mut record = record.to_owned();
+    record.normalize()?;
+
     client
-        .post(&format!("domain/zone/{}/record", zone), record)
+        .post(&format!("domain/zone/{}/record", zone), &record)
+        .await
+}
+
+#[tracing::instrument(skip(client))]
+pub async fn get_record(client: &Client, zone: &str, id: &i64) -> types::Result<Record> {
+    Ok(client
+        .get(&format!("domain/zone/{}/record/{}", zone, id))
         .await
+        .map_err(|err| Context::new(format!("could not retrieve record '{}'", id), err))?)
 }
 
 #[tracing::instrument(skip(client))]
@@ -248,9 +721,9 @@ pub async fn delete_record(client: &Client, zone: &str, id: &i64) -> types::Resu
 #[tracing::instrument(skip(client))]
 pub async fn refresh_records(client: &Client, zone: &str) -> types::Result<()> {
     Ok(client
-        .post(&format!("domain/zone/{}/refresh", zone), &"")
+        .post_empty(&format!("domain/zone/{}/refresh", zone))
         .await
-        .map_err(|err| format!("could not refresh domain records, {}", err))?)
+        .map_err(|err| Context::new("could not refresh domain records", err))?)
 }
 
 #[tracing::instrument]
@@ -0,0 +1,157 @@
+//! # Ip module
+//!
+//! This module provide structure to interact with the ip api
+use std::error::Error;
+
+use prettytable::{Cell, Row};
+use serde::{Deserialize, Serialize};
+
+use crate::cmd::fmt::{self, Short, Wide};
+use crate::ovh::task::Task;
+use crate::ovh::{Client, RestClient};
+use crate::util::types::{self, Context};
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct IpMove {
+    #[serde(rename = "to")]
+    pub to: String,
+    #[serde(rename = "nexthop", skip_serializing_if = "Option::is_none")]
+    pub nexthop: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct IpTask {
+    #[serde(rename = "taskId")]
+    pub task_id: i64,
+    #[serde(rename = "function")]
+    pub function: String,
+    #[serde(rename = "status")]
+    pub status: String,
+    #[serde(rename = "startDate")]
+    pub start_date: Option<String>,
+    #[serde(rename = "doneDate")]
+    pub done_date: Option<String>,
+    #[serde(rename = "comment")]
+    pub comment: Option<String>,
+}
+
+impl IpTask {
+    /// Returns if the task has been successfully executed
+    #[tracing::instrument]
+    pub fn is_done(&self) -> bool {
+        "done" == self.status
+    }
+}
+
+impl Task for IpTask {
+    #[tracing::instrument]
+    fn id(&self) -> String {
+        self.task_id.to_string()
+    }
+
+    #[tracing::instrument]
+    fn status(&self) -> &str {
+        &self.status
+    }
+
+    #[tracing::instrument]
+    fn is_finished(&self) -> bool {
+        matches!(
+            self.status.as_str(),
+            "done" | "cancelled" | "customerError" | "ovhError"
+        )
+    }
+}
+
+impl Short for Vec<IpTask> {
+    type Error = Box<dyn Error + Send + Sync>;
+
+    #[tracing::instrument]
+    fn short(&self, options: &fmt::Options) -> Result<String, Self::Error> {
+        let mut rows = vec![Row::new(vec![
+            Cell::new("Identifier"),
+            Cell::new("Function"),
+            Cell::new("Status"),
+        ])];
+
+        for task in self {
+            rows.push(Row::new(vec![
+                Cell::new(&format!("{}", task.task_id)),
+                Cell::new(&task.function),
+                fmt::status(&task.status, options),
+            ]));
+        }
+
+        Ok(fmt::table(rows, options))
+    }
+}
+
+impl Wide for Vec<IpTask> {
+    type Error = Box<dyn Error + Send + Sync>;
+
+    #[tracing::instrument]
+    fn wide(&self, options: &fmt::Options) -> Result<String, Self::Error> {
+        let mut rows = vec![Row::new(vec![
+            Cell::new("Identifier"),
+            Cell::new("Function"),
+            Cell::new("Status"),
+            Cell::new("Start date"),
+            Cell::new("Done date"),
+            Cell::new("Comment"),
+        ])];
+
+        for task in self {
+            let start_date = match task.start_date.to_owned() {
+                Some(start_date) => start_date,
+                None => "<none>".into(),
+            };
+
+            let done_date = match task.done_date.to_owned() {
+                Some(done_date) => done_date,
+                None => "<none>".into(),
+            };
+
+            let comment = match task.comment.to_owned() {
+                Some(comment) => comment,
+                None => "<none>".into(),
+            };
+
+            rows.push(Row::new(vec![
+                Cell::new(&format!("{}", task.task_id)),
+                Cell::new(&task.function),
+                fmt::status(&task.status, options),
+                Cell::new(&start_date),
+                Cell::new(&done_date),
+                Cell::new(&comment),
+            ]));
+        }
+
+        Ok(fmt::table(rows, options))
+    }
+}
+
+/// Returns the path of the ip, blocks are written as 'a.b.c.d/n' which have to
+/// be escaped in the path
+#[tracing::instrument]
+fn path(ip: &str) -> String {
+    format!("ip/{}", ip.replace('/', "%2F"))
+}
+
+/// Returns the path of a task of the ip
+#[tracing::instrument]
+pub fn task_path(ip: &str, id: &i64) -> String {
+    format!("{}/task/{}", path(ip), id)
+}
+
+#[tracing::instrument(skip(client))]
+pub async fn move_ip(client: &Client, ip: &str, opts: &IpMove) -> types::Result<IpTask> {
+    Ok(client
+        .post(&format!("{}/move", path(ip)), opts)
+        .await
+        .map_err(|err| {
+            Context::new(
+                format!("could not move ip '{}' to service '{}'", ip, opts.to),
+                err,
+            )
+        })?)
+}
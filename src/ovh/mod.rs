@@ -1,35 +1,475 @@
 //! # OVHcloud module
 //!
 //! This module provide all necessary stuffs to communicate with https://api.ovh.com
-use std::{convert::TryFrom, error::Error, io::Read, str, sync::Arc};
+use std::{
+    cell::Cell,
+    collections::HashMap,
+    convert::TryFrom,
+    error::Error,
+    fmt::{self, Display},
+    fs::OpenOptions,
+    io::{Read, Write},
+    path::{Path as StdPath, PathBuf},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
+};
 
 use async_trait::async_trait;
 use bytes::Buf;
 use crypto::{digest::Digest, sha1::Sha1};
+use flate2::read::{GzDecoder, ZlibDecoder};
+use futures::{stream, StreamExt};
 use hyper::{
-    body::aggregate, body::Body, client::HttpConnector, header::USER_AGENT, Method, Request,
+    body::aggregate,
+    body::Body,
+    client::HttpConnector,
+    header::{HeaderName, ACCEPT_ENCODING, CONTENT_ENCODING, USER_AGENT},
+    http::request,
+    Method, Request, Response,
 };
 use hyper_tls::HttpsConnector;
-use serde::{de::DeserializeOwned, Serialize};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_path_to_error::{Path, Segment};
+use tracing::warn;
+use uuid::Uuid;
 
-use crate::cfg::{Configuration, Ovh};
+use crate::cfg::{Configuration, Ovh, Retry, REDACTED};
+use crate::util::types::Context;
 
 pub mod auth;
 pub mod cloud;
 pub mod dedicated;
 pub mod domain;
+pub mod ip;
+pub mod task;
 
 pub const X_OVH_APPLICATION: &str = "X-Ovh-Application";
 pub const X_OVH_TIMESTAMP: &str = "X-Ovh-Timestamp";
 pub const X_OVH_SIGNATURE: &str = "X-Ovh-Signature";
 pub const X_OVH_CONSUMER: &str = "X-Ovh-Consumer";
+pub const X_OVH_BATCH_SEPARATOR: &str = "X-Ovh-BatchSeparator";
+pub const IDEMPOTENCY_KEY: &str = "Idempotency-Key";
+
+/// Pages on which applications are created, by host of the api endpoint
+pub const CREATE_APP_URLS: [(&str, &str); 7] = [
+    ("eu.api.ovh.com", "https://eu.api.ovh.com/createApp/"),
+    ("ca.api.ovh.com", "https://ca.api.ovh.com/createApp/"),
+    (
+        "api.us.ovhcloud.com",
+        "https://api.us.ovhcloud.com/createApp/",
+    ),
+    (
+        "eu.api.soyoustart.com",
+        "https://eu.api.soyoustart.com/createApp/",
+    ),
+    (
+        "ca.api.soyoustart.com",
+        "https://ca.api.soyoustart.com/createApp/",
+    ),
+    (
+        "eu.api.kimsufi.com",
+        "https://eu.api.kimsufi.com/createApp/",
+    ),
+    (
+        "ca.api.kimsufi.com",
+        "https://ca.api.kimsufi.com/createApp/",
+    ),
+];
+
+/// Returns the page on which applications are created for the endpoint
+#[tracing::instrument]
+pub fn create_app_url(endpoint: &str) -> Result<&'static str, Box<dyn Error + Send + Sync>> {
+    let uri: hyper::Uri = endpoint
+        .parse()
+        .map_err(|err| format!("could not parse endpoint '{}', {}", endpoint, err))?;
+
+    let host = uri.host().unwrap_or_default();
+    CREATE_APP_URLS
+        .iter()
+        .find(|(known, _)| *known == host)
+        .map(|(_, url)| *url)
+        .ok_or_else(|| {
+            format!(
+                "could not find the page to create an application for host '{}', known hosts are {}",
+                host,
+                CREATE_APP_URLS
+                    .iter()
+                    .map(|(known, _)| format!("'{}'", known))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )
+            .into()
+        })
+}
+
+/// Endpoints of the api, by alias, e.g. to select one using the `OVHCTL_REGION`
+/// environment variable
+pub const ENDPOINTS: [(&str, &str); 7] = [
+    ("ovh-eu", "https://eu.api.ovh.com/1.0"),
+    ("ovh-ca", "https://ca.api.ovh.com/1.0"),
+    ("ovh-us", "https://api.us.ovhcloud.com/1.0"),
+    ("soyoustart-eu", "https://eu.api.soyoustart.com/1.0"),
+    ("soyoustart-ca", "https://ca.api.soyoustart.com/1.0"),
+    ("kimsufi-eu", "https://eu.api.kimsufi.com/1.0"),
+    ("kimsufi-ca", "https://ca.api.kimsufi.com/1.0"),
+];
+
+/// Returns the endpoint of the api for the alias, e.g. 'ovh-ca'
+#[tracing::instrument]
+pub fn endpoint(alias: &str) -> Result<&'static str, Box<dyn Error + Send + Sync>> {
+    ENDPOINTS
+        .iter()
+        .find(|(known, _)| *known == alias)
+        .map(|(_, endpoint)| *endpoint)
+        .ok_or_else(|| {
+            format!(
+                "unknown endpoint alias '{}', known aliases are {}",
+                alias,
+                ENDPOINTS
+                    .iter()
+                    .map(|(known, _)| format!("'{}'", known))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )
+            .into()
+        })
+}
+
+/// Maximum number of resources retrieved by a single batch request
+pub const BATCH_SIZE: usize = 50;
+
+/// Maximum number of characters of the offending value written in
+/// deserialization errors
+pub const EXCERPT_LENGTH: usize = 120;
+
+/// Maximum number of resources retrieved at once using a request per item
+pub const DETAIL_CONCURRENCY: usize = 4;
+
+/// Delay before the first retry of a request, it doubles with each retry
+pub const RETRY_DELAY: Duration = Duration::from_secs(1);
+
+/// Number of resources skipped by list functions in best effort mode
+static SKIPPED: AtomicUsize = AtomicUsize::new(0);
+
+/// Header giving the number of requests allowed in the current window
+pub const RATE_LIMIT_LIMIT: &str = "X-RateLimit-Limit";
+/// Header giving the number of requests which could still be sent in the window
+pub const RATE_LIMIT_REMAINING: &str = "X-RateLimit-Remaining";
+/// Header giving when the window is reset, either as a number of seconds or as
+/// a unix timestamp
+pub const RATE_LIMIT_RESET: &str = "X-RateLimit-Reset";
+
+/// Latest rate limit given by the api, if any
+static RATE_LIMIT: Mutex<Option<RateLimit>> = Mutex::new(None);
+
+/// Quota of requests given by the rate limit headers of the api
+#[derive(Clone, Copy, Debug)]
+pub struct RateLimit {
+    /// Number of requests allowed in the window, if given
+    pub limit: Option<u64>,
+    /// Number of requests which could still be sent in the window
+    pub remaining: u64,
+    /// Moment at which the window is reset, if given
+    pub reset: Option<Instant>,
+}
+
+impl RateLimit {
+    /// Parse the rate limit headers of the response, returns none if the api
+    /// did not give the remaining quota
+    #[tracing::instrument(skip(response))]
+    pub fn from_response(response: &Response<Body>) -> Option<Self> {
+        let header = |name: &str| -> Option<u64> {
+            response
+                .headers()
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .and_then(|value| value.trim().parse().ok())
+        };
+
+        // values larger than a year of seconds could only be unix timestamps
+        let reset = header(RATE_LIMIT_RESET).map(|reset| {
+            let seconds = match reset > 31_536_000 {
+                true => (reset as i64 - chrono::offset::Utc::now().timestamp()).max(0) as u64,
+                false => reset,
+            };
+
+            Instant::now() + Duration::from_secs(seconds)
+        });
+
+        Some(Self {
+            limit: header(RATE_LIMIT_LIMIT),
+            remaining: header(RATE_LIMIT_REMAINING)?,
+            reset,
+        })
+    }
+}
+
+/// Returns the latest rate limit given by the api, if any
+#[tracing::instrument]
+pub fn rate_limit() -> Option<RateLimit> {
+    RATE_LIMIT.lock().ok().and_then(|guard| *guard)
+}
+
+/// Requests sent to the api during the command
+static METRICS: Mutex<Metrics> = Mutex::new(Metrics {
+    endpoint: None,
+    requests: 0,
+    duration_ms: 0,
+});
+
+/// Number of requests sent to the api and time spent waiting for their
+/// responses, retries included
+#[derive(Serialize, Clone, Debug)]
+pub struct Metrics {
+    /// Endpoint of the latest request, none if no request has been sent
+    #[serde(rename = "endpoint")]
+    pub endpoint: Option<String>,
+    #[serde(rename = "requests")]
+    pub requests: usize,
+    #[serde(rename = "duration_ms")]
+    pub duration_ms: u64,
+}
+
+/// Returns the requests sent to the api so far
+#[tracing::instrument]
+pub fn metrics() -> Metrics {
+    match METRICS.lock() {
+        Ok(guard) => guard.to_owned(),
+        Err(poisoned) => poisoned.into_inner().to_owned(),
+    }
+}
+
+/// Returns the number of resources that have been skipped in best effort mode
+pub fn skipped() -> usize {
+    SKIPPED.load(Ordering::Relaxed)
+}
+
+/// Serialize the writes of the api call log, clients are used concurrently
+static API_CALL_LOG: Mutex<()> = Mutex::new(());
+
+thread_local! {
+    /// Set when a non-critical field has been defaulted while deserializing
+    static DEFAULTED: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Default value of a non-critical field missing from a payload, such payloads
+/// are only accepted in lenient mode
+pub fn missing<T>() -> T
+where
+    T: Default,
+{
+    DEFAULTED.with(|defaulted| defaulted.set(true));
+    T::default()
+}
+
+#[derive(Deserialize, Clone, Debug)]
+struct BatchItem<T> {
+    #[serde(rename = "key")]
+    key: String,
+    #[serde(rename = "value")]
+    value: Option<T>,
+    #[serde(rename = "error")]
+    error: Option<String>,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+struct ApiErrorPayload {
+    #[serde(rename = "class")]
+    class: Option<String>,
+    #[serde(rename = "message")]
+    message: String,
+}
+
+/// Task queued by the ovh api, some endpoints answer with it instead of the
+/// resource
+#[derive(Deserialize, Clone, Debug)]
+struct TaskPayload {
+    #[serde(rename = "taskId", alias = "id")]
+    id: i64,
+    #[serde(rename = "function")]
+    function: String,
+    #[serde(rename = "status")]
+    status: String,
+}
+
+/// Error returned when the ovh api has queued a task instead of returning the
+/// expected resource, the task could be followed to know the outcome
+#[derive(thiserror::Error, Clone, Debug)]
+#[error("the request '{uri}' has queued task '{id}' ({function}, {status}) instead of returning the resource")]
+pub struct QueuedTask {
+    pub uri: String,
+    pub id: i64,
+    pub function: String,
+    pub status: String,
+}
+
+/// Error returned by the ovh api when a request is not successful
+#[derive(thiserror::Error, Clone, Debug)]
+#[error("could not execute the request '{uri}', got '{status}', {message}")]
+pub struct ApiError {
+    pub uri: String,
+    pub status: u16,
+    pub class: Option<String>,
+    pub message: String,
+}
+
+impl ApiError {
+    /// Returns if the request has been rejected because the api is in a
+    /// maintenance or read-only window, during which write calls are refused
+    #[tracing::instrument]
+    pub fn is_maintenance(&self) -> bool {
+        let class = self.class.to_owned().unwrap_or_default().to_lowercase();
+        if class.contains("maintenance") || class.contains("readonly") {
+            return true;
+        }
 
-#[derive(Clone, Debug)]
+        503 == self.status && self.message.to_lowercase().contains("maintenance")
+    }
+
+    /// Returns if the request has been rejected because the consumer key is not
+    /// valid anymore, e.g. it has expired or has been revoked
+    #[tracing::instrument]
+    pub fn is_invalid_credential(&self) -> bool {
+        let class = self.class.to_owned().unwrap_or_default().to_lowercase();
+        if class.contains("invalidcredential") {
+            return true;
+        }
+
+        (401 == self.status || 403 == self.status)
+            && self
+                .message
+                .to_lowercase()
+                .contains("this credential is not valid")
+    }
+
+    /// Returns if the request has been rejected because the endpoint does not
+    /// support batch requests, in which case items are retrieved one by one
+    #[tracing::instrument]
+    pub fn is_batch_unsupported(&self) -> bool {
+        matches!(self.status, 400 | 404 | 501)
+    }
+
+    #[tracing::instrument(skip(response))]
+    pub async fn from_response(
+        uri: &str,
+        response: Response<Body>,
+    ) -> Result<Self, Box<dyn Error + Send + Sync>> {
+        let status = response.status().as_u16();
+
+        let buf = read_body(response).await?;
+
+        Ok(match serde_json::from_slice::<ApiErrorPayload>(&buf) {
+            Ok(payload) => Self {
+                uri: uri.to_string(),
+                status,
+                class: payload.class,
+                message: payload.message,
+            },
+            Err(_) => Self {
+                uri: uri.to_string(),
+                status,
+                class: None,
+                message: String::from_utf8_lossy(&buf).to_string(),
+            },
+        })
+    }
+}
+
+#[derive(Clone)]
 pub struct ClientConfiguration {
     pub endpoint: String,
     pub application_key: String,
     pub application_secret: String,
     pub consumer_key: String,
+    pub pool_max_idle: usize,
+    pub pool_idle_timeout: u64,
+    pub idempotency_keys: bool,
+    pub best_effort: bool,
+    pub print_curl: bool,
+    pub deserialize_lenient: bool,
+    pub id_cache_ttl: u64,
+    pub retry: Retry,
+    /// Name of the header and delegated identity sent with signed requests
+    pub delegate: Option<(String, String)>,
+    pub api_call_log: Option<PathBuf>,
+}
+
+impl fmt::Debug for ClientConfiguration {
+    /// Hides the secrets used to sign requests
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ClientConfiguration")
+            .field("endpoint", &self.endpoint)
+            .field("application_key", &self.application_key)
+            .field("application_secret", &REDACTED)
+            .field("consumer_key", &REDACTED)
+            .field("pool_max_idle", &self.pool_max_idle)
+            .field("pool_idle_timeout", &self.pool_idle_timeout)
+            .field("idempotency_keys", &self.idempotency_keys)
+            .field("best_effort", &self.best_effort)
+            .field("print_curl", &self.print_curl)
+            .field("deserialize_lenient", &self.deserialize_lenient)
+            .field("id_cache_ttl", &self.id_cache_ttl)
+            .field("retry", &self.retry)
+            .field("delegate", &self.delegate)
+            .field("api_call_log", &self.api_call_log)
+            .finish()
+    }
+}
+
+impl ClientConfiguration {
+    /// Create the configuration of a client which only sends unauthenticated
+    /// requests, so that the endpoint is enough and credentials may be missing
+    #[tracing::instrument]
+    pub fn unauthenticated(config: Ovh) -> Result<Self, Box<dyn Error + Send + Sync>> {
+        if !config.endpoint.starts_with("https://") && !config.endpoint.starts_with("http://") {
+            return Err(format!(
+                "endpoint '{}' should be an url starting with 'https://' or 'http://'",
+                config.endpoint
+            )
+            .into());
+        }
+
+        config
+            .retry
+            .validate()
+            .map_err(|err| format!("could not validate 'retry.on', {}", err))?;
+
+        let delegate = match (config.delegate_header, config.delegate) {
+            (Some(header), Some(delegate)) => {
+                HeaderName::from_bytes(header.as_bytes()).map_err(|err| {
+                    format!("could not use 'delegate-header' '{}', {}", header, err)
+                })?;
+
+                Some((header, delegate))
+            }
+            (None, Some(_)) => {
+                return Err(
+                    "a delegate is given, but 'delegate-header' is missing in configuration".into(),
+                )
+            }
+            (_, None) => None,
+        };
+
+        Ok(Self {
+            endpoint: config.endpoint.trim_end_matches('/').to_string(),
+            application_key: config.application_key,
+            application_secret: config.application_secret,
+            consumer_key: config.consumer_key.unwrap_or_default(),
+            pool_max_idle: config.pool_max_idle,
+            pool_idle_timeout: config.pool_idle_timeout,
+            idempotency_keys: config.idempotency_keys,
+            best_effort: config.best_effort,
+            print_curl: config.print_curl,
+            deserialize_lenient: config.deserialize_lenient,
+            id_cache_ttl: config.id_cache_ttl,
+            retry: config.retry,
+            delegate,
+            api_call_log: config.api_call_log,
+        })
+    }
 }
 
 impl TryFrom<Ovh> for ClientConfiguration {
@@ -37,13 +477,21 @@ impl TryFrom<Ovh> for ClientConfiguration {
 
     #[tracing::instrument]
     fn try_from(config: Ovh) -> Result<Self, Self::Error> {
+        let consumer_key = config.consumer_key.to_owned();
+        let client = Self::unauthenticated(config)?;
+
+        if client.application_key.is_empty() || client.application_secret.is_empty() {
+            return Err(format!(
+                "application key and secret are missing, use '{} connect --bootstrap' to create an application",
+                env!("CARGO_PKG_NAME")
+            )
+            .into());
+        }
+
         Ok(Self {
-            endpoint: config.endpoint,
-            application_key: config.application_key,
-            application_secret: config.application_secret,
-            consumer_key: config
-                .consumer_key
+            consumer_key: consumer_key
                 .ok_or_else(|| "could not retrieve consumer key".to_string())?,
+            ..client
         })
     }
 }
@@ -60,16 +508,24 @@ impl TryFrom<Arc<Configuration>> for ClientConfiguration {
 pub struct Client {
     inner: hyper::Client<HttpsConnector<HttpConnector>, Body>,
     config: ClientConfiguration,
+    /// Identifiers listed under a path along with the instant of their retrieval
+    ids: Mutex<HashMap<String, (Instant, serde_json::Value)>>,
 }
 
 impl From<ClientConfiguration> for Client {
     #[tracing::instrument]
     fn from(config: ClientConfiguration) -> Self {
-        let client = hyper::Client::builder().build(HttpsConnector::new());
+        // connections are kept alive and reused across requests, which matters
+        // when doing bulk operations against the api
+        let client = hyper::Client::builder()
+            .pool_max_idle_per_host(config.pool_max_idle)
+            .pool_idle_timeout(Duration::from_secs(config.pool_idle_timeout))
+            .build(HttpsConnector::new());
 
         Self {
             inner: client,
             config,
+            ids: Mutex::new(HashMap::new()),
         }
     }
 }
@@ -82,11 +538,24 @@ pub trait RestClient {
     where
         T: Sized + DeserializeOwned + Send + Sync;
 
+    /// Retrieve the resources with the given identifiers under the path using as
+    /// few requests as possible
+    async fn get_batch<I, T>(&self, path: &str, ids: &[I]) -> Result<Vec<T>, Self::Error>
+    where
+        I: ToString + Send + Sync,
+        T: Sized + DeserializeOwned + Send + Sync;
+
     async fn post<T, U>(&self, path: &str, obj: &T) -> Result<U, Self::Error>
     where
         T: Sized + Serialize + Send + Sync,
         U: Sized + DeserializeOwned + Send + Sync;
 
+    /// Send a post request without body nor content type, e.g. to trigger an
+    /// action which takes no parameter
+    async fn post_empty<U>(&self, path: &str) -> Result<U, Self::Error>
+    where
+        U: Sized + DeserializeOwned + Send + Sync;
+
     async fn put<T, U>(&self, path: &str, obj: &T) -> Result<U, Self::Error>
     where
         T: Sized + Serialize + Send + Sync,
@@ -104,45 +573,65 @@ impl RestClient for Client {
     where
         T: Sized + DeserializeOwned + Send + Sync,
     {
-        let timestamp = chrono::offset::Utc::now().timestamp();
-        let uri = format!("{}/{}", self.config.endpoint.to_owned(), path);
-
-        let request = Request::builder()
-            .header(X_OVH_APPLICATION, self.config.application_key.to_owned())
-            .header(X_OVH_TIMESTAMP, format!("{}", timestamp))
-            .header(X_OVH_CONSUMER, self.config.consumer_key.to_owned())
-            .header(X_OVH_SIGNATURE, self.hash("GET", &uri, "", timestamp))
-            .header(
-                USER_AGENT,
-                format!("{}/{}", env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION")),
-            )
-            .method(Method::GET)
-            .uri(&uri)
-            .body(Body::empty())
+        let uri = self.build_uri(path);
+        let request = self
+            .authenticated(Method::GET, &uri, "")
+            .body(String::new())
             .map_err(|err| format!("could not create request, {}", err))?;
 
-        let response = self
-            .inner
-            .request(request)
-            .await
-            .map_err(|err| format!("could not execute request, {}", err))?;
-
-        let status = response.status();
-        if !status.is_success() {
-            return Err(format!(
-                "could not execute the request '{}', got '{}'",
-                uri,
-                status.as_u16()
-            )
-            .into());
+        let response = self.send(request).await?;
+        if !response.status().is_success() {
+            return Err(self
+                .hint(ApiError::from_response(&uri, response).await?)
+                .into());
         }
 
-        let body = aggregate(response)
-            .await
-            .map_err(|err| format!("could not aggregate payload, {}", err))?;
+        deserialize(&uri, response, self.config.deserialize_lenient).await
+    }
+
+    #[tracing::instrument(skip(self, ids))]
+    async fn get_batch<I, T>(&self, path: &str, ids: &[I]) -> Result<Vec<T>, Self::Error>
+    where
+        I: ToString + Send + Sync,
+        T: Sized + DeserializeOwned + Send + Sync,
+    {
+        let mut items = vec![];
+        for chunk in ids.chunks(BATCH_SIZE) {
+            let keys: Vec<_> = chunk.iter().map(ToString::to_string).collect();
+            let uri = format!("{}/{}", self.build_uri(path), keys.join(","));
+
+            let request = self
+                .authenticated(Method::GET, &uri, "")
+                .header(X_OVH_BATCH_SEPARATOR, ",")
+                .body(String::new())
+                .map_err(|err| format!("could not create request, {}", err))?;
+
+            let response = self.send(request).await?;
+            if !response.status().is_success() {
+                return Err(self
+                    .hint(ApiError::from_response(&uri, response).await?)
+                    .into());
+            }
+
+            for item in
+                deserialize::<Vec<BatchItem<T>>>(&uri, response, self.config.deserialize_lenient)
+                    .await?
+            {
+                match (item.value, item.error) {
+                    (Some(value), None) => items.push(value),
+                    (_, Some(err)) => {
+                        return Err(format!("could not retrieve '{}', {}", item.key, err).into())
+                    }
+                    (None, None) => {
+                        return Err(
+                            format!("could not retrieve '{}', got no value", item.key).into()
+                        )
+                    }
+                }
+            }
+        }
 
-        Ok(serde_json::from_reader(body.reader())
-            .map_err(|err| format!("could not deserialize the payload, {}", err))?)
+        Ok(items)
     }
 
     #[tracing::instrument(skip(self, obj))]
@@ -151,59 +640,18 @@ impl RestClient for Client {
         T: Sized + Serialize + Send + Sync,
         U: Sized + DeserializeOwned + Send + Sync,
     {
-        let timestamp = chrono::offset::Utc::now().timestamp();
-        let uri = format!("{}/{}", self.config.endpoint.to_owned(), path);
-
-        let mut body = serde_json::to_string(obj)
+        let body = serde_json::to_string(obj)
             .map_err(|err| format!("could not serialize given object, {}", err))?;
 
-        let mut request_builder = Request::builder();
-        if "\"\"" != &body {
-            request_builder = request_builder.header("Content-Type", "application/json");
-        } else {
-            body = String::new();
-        }
-
-        let request = request_builder
-            .header(X_OVH_APPLICATION, self.config.application_key.to_owned())
-            .header(X_OVH_TIMESTAMP, format!("{}", timestamp))
-            .header(X_OVH_CONSUMER, self.config.consumer_key.to_owned())
-            .header(X_OVH_SIGNATURE, self.hash("POST", &uri, &body, timestamp))
-            .header(
-                USER_AGENT,
-                format!("{}/{}", env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION")),
-            )
-            .method(Method::POST)
-            .uri(&uri)
-            .body(Body::from(body))
-            .map_err(|err| format!("could not create request, {}", err))?;
-
-        let response = self
-            .inner
-            .request(request)
-            .await
-            .map_err(|err| format!("could not execute request, {}", err))?;
-
-        let status = response.status();
-        if !status.is_success() {
-            let mut buf = vec![];
-            aggregate(response).await?.reader().read_to_end(&mut buf)?;
-
-            return Err(format!(
-                "could not execute the request '{}', got '{}', {}",
-                uri,
-                status.as_u16(),
-                str::from_utf8(&buf)?,
-            )
-            .into());
-        }
-
-        let body = aggregate(response)
-            .await
-            .map_err(|err| format!("could not aggregate payload, {}", err))?;
+        self.write(Method::POST, path, Some(body)).await
+    }
 
-        Ok(serde_json::from_reader(body.reader())
-            .map_err(|err| format!("could not deserialize the payload, {}", err))?)
+    #[tracing::instrument(skip(self))]
+    async fn post_empty<U>(&self, path: &str) -> Result<U, Self::Error>
+    where
+        U: Sized + DeserializeOwned + Send + Sync,
+    {
+        self.write(Method::POST, path, None).await
     }
 
     #[tracing::instrument(skip(self, obj))]
@@ -212,100 +660,27 @@ impl RestClient for Client {
         T: Sized + Serialize + Send + Sync,
         U: Sized + DeserializeOwned + Send + Sync,
     {
-        let timestamp = chrono::offset::Utc::now().timestamp();
-        let uri = format!("{}/{}", self.config.endpoint.to_owned(), path);
-
-        let mut body = serde_json::to_string(obj)
+        let body = serde_json::to_string(obj)
             .map_err(|err| format!("could not serialize given object, {}", err))?;
 
-        let mut request_builder = Request::builder();
-        if "\"\"" != &body {
-            request_builder = request_builder.header("Content-Type", "application/json");
-        } else {
-            body = String::new();
-        }
-
-        let request = request_builder
-            .header("Content-Type", "application/json")
-            .header(X_OVH_APPLICATION, self.config.application_key.to_owned())
-            .header(X_OVH_APPLICATION, self.config.application_key.to_owned())
-            .header(X_OVH_TIMESTAMP, format!("{}", timestamp))
-            .header(X_OVH_CONSUMER, self.config.consumer_key.to_owned())
-            .header(X_OVH_SIGNATURE, self.hash("PUT", &uri, &body, timestamp))
-            .header(
-                USER_AGENT,
-                format!("{}/{}", env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION")),
-            )
-            .method(Method::PUT)
-            .uri(&uri)
-            .body(Body::from(body))
-            .map_err(|err| format!("could not create request, {}", err))?;
-
-        let response = self
-            .inner
-            .request(request)
-            .await
-            .map_err(|err| format!("could not execute request, {}", err))?;
-
-        let status = response.status();
-        if !status.is_success() {
-            let mut buf = vec![];
-            aggregate(response).await?.reader().read_to_end(&mut buf)?;
-
-            return Err(format!(
-                "could not execute the request '{}', got '{}', {}",
-                uri,
-                status.as_u16(),
-                str::from_utf8(&buf)?,
-            )
-            .into());
-        }
-
-        let body = aggregate(response)
-            .await
-            .map_err(|err| format!("could not aggregate payload, {}", err))?;
-
-        Ok(serde_json::from_reader(body.reader())
-            .map_err(|err| format!("could not deserialize the payload, {}", err))?)
+        self.write(Method::PUT, path, Some(body)).await
     }
 
     #[tracing::instrument(skip(self))]
     async fn delete(&self, path: &str) -> Result<(), Self::Error> {
-        let timestamp = chrono::offset::Utc::now().timestamp();
-        let uri = format!("{}/{}", self.config.endpoint.to_owned(), path);
-
-        let request = Request::builder()
-            .header(X_OVH_APPLICATION, self.config.application_key.to_owned())
-            .header(X_OVH_TIMESTAMP, format!("{}", timestamp))
-            .header(X_OVH_CONSUMER, self.config.consumer_key.to_owned())
-            .header(X_OVH_SIGNATURE, self.hash("DELETE", &uri, "", timestamp))
-            .header(
-                USER_AGENT,
-                format!("{}/{}", env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION")),
-            )
-            .method(Method::DELETE)
-            .uri(&uri)
-            .body(Body::empty())
+        self.invalidate(path);
+        let uri = self.build_uri(path);
+        let request = self
+            .authenticated(Method::DELETE, &uri, "")
+            .body(String::new())
             .map_err(|err| format!("could not create request, {}", err))?;
 
-        let response = self
-            .inner
-            .request(request)
-            .await
-            .map_err(|err| format!("could not execute request, {}", err))?;
-
+        let response = self.send(request).await?;
         let status = response.status();
         if !status.is_success() && 404 != status.as_u16() {
-            let mut buf = vec![];
-            aggregate(response).await?.reader().read_to_end(&mut buf)?;
-
-            return Err(format!(
-                "could not execute the request '{}', got '{}', {}",
-                uri,
-                status.as_u16(),
-                str::from_utf8(&buf)?
-            )
-            .into());
+            return Err(self
+                .hint(ApiError::from_response(&uri, response).await?)
+                .into());
         }
 
         Ok(())
@@ -335,40 +710,18 @@ impl UnauthenticatedRestClient for Client {
     where
         T: Sized + DeserializeOwned + Send + Sync,
     {
-        let uri = format!("{}/{}", self.config.endpoint.to_owned(), path);
-        let request = Request::builder()
-            .header(X_OVH_APPLICATION, self.config.application_key.to_owned())
-            .header(
-                USER_AGENT,
-                format!("{}/{}", env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION")),
-            )
-            .method(Method::GET)
-            .uri(&uri)
-            .body(Body::empty())
+        let uri = self.build_uri(path);
+        let request = self
+            .unauthenticated(Method::GET, &uri)
+            .body(String::new())
             .map_err(|err| format!("could not create request, {}", err))?;
 
-        let response = self
-            .inner
-            .request(request)
-            .await
-            .map_err(|err| format!("could not execute request, {}", err))?;
-
-        let status = response.status();
-        if !status.is_success() {
-            return Err(format!(
-                "could not execute the request '{}', got '{}'",
-                uri,
-                status.as_u16()
-            )
-            .into());
+        let response = self.send(request).await?;
+        if !response.status().is_success() {
+            return Err(ApiError::from_response(&uri, response).await?.into());
         }
 
-        let body = aggregate(response)
-            .await
-            .map_err(|err| format!("could not aggregate payload, {}", err))?;
-
-        Ok(serde_json::from_reader(body.reader())
-            .map_err(|err| format!("could not deserialize the payload, {}", err))?)
+        deserialize(&uri, response, self.config.deserialize_lenient).await
     }
 
     #[tracing::instrument(skip(self, obj))]
@@ -377,48 +730,360 @@ impl UnauthenticatedRestClient for Client {
         T: Sized + Serialize + Send + Sync,
         U: Sized + DeserializeOwned + Send + Sync,
     {
-        let uri = format!("{}/{}", self.config.endpoint.to_owned(), path);
+        let uri = self.build_uri(path);
         let body = serde_json::to_string(obj)
             .map_err(|err| format!("could not serialize given object, {}", err))?;
 
-        let request = Request::builder()
+        let request = self
+            .unauthenticated(Method::POST, &uri)
             .header("Content-Type", "application/json")
+            .body(body)
+            .map_err(|err| format!("could not create request, {}", err))?;
+
+        let response = self.send(request).await?;
+        if !response.status().is_success() {
+            return Err(ApiError::from_response(&uri, response).await?.into());
+        }
+
+        deserialize(&uri, response, self.config.deserialize_lenient).await
+    }
+}
+
+impl Client {
+    /// Send a request which changes the resource at the path, the body is sent as
+    /// json, if any, and the response is deserialized
+    #[tracing::instrument(skip(self, body))]
+    async fn write<U>(
+        &self,
+        method: Method,
+        path: &str,
+        body: Option<String>,
+    ) -> Result<U, Box<dyn Error + Send + Sync>>
+    where
+        U: Sized + DeserializeOwned + Send + Sync,
+    {
+        self.invalidate(path);
+        let uri = self.build_uri(path);
+        let creation = Method::POST == method;
+        let mut request_builder =
+            self.authenticated(method, &uri, body.as_deref().unwrap_or_default());
+        if body.is_some() {
+            request_builder = request_builder.header("Content-Type", "application/json");
+        }
+
+        // the key identifies the creation, so it has to be the same if the request
+        // is sent again. Endpoints which do not honor it simply ignore the header.
+        if creation && self.config.idempotency_keys {
+            request_builder = request_builder.header(IDEMPOTENCY_KEY, Uuid::new_v4().to_string());
+        }
+
+        let request = request_builder
+            .body(body.unwrap_or_default())
+            .map_err(|err| format!("could not create request, {}", err))?;
+
+        let response = self.send(request).await?;
+        if !response.status().is_success() {
+            return Err(self
+                .hint(ApiError::from_response(&uri, response).await?)
+                .into());
+        }
+
+        deserialize(&uri, response, self.config.deserialize_lenient).await
+    }
+
+    /// Retrieve the identifiers listed under the path, they are kept in memory
+    /// for the configured time to live, if any, to skip the enumeration when
+    /// listing the same resources again
+    #[tracing::instrument(skip(self))]
+    pub async fn get_ids<I>(&self, path: &str) -> Result<Vec<I>, Box<dyn Error + Send + Sync>>
+    where
+        I: Sized + Serialize + DeserializeOwned + Send + Sync,
+    {
+        if 0 == self.config.id_cache_ttl {
+            return self.get(path).await;
+        }
+
+        let cached = self
+            .ids
+            .lock()
+            .map_err(|err| format!("could not lock identifiers cache, {}", err))?
+            .get(path)
+            .filter(|(instant, _)| {
+                instant.elapsed() < Duration::from_secs(self.config.id_cache_ttl)
+            })
+            .map(|(_, ids)| ids.to_owned());
+
+        if let Some(ids) = cached {
+            return Ok(serde_json::from_value(ids)
+                .map_err(|err| format!("could not deserialize cached identifiers, {}", err))?);
+        }
+
+        let ids: Vec<I> = self.get(path).await?;
+        self.ids
+            .lock()
+            .map_err(|err| format!("could not lock identifiers cache, {}", err))?
+            .insert(
+                path.to_string(),
+                (Instant::now(), serde_json::to_value(&ids)?),
+            );
+
+        Ok(ids)
+    }
+
+    /// Retrieve the resources with the given identifiers under the path using a
+    /// request per item, a few at a time, when batch requests could not be used.
+    /// Each request is retried on its own, so that a transient failure of an
+    /// item does not fail the whole list, and keeps its slot while retrying
+    #[tracing::instrument(skip(self, ids, context))]
+    pub async fn get_each<I, T, F>(
+        &self,
+        path: &str,
+        ids: &[I],
+        context: F,
+    ) -> Result<Vec<T>, Box<dyn Error + Send + Sync>>
+    where
+        I: Display + Sync,
+        T: Sized + DeserializeOwned + Send + Sync,
+        F: Fn(&I) -> String,
+    {
+        // paths and messages are owned, so that the future is sendable
+        let requests: Vec<(String, String)> = ids
+            .iter()
+            .map(|id| (format!("{}/{}", path, id), context(id)))
+            .collect();
+
+        let results: Vec<Result<T, Context>> = stream::iter(requests)
+            .map(|(path, message)| async move {
+                self.get(&path)
+                    .await
+                    .map_err(|err| Context::new(message, err))
+            })
+            .buffered(DETAIL_CONCURRENCY)
+            .collect()
+            .await;
+
+        let mut items = vec![];
+        for result in results {
+            if let Some(item) = self.tolerate(result)? {
+                items.push(item);
+            }
+        }
+
+        Ok(items)
+    }
+
+    /// Forget identifiers listed under the parent of the path, as a mutation on
+    /// the path may have created or deleted some
+    #[tracing::instrument(skip(self))]
+    fn invalidate(&self, path: &str) {
+        let parent = match path.rsplit_once('/') {
+            Some((parent, _)) => parent,
+            None => path,
+        };
+
+        if let Ok(mut ids) = self.ids.lock() {
+            ids.retain(|key, _| !key.starts_with(parent));
+        }
+    }
+
+    /// Returns the retrieved resource, in best effort mode an error is logged
+    /// and the resource skipped instead of failing the whole list
+    #[tracing::instrument(skip(self, result))]
+    pub fn tolerate<T, E>(
+        &self,
+        result: Result<T, E>,
+    ) -> Result<Option<T>, Box<dyn Error + Send + Sync>>
+    where
+        E: Into<Box<dyn Error + Send + Sync>>,
+    {
+        match result {
+            Ok(item) => Ok(Some(item)),
+            Err(err) if self.config.best_effort => {
+                warn!("skip resource, {}", err.into());
+                SKIPPED.fetch_add(1, Ordering::Relaxed);
+                Ok(None)
+            }
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    /// Returns the uri of the given path on the endpoint, the path is joined
+    /// with exactly one slash whether it starts or ends with slashes or not,
+    /// e.g. '/me', 'me' and 'me/' all give '<endpoint>/me'
+    #[tracing::instrument(skip(self))]
+    pub fn build_uri(&self, path: &str) -> String {
+        let endpoint = self.config.endpoint.trim_end_matches('/');
+        let path = path.trim_matches('/');
+        if path.is_empty() {
+            return endpoint.to_string();
+        }
+
+        format!("{}/{}", endpoint, path)
+    }
+
+    /// Create a request builder with the headers shared by every request
+    #[tracing::instrument(skip(self))]
+    fn unauthenticated(&self, method: Method, uri: &str) -> request::Builder {
+        Request::builder()
             .header(X_OVH_APPLICATION, self.config.application_key.to_owned())
+            .header(ACCEPT_ENCODING, "gzip, deflate")
             .header(
                 USER_AGENT,
                 format!("{}/{}", env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION")),
             )
-            .method(Method::POST)
-            .uri(&uri)
-            .body(Body::from(body))
-            .map_err(|err| format!("could not create request, {}", err))?;
+            .method(method)
+            .uri(uri)
+    }
+
+    /// Append to the error that the consumer key may belong to another endpoint
+    /// when the api rejects the credentials, as keys are only valid on the
+    /// endpoint on which they have been created
+    #[tracing::instrument(skip(self))]
+    fn hint(&self, mut err: ApiError) -> ApiError {
+        let message = err.message.to_lowercase();
+        if (401 == err.status || 403 == err.status)
+            && !err.is_invalid_credential()
+            && (message.contains("credential") || message.contains("key"))
+            && !message.contains("not been granted")
+        {
+            err.message = format!(
+                "{}, your consumer key may belong to a different endpoint, current endpoint is '{}'",
+                err.message, self.config.endpoint
+            );
+        }
+
+        err
+    }
+
+    /// Create a request builder with the headers shared by every request and the
+    /// signature of the request
+    #[tracing::instrument(skip(self, body))]
+    fn authenticated(&self, method: Method, uri: &str, body: &str) -> request::Builder {
+        let timestamp = chrono::offset::Utc::now().timestamp();
+        let signature = self.hash(method.as_str(), uri, body, timestamp);
 
-        let response = self
+        let builder = self
+            .unauthenticated(method, uri)
+            .header(X_OVH_TIMESTAMP, format!("{}", timestamp))
+            .header(X_OVH_CONSUMER, self.config.consumer_key.to_owned())
+            .header(X_OVH_SIGNATURE, signature);
+
+        // the signature only covers the secret, the consumer key, the method, the
+        // url, the body and the timestamp, so the delegate header is not part of it
+        match &self.config.delegate {
+            Some((header, delegate)) => builder.header(header.as_str(), delegate.as_str()),
+            None => builder,
+        }
+    }
+
+    /// Send the request, it is sent again when the api answers with one of the
+    /// statuses on which requests are retried
+    #[tracing::instrument(skip(self, request))]
+    async fn send(
+        &self,
+        request: Request<String>,
+    ) -> Result<Response<Body>, Box<dyn Error + Send + Sync>> {
+        // the api may have created the resource before answering with an error,
+        // and it does not document which endpoints deduplicate idempotency keys,
+        // so that creations are only retried when explicitly asked
+        let creations = self.config.retry.creations && self.config.idempotency_keys;
+        let attempts = match (request.method(), creations) {
+            (&Method::POST, false) => 0,
+            _ => self.config.retry.attempts,
+        };
+
+        let mut delay = RETRY_DELAY;
+        for _ in 0..attempts {
+            let response = self.send_once(duplicate(&request)).await?;
+            if !self.config.retry.on.contains(&response.status().as_u16()) {
+                return Ok(response);
+            }
+
+            warn!(
+                "retry request on '{}' in {} second(s), got status '{}'",
+                request.uri().path(),
+                delay.as_secs(),
+                response.status()
+            );
+
+            tokio::time::sleep(delay).await;
+            delay *= 2;
+        }
+
+        self.send_once(request).await
+    }
+
+    #[tracing::instrument(skip(self, request))]
+    async fn send_once(
+        &self,
+        request: Request<String>,
+    ) -> Result<Response<Body>, Box<dyn Error + Send + Sync>> {
+        if self.config.print_curl {
+            eprintln!("{}", curl(&request));
+        }
+
+        // a request sent once the quota is exhausted is rejected anyway, so that
+        // it is held until the window is reset
+        if let Some(RateLimit {
+            remaining: 0,
+            reset: Some(reset),
+            ..
+        }) = rate_limit()
+        {
+            let delay = reset.saturating_duration_since(Instant::now());
+            if !delay.is_zero() {
+                warn!(
+                    "rate limit of the api is exhausted, wait {} second(s) before request on '{}'",
+                    delay.as_secs_f64().ceil(),
+                    request.uri().path()
+                );
+
+                tokio::time::sleep(delay).await;
+            }
+        }
+
+        let method = request.method().to_string();
+        let path = request.uri().path().to_string();
+        let begin = Instant::now();
+        let result = self
             .inner
-            .request(request)
+            .request(request.map(Body::from))
             .await
-            .map_err(|err| format!("could not execute request, {}", err))?;
+            .map_err(|err| format!("could not execute request, {}", err));
 
-        let status = response.status();
-        if !status.is_success() {
-            return Err(format!(
-                "could not execute the request '{}', got '{}'",
-                uri,
-                status.as_u16()
-            )
-            .into());
+        if let Ok(mut metrics) = METRICS.lock() {
+            metrics.endpoint = Some(self.config.endpoint.to_owned());
+            metrics.requests += 1;
+            metrics.duration_ms += begin.elapsed().as_millis() as u64;
         }
 
-        let body = aggregate(response)
-            .await
-            .map_err(|err| format!("could not aggregate payload, {}", err))?;
+        if let Some(log) = &self.config.api_call_log {
+            let status = result
+                .as_ref()
+                .ok()
+                .map(|response| response.status().as_u16());
+            log_call(log, &method, &path, status, begin.elapsed())?;
+        }
 
-        Ok(serde_json::from_reader(body.reader())
-            .map_err(|err| format!("could not deserialize the payload, {}", err))?)
+        let response = result?;
+        if let Some(limit) = RateLimit::from_response(&response) {
+            warn!(
+                "rate limit of the api, {} request(s) remaining{}",
+                limit.remaining,
+                limit
+                    .limit
+                    .map(|limit| format!(" out of {}", limit))
+                    .unwrap_or_default()
+            );
+
+            if let Ok(mut guard) = RATE_LIMIT.lock() {
+                *guard = Some(limit);
+            }
+        }
+
+        Ok(response)
     }
-}
 
-impl Client {
     #[tracing::instrument(skip(self))]
     fn hash(&self, method: &str, path: &str, body: &str, timestamp: i64) -> String {
         let mut hasher = Sha1::new();
@@ -438,3 +1103,199 @@ impl Client {
         format!("$1${}", hasher.result_str())
     }
 }
+
+/// Returns a copy of the request, to send it again
+#[tracing::instrument(skip(request))]
+fn duplicate(request: &Request<String>) -> Request<String> {
+    let mut copy = Request::new(request.body().to_owned());
+    *copy.method_mut() = request.method().to_owned();
+    *copy.uri_mut() = request.uri().to_owned();
+    *copy.version_mut() = request.version();
+    *copy.headers_mut() = request.headers().to_owned();
+
+    copy
+}
+
+/// Append a json line describing the call to the api in the file, headers and
+/// bodies are never written as they hold secrets, the status is null when no
+/// response has been received
+#[tracing::instrument]
+fn log_call(
+    log: &StdPath,
+    method: &str,
+    path: &str,
+    status: Option<u16>,
+    duration: Duration,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let line = serde_json::json!({
+        "timestamp": chrono::offset::Utc::now().to_rfc3339(),
+        "method": method,
+        "path": path,
+        "status": status,
+        "duration_ms": duration.as_millis() as u64,
+    });
+
+    let _guard = API_CALL_LOG.lock().unwrap_or_else(|err| err.into_inner());
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(log)
+        .map_err(|err| format!("could not open file '{}', {}", log.display(), err))?;
+
+    writeln!(file, "{}", line)
+        .map_err(|err| format!("could not write file '{}', {}", log.display(), err).into())
+}
+
+/// Returns the curl command line equivalent to the request, including the
+/// signature and timestamp, so it could be replayed while the signature is valid
+#[tracing::instrument(skip(request))]
+fn curl(request: &Request<String>) -> String {
+    let quote = |value: &str| format!("'{}'", value.replace('\'', "'\\''"));
+
+    let mut command = vec![
+        String::from("curl"),
+        String::from("--compressed"),
+        String::from("-X"),
+        request.method().to_string(),
+        quote(&request.uri().to_string()),
+    ];
+
+    for (name, value) in request.headers() {
+        command.push(String::from("-H"));
+        command.push(quote(&format!(
+            "{}: {}",
+            name,
+            String::from_utf8_lossy(value.as_bytes())
+        )));
+    }
+
+    if !request.body().is_empty() {
+        command.push(String::from("--data-raw"));
+        command.push(quote(request.body()));
+    }
+
+    command.join(" ")
+}
+
+/// Read the payload of the response, which is decompressed on the fly when the
+/// api or a proxy in between has compressed it using gzip or deflate
+#[tracing::instrument(skip(response))]
+async fn read_body(response: Response<Body>) -> Result<Vec<u8>, Box<dyn Error + Send + Sync>> {
+    let encoding = response
+        .headers()
+        .get(CONTENT_ENCODING)
+        .and_then(|value| value.to_str().ok())
+        .map(|value| value.trim().to_ascii_lowercase());
+
+    let mut reader = aggregate(response)
+        .await
+        .map_err(|err| format!("could not aggregate payload, {}", err))?
+        .reader();
+
+    let mut buf = vec![];
+    match encoding.as_deref() {
+        None | Some("identity") => reader.read_to_end(&mut buf),
+        Some("gzip") | Some("x-gzip") => GzDecoder::new(reader).read_to_end(&mut buf),
+        Some("deflate") => ZlibDecoder::new(reader).read_to_end(&mut buf),
+        Some(encoding) => {
+            return Err(format!("could not read payload encoded using '{}'", encoding).into())
+        }
+    }
+    .map_err(|err| format!("could not read payload, {}", err))?;
+
+    Ok(buf)
+}
+
+/// Deserialize the payload of a successful response, some endpoints answer with
+/// an error or a queued task along with a successful status, those are detected
+/// when the payload is not the expected one to return a meaningful error
+#[tracing::instrument(skip(response))]
+async fn deserialize<T>(
+    uri: &str,
+    response: Response<Body>,
+    lenient: bool,
+) -> Result<T, Box<dyn Error + Send + Sync>>
+where
+    T: Sized + DeserializeOwned,
+{
+    let status = response.status().as_u16();
+
+    let buf = read_body(response).await?;
+
+    DEFAULTED.with(|defaulted| defaulted.set(false));
+    let mut deserializer = serde_json::Deserializer::from_slice(&buf);
+    let err = match serde_path_to_error::deserialize(&mut deserializer) {
+        Ok(_) if DEFAULTED.with(Cell::get) && !lenient => {
+            return Err(format!(
+                "could not deserialize the payload of '{}', non-critical fields are missing, use '--deserialize-lenient' to default them",
+                uri
+            )
+            .into());
+        }
+        Ok(value) => return Ok(value),
+        Err(err) => err,
+    };
+
+    if let Ok(task) = serde_json::from_slice::<TaskPayload>(&buf) {
+        return Err(QueuedTask {
+            uri: uri.to_string(),
+            id: task.id,
+            function: task.function,
+            status: task.status,
+        }
+        .into());
+    }
+
+    if let Ok(payload) = serde_json::from_slice::<ApiErrorPayload>(&buf) {
+        return Err(ApiError {
+            uri: uri.to_string(),
+            status,
+            class: payload.class,
+            message: payload.message,
+        }
+        .into());
+    }
+
+    Err(match excerpt(&buf, err.path()) {
+        Some(excerpt) => format!(
+            "could not deserialize the payload of '{}' at '{}', {}, got '{}'",
+            uri,
+            err.path(),
+            err.inner(),
+            excerpt
+        ),
+        None => format!(
+            "could not deserialize the payload of '{}' at '{}', {}",
+            uri,
+            err.path(),
+            err.inner()
+        ),
+    }
+    .into())
+}
+
+/// Returns the value found at the given path of the payload, truncated to
+/// [`EXCERPT_LENGTH`] characters, if the payload is valid json
+#[tracing::instrument(skip(buf))]
+fn excerpt(buf: &[u8], path: &Path) -> Option<String> {
+    let value: serde_json::Value = serde_json::from_slice(buf).ok()?;
+
+    let mut pointer = String::new();
+    for segment in path.iter() {
+        match segment {
+            Segment::Seq { index } => pointer.push_str(&format!("/{}", index)),
+            Segment::Map { key } => {
+                pointer.push_str(&format!("/{}", key.replace('~', "~0").replace('/', "~1")))
+            }
+            Segment::Enum { variant } => pointer.push_str(&format!("/{}", variant)),
+            Segment::Unknown => return None,
+        }
+    }
+
+    let excerpt = value.pointer(&pointer)?.to_string();
+    if excerpt.chars().count() <= EXCERPT_LENGTH {
+        return Some(excerpt);
+    }
+
+    Some(excerpt.chars().take(EXCERPT_LENGTH).collect::<String>() + "...")
+}
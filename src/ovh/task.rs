@@ -0,0 +1,84 @@
+//! # Task module
+//!
+//! This module provide the structure to poll resources of the api which change
+//! asynchronously, e.g. tasks or instances being built, until they end
+use std::time::{Duration, Instant};
+
+use serde::de::DeserializeOwned;
+use tracing::info;
+
+use crate::ovh::{Client, RestClient};
+use crate::util::progress::Spinner;
+use crate::util::types::{self, Context};
+
+/// Interval between two frames of the spinner drawn while polling
+const SPINNER_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Resource of the api whose status changes asynchronously
+pub trait Task {
+    /// Returns the identifier of the task, written in messages
+    fn id(&self) -> String;
+
+    /// Returns the status of the task, as given by the api
+    fn status(&self) -> &str;
+
+    /// Returns if the task will not change anymore, successfully or not
+    fn is_finished(&self) -> bool;
+}
+
+/// Retrieve the task at the url until the predicate holds or the task is
+/// finished and returns it, fails if none of them happened before the timeout,
+/// if any
+#[tracing::instrument(skip(client, predicate, spinner))]
+pub async fn poll_task<T, F>(
+    client: &Client,
+    url: &str,
+    predicate: F,
+    timeout: Option<Duration>,
+    interval: Duration,
+    spinner: &mut Spinner,
+) -> types::Result<T>
+where
+    T: Task + DeserializeOwned + Send + Sync,
+    F: Fn(&T) -> bool,
+{
+    let begin = Instant::now();
+    loop {
+        let task: T = client.get(url).await.map_err(|err| {
+            spinner.finish();
+            Context::new(format!("could not retrieve task at '{}'", url), err)
+        })?;
+
+        if predicate(&task) || task.is_finished() {
+            spinner.finish();
+            return Ok(task);
+        }
+
+        if let Some(timeout) = timeout {
+            if begin.elapsed() >= timeout {
+                spinner.finish();
+                return Err(format!(
+                    "task '{}' did not end within {} seconds, last status is '{}'",
+                    task.id(),
+                    timeout.as_secs(),
+                    task.status()
+                )
+                .into());
+            }
+        }
+
+        info!("Task '{}' is '{}', waiting", task.id(), task.status());
+
+        let message = format!("Task '{}' is '{}'", task.id(), task.status());
+        let next = Instant::now() + interval;
+        loop {
+            let remaining = next.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                break;
+            }
+
+            spinner.tick(&message);
+            tokio::time::sleep(remaining.min(SPINNER_INTERVAL)).await;
+        }
+    }
+}
@@ -0,0 +1,66 @@
+//! # Human module
+//!
+//! This module export all stuff that you could need to write sizes and prices
+//! in a human readable way in tables
+/// Number of bytes in a kilobyte, the api gives sizes in decimal units, e.g. the
+/// ram of a 'b2-7' flavor is 7000 megabytes
+pub const KB: f64 = 1e3;
+/// Number of bytes in a megabyte
+pub const MB: f64 = 1e6;
+/// Number of bytes in a gigabyte
+pub const GB: f64 = 1e9;
+
+const UNITS: [&str; 6] = ["B", "kB", "MB", "GB", "TB", "PB"];
+
+/// Format a size given in the unit of the api, e.g. [`MB`] for the ram of a
+/// flavor, as a human readable size if enabled, e.g. '7 GB', as is otherwise
+#[tracing::instrument]
+pub fn size(value: f64, unit: f64, human: bool) -> String {
+    if !human {
+        return format!("{}", value);
+    }
+
+    let mut value = value * unit;
+    let mut index = 0;
+    while value.abs() >= KB && index < UNITS.len() - 1 {
+        value /= KB;
+        index += 1;
+    }
+
+    if 0.0 == value.fract() {
+        return format!("{} {}", value, UNITS[index]);
+    }
+
+    format!("{:.1} {}", value, UNITS[index])
+}
+
+/// Format a price with two decimals, along with the symbol of the currency and
+/// thousands separators if enabled, e.g. '€1,234.50'
+#[tracing::instrument]
+pub fn price(value: f64, currency: Option<&str>, human: bool) -> String {
+    if !human {
+        return format!("{:.2}", value);
+    }
+
+    let raw = format!("{:.2}", value.abs());
+    let (integer, decimals) = raw.split_once('.').unwrap_or((&raw, "00"));
+
+    let mut grouped = String::new();
+    for (index, digit) in integer.chars().enumerate() {
+        if 0 != index && 0 == (integer.len() - index) % 3 {
+            grouped.push(',');
+        }
+
+        grouped.push(digit);
+    }
+
+    let sign = if value < 0.0 { "-" } else { "" };
+    match currency {
+        Some("EUR") => format!("{}€{}.{}", sign, grouped, decimals),
+        Some("GBP") => format!("{}£{}.{}", sign, grouped, decimals),
+        Some("USD") => format!("{}${}.{}", sign, grouped, decimals),
+        Some("CAD") => format!("{}CA${}.{}", sign, grouped, decimals),
+        Some(code) => format!("{}{}.{} {}", sign, grouped, decimals, code),
+        None => format!("{}{}.{}", sign, grouped, decimals),
+    }
+}
@@ -2,5 +2,10 @@
 //!
 //! This module export all stuff that you could need
 
+pub mod human;
 pub mod net;
+pub mod progress;
+pub mod prompt;
+pub mod signal;
+pub mod time;
 pub mod types;
@@ -0,0 +1,133 @@
+//! # Progress module
+//!
+//! This module export all stuff that you could need to show the progress of an
+//! operation whose items are completed by concurrent tasks
+use std::io::{self, Write};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+use pbr::ProgressBar;
+
+/// Handle given to concurrent tasks to record the items that they completed, it
+/// never draws anything, so that tasks do not write on the same terminal
+#[derive(Clone, Debug, Default)]
+pub struct Tracker {
+    completed: Arc<AtomicU64>,
+}
+
+impl Tracker {
+    /// Record an item which has been completed
+    #[tracing::instrument]
+    pub fn inc(&self) {
+        self.completed.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+/// Progress bar owned by the task which drives the operation, completions
+/// recorded by trackers are drawn when [`Progress::update`] is called
+pub struct Progress {
+    bar: ProgressBar<Box<dyn Write + Send>>,
+    tracker: Tracker,
+    drawn: u64,
+}
+
+impl From<ProgressBar<Box<dyn Write + Send>>> for Progress {
+    #[tracing::instrument(skip(bar))]
+    fn from(bar: ProgressBar<Box<dyn Write + Send>>) -> Self {
+        Self {
+            bar,
+            tracker: Tracker::default(),
+            drawn: 0,
+        }
+    }
+}
+
+impl Progress {
+    /// Create a progress which draws nothing, e.g. for a unit of work of an
+    /// operation which has its own bar
+    #[tracing::instrument]
+    pub fn hidden(total: u64) -> Self {
+        let output: Box<dyn Write + Send> = Box::new(io::sink());
+
+        Self::from(ProgressBar::on(output, total))
+    }
+
+    /// Returns a handle to record completions from concurrent tasks
+    #[tracing::instrument(skip(self))]
+    pub fn tracker(&self) -> Tracker {
+        self.tracker.to_owned()
+    }
+
+    /// Draw the completions recorded since the last update
+    #[tracing::instrument(skip(self))]
+    pub fn update(&mut self) {
+        let completed = self.tracker.completed.load(Ordering::Relaxed);
+        if completed > self.drawn {
+            self.bar.add(completed - self.drawn);
+            self.drawn = completed;
+        }
+    }
+
+    /// Record an item completed by the task which owns the progress and draw it
+    #[tracing::instrument(skip(self))]
+    pub fn inc(&mut self) {
+        self.tracker.inc();
+        self.update();
+    }
+
+    /// Draw the remaining completions and finish the bar
+    #[tracing::instrument(skip(self))]
+    pub fn finish(&mut self) {
+        self.update();
+        self.bar.finish();
+    }
+}
+
+/// Spinner drawn while waiting for a resource whose completion could not be
+/// measured, e.g. an instance which is being built
+pub struct Spinner {
+    bar: ProgressBar<Box<dyn Write + Send>>,
+    drawn: bool,
+}
+
+impl From<ProgressBar<Box<dyn Write + Send>>> for Spinner {
+    #[tracing::instrument(skip(bar))]
+    fn from(mut bar: ProgressBar<Box<dyn Write + Send>>) -> Self {
+        bar.show_bar = false;
+        bar.show_counter = false;
+        bar.show_percent = false;
+        bar.show_speed = false;
+        bar.show_time_left = false;
+        bar.show_tick = true;
+        bar.show_message = true;
+        bar.tick_format("\\|/-");
+
+        Self { bar, drawn: false }
+    }
+}
+
+impl Spinner {
+    /// Create a spinner which draws nothing
+    #[tracing::instrument]
+    pub fn hidden() -> Self {
+        let output: Box<dyn Write + Send> = Box::new(io::sink());
+
+        Self::from(ProgressBar::on(output, 0))
+    }
+
+    /// Draw the next frame of the spinner along with the given message
+    #[tracing::instrument(skip(self))]
+    pub fn tick(&mut self, message: &str) {
+        self.bar.message(&format!("{} ", message));
+        self.bar.tick();
+        self.drawn = true;
+    }
+
+    /// Leave the last frame of the spinner, if any, and go to the next line
+    #[tracing::instrument(skip(self))]
+    pub fn finish(&mut self) {
+        if self.drawn {
+            self.bar.finish_println("");
+        }
+    }
+}
@@ -0,0 +1,70 @@
+//! # Prompt module
+//!
+//! This module export all stuff that you could need to ask the user a question
+use std::error::Error;
+use std::io::{self, BufRead, Write};
+use std::str::FromStr;
+
+use is_terminal::IsTerminal;
+
+use crate::util::types::Result;
+
+/// When destructive operations, e.g. deletions, ask for a confirmation
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Policy {
+    /// Always ask, even when the standard input is not a terminal
+    Always,
+    /// Never ask, e.g. in scripts
+    Never,
+    /// Only ask when the standard input is a terminal
+    Tty,
+}
+
+impl FromStr for Policy {
+    type Err = Box<dyn Error + Send + Sync>;
+
+    #[tracing::instrument]
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "always" => Ok(Self::Always),
+            "never" => Ok(Self::Never),
+            "tty" => Ok(Self::Tty),
+            _ => Err(format!("'{}' is not allowed, only 'always', 'never' or 'tty'", s).into()),
+        }
+    }
+}
+
+/// Ask the user to type the expected value to confirm an operation, returns if
+/// the typed value matches
+#[tracing::instrument]
+pub fn confirm(message: &str, expected: &str) -> Result<bool> {
+    print!("{}, type '{}' to confirm: ", message, expected);
+    io::stdout()
+        .flush()
+        .map_err(|err| format!("could not write on standard output, {}", err))?;
+
+    let mut answer = String::new();
+    io::stdin()
+        .lock()
+        .read_line(&mut answer)
+        .map_err(|err| format!("could not read standard input, {}", err))?;
+
+    Ok(expected == answer.trim())
+}
+
+/// Confirm a destructive operation according to the policy, the user is asked
+/// to type the expected value if needed, returns if the operation could go on
+#[tracing::instrument]
+pub fn confirm_destructive(policy: Policy, message: &str, expected: &str) -> Result<bool> {
+    let ask = match policy {
+        Policy::Always => true,
+        Policy::Never => false,
+        Policy::Tty => io::stdin().is_terminal(),
+    };
+
+    if !ask {
+        return Ok(true);
+    }
+
+    confirm(message, expected)
+}
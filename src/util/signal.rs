@@ -0,0 +1,49 @@
+//! # Signal module
+//!
+//! This module export all stuff that you could need to handle process signals
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+
+use tracing::warn;
+
+/// Exit code of a process stopped by a second Ctrl-C, following the convention
+/// of shells, which is 128 plus the number of the signal
+pub const INTERRUPTED_EXIT_CODE: i32 = 130;
+
+/// Token flagged once the user asked to interrupt the current operation
+#[derive(Clone, Debug, Default)]
+pub struct CancellationToken {
+    cancelled: Arc<AtomicBool>,
+}
+
+impl CancellationToken {
+    /// Create a token which is cancelled when the process receives a Ctrl-C, a
+    /// second Ctrl-C stops the process right away
+    #[tracing::instrument]
+    pub fn ctrl_c() -> Self {
+        let token = Self::default();
+        let cancelled = token.cancelled.to_owned();
+
+        tokio::spawn(async move {
+            if tokio::signal::ctrl_c().await.is_err() {
+                return;
+            }
+
+            warn!("received interruption, finishing in-flight operation before stopping, press Ctrl-C again to stop right away");
+            cancelled.store(true, Ordering::SeqCst);
+
+            if tokio::signal::ctrl_c().await.is_ok() {
+                warn!("received second interruption, stopping right away");
+                std::process::exit(INTERRUPTED_EXIT_CODE);
+            }
+        });
+
+        token
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::SeqCst)
+    }
+}
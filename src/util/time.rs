@@ -0,0 +1,88 @@
+//! # Time module
+//!
+//! This module export all stuff that you could need to filter resources on
+//! their timestamps
+use chrono::{DateTime, Duration, Utc};
+
+use crate::util::types::Result;
+
+/// Parse a point in time given either as a rfc3339 date or as a duration
+/// relative to now, e.g. '30m', '24h' or '7d'
+#[tracing::instrument]
+pub fn parse(value: &str) -> Result<DateTime<Utc>> {
+    if let Ok(datetime) = DateTime::parse_from_rfc3339(value) {
+        return Ok(datetime.with_timezone(&Utc));
+    }
+
+    let unit = match value.char_indices().last() {
+        Some((index, unit)) if index > 0 => unit,
+        _ => return Err(format!("could not parse '{}' as a date or a duration", value).into()),
+    };
+
+    let amount: i64 = value[..value.len() - unit.len_utf8()]
+        .parse()
+        .map_err(|err| format!("could not parse '{}' as a duration, {}", value, err))?;
+
+    let seconds = match unit {
+        's' => 1,
+        'm' => 60,
+        'h' => 60 * 60,
+        'd' => 24 * 60 * 60,
+        'w' => 7 * 24 * 60 * 60,
+        _ => {
+            return Err(format!(
+            "could not parse '{}' as a duration, unit should be one of 's', 'm', 'h', 'd' or 'w'",
+            value
+        )
+            .into())
+        }
+    };
+
+    // durations are stored in milliseconds, larger amounts would panic
+    amount
+        .checked_mul(seconds)
+        .filter(|seconds| {
+            seconds
+                .checked_abs()
+                .map_or(false, |abs| abs <= i64::MAX / 1000)
+        })
+        .and_then(|seconds| Utc::now().checked_sub_signed(Duration::seconds(seconds)))
+        .ok_or_else(|| {
+            format!(
+                "could not parse '{}' as a duration, duration out of range",
+                value
+            )
+            .into()
+        })
+}
+
+/// Bounds used to filter resources on their creation timestamp
+#[derive(clap::Args, Clone, Debug, Default)]
+pub struct Range {
+    /// Only keep resources created after the given rfc3339 date or duration, e.g. '7d'
+    #[clap(long = "since", value_parser = parse)]
+    pub since: Option<DateTime<Utc>>,
+
+    /// Only keep resources created before the given rfc3339 date or duration, e.g. '24h'
+    #[clap(long = "until", value_parser = parse)]
+    pub until: Option<DateTime<Utc>>,
+}
+
+impl Range {
+    /// Returns if the rfc3339 timestamp is within the range, a timestamp that
+    /// could not be parsed is only kept if the range is unbounded
+    #[tracing::instrument]
+    pub fn contains(&self, timestamp: Option<&str>) -> bool {
+        if self.since.is_none() && self.until.is_none() {
+            return true;
+        }
+
+        let timestamp = match timestamp.map(DateTime::parse_from_rfc3339) {
+            Some(Ok(timestamp)) => timestamp.with_timezone(&Utc),
+            _ => return false,
+        };
+
+        self.since.map_or(true, |since| timestamp >= since)
+            && self.until.map_or(true, |until| timestamp <= until)
+    }
+}
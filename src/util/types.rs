@@ -4,3 +4,94 @@
 use std::error::Error;
 
 pub type Result<T> = std::result::Result<T, Box<dyn Error + Send + Sync>>;
+
+/// Error giving the context in which its source has occurred
+#[derive(thiserror::Error, Debug)]
+#[error("{message}, {source}")]
+pub struct Context {
+    message: String,
+    source: Box<dyn Error + Send + Sync>,
+}
+
+impl Context {
+    pub fn new<M>(message: M, source: Box<dyn Error + Send + Sync>) -> Self
+    where
+        M: Into<String>,
+    {
+        Self {
+            message: message.into(),
+            source,
+        }
+    }
+}
+
+/// Error listing every problem found while validating a request body
+#[derive(thiserror::Error, Debug)]
+#[error("invalid {name}, {}", .problems.join(", "))]
+pub struct Validation {
+    name: String,
+    problems: Vec<String>,
+}
+
+impl Validation {
+    pub fn new<N>(name: N) -> Self
+    where
+        N: Into<String>,
+    {
+        Self {
+            name: name.into(),
+            problems: vec![],
+        }
+    }
+
+    /// Record a problem found in the validated body
+    pub fn push<P>(&mut self, problem: P)
+    where
+        P: Into<String>,
+    {
+        self.problems.push(problem.into());
+    }
+
+    /// Returns the validation error if any problem has been recorded
+    pub fn check(self) -> std::result::Result<(), Self> {
+        if self.problems.is_empty() {
+            return Ok(());
+        }
+
+        Err(self)
+    }
+}
+
+/// Error returned when a listing has no result and it has been asked to fail in
+/// that case
+#[derive(thiserror::Error, Debug)]
+#[error("no {noun} found")]
+pub struct Empty {
+    noun: String,
+}
+
+impl Empty {
+    pub fn new<N>(noun: N) -> Self
+    where
+        N: Into<String>,
+    {
+        Self { noun: noun.into() }
+    }
+}
+
+/// Walk the chain of sources of the given error to find an error of type `T`
+pub fn find<'a, T>(err: &'a (dyn Error + 'static)) -> Option<&'a T>
+where
+    T: Error + 'static,
+{
+    let mut current = Some(err);
+    while let Some(err) = current {
+        if let Some(err) = err.downcast_ref::<T>() {
+            return Some(err);
+        }
+
+        current = err.source();
+    }
+
+    None
+}
@@ -0,0 +1,243 @@
+//! # Configuration test module
+//!
+//! This module checks the precedence of the ways to give the configuration file
+//! to the command line interface.
+use std::fs;
+use std::path::PathBuf;
+use std::process::{Command, Output};
+
+/// Write a configuration file in the temporary directory and returns its path
+fn config(name: &str) -> PathBuf {
+    write(
+        name,
+        "[ovh]\napplication-key = \"key\"\napplication-secret = \"secret\"\n",
+    )
+}
+
+/// Write the content in a file of the temporary directory and returns its path
+fn write(name: &str, content: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("ovhctl-{}-{}.toml", name, std::process::id()));
+    fs::write(&path, content).expect("could not write configuration");
+
+    path
+}
+
+/// Validate the configuration with the given arguments and environment
+fn ovhctl(args: &[&str], env: Option<&PathBuf>) -> Output {
+    let mut command = Command::new(env!("CARGO_BIN_EXE_ovhctl"));
+    command
+        .arg("-t")
+        .args(args)
+        .env_remove("OVHCTL_CONFIG")
+        .env_remove("OVHCTL_REGION");
+    if let Some(path) = env {
+        command.env("OVHCTL_CONFIG", path);
+    }
+
+    command.output().expect("could not execute ovhctl")
+}
+
+#[test]
+fn config_path_from_env() {
+    let path = config("env");
+    let missing = std::env::temp_dir().join("ovhctl-missing.toml");
+
+    assert!(ovhctl(&[], Some(&path)).status.success());
+    assert!(!ovhctl(&[], Some(&missing)).status.success());
+
+    fs::remove_file(path).expect("could not remove configuration");
+}
+
+#[test]
+fn config_flag_takes_precedence_over_env() {
+    let path = config("flag");
+    let missing = std::env::temp_dir().join("ovhctl-missing.toml");
+
+    let output = ovhctl(&["-c", path.to_str().unwrap()], Some(&missing));
+    assert!(output.status.success(), "{:?}", output);
+
+    let output = ovhctl(&["-c", missing.to_str().unwrap()], Some(&path));
+    assert!(!output.status.success(), "{:?}", output);
+
+    fs::remove_file(path).expect("could not remove configuration");
+}
+
+#[test]
+fn config_files_are_merged_in_order() {
+    let base = write(
+        "base",
+        "[ovh]\napplication-key = \"key\"\napplication-secret = \"secret\"\npool-max-idle = \"many\"\n",
+    );
+    let prod = write("prod", "[ovh]\npool-max-idle = 4\n");
+
+    let output = ovhctl(
+        &["-c", base.to_str().unwrap(), "-c", prod.to_str().unwrap()],
+        None,
+    );
+    assert!(output.status.success(), "{:?}", output);
+
+    let output = ovhctl(
+        &["-c", prod.to_str().unwrap(), "-c", base.to_str().unwrap()],
+        None,
+    );
+    assert!(!output.status.success(), "{:?}", output);
+
+    fs::remove_file(base).expect("could not remove configuration");
+    fs::remove_file(prod).expect("could not remove configuration");
+}
+
+#[test]
+fn config_secrets_are_redacted_in_logs() {
+    let path = write(
+        "redacted",
+        "[ovh]\napplication-key = \"key\"\napplication-secret = \"s3cr3t-value\"\nconsumer-key = \"c0nsum3r-value\"\n",
+    );
+
+    let output = ovhctl(&["-vvvvv", "-c", path.to_str().unwrap()], None);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    assert!(output.status.success(), "{:?}", output);
+    assert!(stdout.contains("Configuration:"), "{}", stdout);
+    assert!(!stdout.contains("s3cr3t-value"), "{}", stdout);
+    assert!(!stdout.contains("c0nsum3r-value"), "{}", stdout);
+
+    fs::remove_file(path).expect("could not remove configuration");
+}
+
+#[test]
+fn config_endpoint_region_precedence() {
+    let path = config("region");
+    let region = |args: &[&str], env: Option<&str>| {
+        let mut command = Command::new(env!("CARGO_BIN_EXE_ovhctl"));
+        command
+            .args(["-t", "-vvvvv", "-c", path.to_str().unwrap()])
+            .args(args)
+            .env_remove("OVHCTL_CONFIG")
+            .env_remove("OVHCTL_REGION");
+        if let Some(region) = env {
+            command.env("OVHCTL_REGION", region);
+        }
+
+        command.output().expect("could not execute ovhctl")
+    };
+
+    let output = region(&[], None);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "{:?}", output);
+    assert!(stdout.contains("https://eu.api.ovh.com/1.0"), "{}", stdout);
+
+    let output = region(&[], Some("ovh-ca"));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "{:?}", output);
+    assert!(stdout.contains("https://ca.api.ovh.com/1.0"), "{}", stdout);
+
+    let output = region(&["--endpoint-region", "ovh-us"], Some("ovh-ca"));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "{:?}", output);
+    assert!(
+        stdout.contains("https://api.us.ovhcloud.com/1.0"),
+        "{}",
+        stdout
+    );
+    assert!(!stdout.contains("https://ca.api.ovh.com/1.0"), "{}", stdout);
+
+    let output = region(&[], Some("ovh-mars"));
+    assert!(!output.status.success(), "{:?}", output);
+
+    fs::remove_file(path).expect("could not remove configuration");
+}
+
+#[test]
+fn config_loads_without_home() {
+    let output = Command::new(env!("CARGO_BIN_EXE_ovhctl"))
+        .arg("-t")
+        .current_dir(std::env::temp_dir())
+        .env_remove("OVHCTL_CONFIG")
+        .env_remove("OVHCTL_REGION")
+        .env_remove("HOME")
+        .output()
+        .expect("could not execute ovhctl");
+
+    assert!(output.status.success(), "{:?}", output);
+}
+
+#[test]
+fn config_write_template() {
+    let path = std::env::temp_dir().join(format!("ovhctl-template-{}.toml", std::process::id()));
+    let _ = fs::remove_file(&path);
+
+    let write = |args: &[&str]| {
+        Command::new(env!("CARGO_BIN_EXE_ovhctl"))
+            .args(["--config-write", path.to_str().unwrap()])
+            .args(args)
+            .output()
+            .expect("could not execute ovhctl")
+    };
+
+    let output = write(&[]);
+    assert!(output.status.success(), "{:?}", output);
+
+    let content = fs::read_to_string(&path).expect("could not read configuration");
+    assert!(content.contains("application-key"), "{}", content);
+    assert!(content.contains("createApp"), "{}", content);
+
+    let output = write(&[]);
+    assert!(!output.status.success(), "{:?}", output);
+
+    let output = write(&["--force"]);
+    assert!(output.status.success(), "{:?}", output);
+
+    let output = ovhctl(&["-c", path.to_str().unwrap()], None);
+    assert!(output.status.success(), "{:?}", output);
+
+    fs::remove_file(path).expect("could not remove configuration");
+}
+
+#[test]
+fn config_values_expand_environment_variables() {
+    let expand = |name: &str, endpoint: &str| {
+        let path = write(
+            name,
+            &format!(
+                "[ovh]\nendpoint = \"{}\"\napplication-key = \"key\"\napplication-secret = \"secret\"\n",
+                endpoint
+            ),
+        );
+
+        let output = Command::new(env!("CARGO_BIN_EXE_ovhctl"))
+            .args(["-t", "-vvvvv", "-c", path.to_str().unwrap()])
+            .env_remove("OVHCTL_CONFIG")
+            .env_remove("OVHCTL_REGION")
+            .env_remove("OVHCTL_TEST_UNDEFINED")
+            .env("OVHCTL_TEST_HOST", "ca.api.ovh.com")
+            .output()
+            .expect("could not execute ovhctl");
+
+        fs::remove_file(path).expect("could not remove configuration");
+        output
+    };
+
+    let output = expand("expand", "https://${OVHCTL_TEST_HOST}/1.0");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "{:?}", output);
+    assert!(stdout.contains("https://ca.api.ovh.com/1.0"), "{}", stdout);
+
+    let output = expand("literal", "https://eu.api.ovh.com/1.0?$HOME");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "{:?}", output);
+    assert!(
+        stdout.contains("https://eu.api.ovh.com/1.0?$HOME"),
+        "{}",
+        stdout
+    );
+
+    let output = expand("undefined", "https://${OVHCTL_TEST_UNDEFINED}/1.0");
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(!output.status.success(), "{:?}", output);
+    assert!(stderr.contains("OVHCTL_TEST_UNDEFINED"), "{}", stderr);
+
+    let output = expand("unclosed", "https://${OVHCTL_TEST_HOST/1.0");
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(!output.status.success(), "{:?}", output);
+    assert!(stderr.contains("missing closing"), "{}", stderr);
+}
@@ -0,0 +1,2217 @@
+//! # Endpoint test module
+//!
+//! This module runs commands against a mock of the ovh api using the hidden
+//! `--endpoint-test` flag, which is also the way to point the command line
+//! interface to a non-production endpoint.
+use std::io::{Read, Write};
+use std::net::{TcpListener, TcpStream, UdpSocket};
+use std::process::{Command, Output, Stdio};
+use std::thread;
+use std::time::Duration;
+
+use flate2::{write::GzEncoder, Compression};
+use serde_json::json;
+use wiremock::matchers::{body_json, body_partial_json, header, header_exists, method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+/// Start a dns server answering NXDOMAIN to every query, returns its address
+fn nxdomain_resolver() -> String {
+    let socket = UdpSocket::bind("127.0.0.1:0").expect("could not bind dns server");
+    let address = socket
+        .local_addr()
+        .expect("could not get dns server address");
+
+    thread::spawn(move || {
+        let mut buf = [0; 512];
+        while let Ok((len, peer)) = socket.recv_from(&mut buf) {
+            // keep the identifier and the question, flag the response as an
+            // authoritative NXDOMAIN without any record
+            let mut response = buf[..len].to_vec();
+            response[2..4].copy_from_slice(&[0x85, 0x83]);
+            response[6..12].copy_from_slice(&[0; 6]);
+            let _ = socket.send_to(&response, peer);
+        }
+    });
+
+    address.to_string()
+}
+
+/// Run the command line interface with the given arguments against the server
+fn ovhctl(server: &MockServer, args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_ovhctl"))
+        .args(["--endpoint-test", "--no-color"])
+        .args(args)
+        .env("OVHCTL_TEST_ENDPOINT", server.uri())
+        .output()
+        .expect("could not execute ovhctl")
+}
+
+/// Mount the listing of the records of the 'example.com' zone, along with the
+/// batch request retrieving them
+async fn mount_records(server: &MockServer, records: serde_json::Value) {
+    let records = records.as_array().cloned().unwrap_or_default();
+    let ids: Vec<_> = records
+        .iter()
+        .map(|record| record["id"].to_owned())
+        .collect();
+
+    Mock::given(method("GET"))
+        .and(path("/domain/zone/example.com/record"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!(ids)))
+        .mount(server)
+        .await;
+
+    let keys: Vec<_> = ids.iter().map(ToString::to_string).collect();
+    let items: Vec<_> = records
+        .iter()
+        .map(|record| json!({ "key": record["id"].to_string(), "value": record }))
+        .collect();
+
+    Mock::given(method("GET"))
+        .and(path(format!(
+            "/domain/zone/example.com/record/{}",
+            keys.join(",")
+        )))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!(items)))
+        .mount(server)
+        .await;
+}
+
+#[tokio::test]
+async fn domain_record_list() {
+    let server = MockServer::start().await;
+
+    Mock::given(method("GET"))
+        .and(path("/domain/zone/example.com/record"))
+        .and(header("X-Ovh-Application", "test-application-key"))
+        .and(header("X-Ovh-Consumer", "test-consumer-key"))
+        .and(header_exists("X-Ovh-Signature"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!([1, 2])))
+        .mount(&server)
+        .await;
+
+    Mock::given(method("GET"))
+        .and(path("/domain/zone/example.com/record/1,2"))
+        .and(header_exists("X-Ovh-BatchSeparator"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!([
+            {
+                "key": "1",
+                "value": {
+                    "id": 1,
+                    "fieldType": "A",
+                    "subDomain": "www",
+                    "ttl": 3600,
+                    "zone": "example.com",
+                    "target": "192.0.2.1"
+                }
+            },
+            {
+                "key": "2",
+                "value": {
+                    "id": 2,
+                    "fieldType": "CNAME",
+                    "subDomain": "blog",
+                    "ttl": 0,
+                    "zone": "example.com",
+                    "target": "www.example.com."
+                }
+            }
+        ])))
+        .mount(&server)
+        .await;
+
+    let output = ovhctl(&server, &["domain", "record", "list", "example.com"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    assert!(output.status.success(), "{:?}", output);
+    assert!(stdout.contains("192.0.2.1"), "{}", stdout);
+    assert!(stdout.contains("www.example.com."), "{}", stdout);
+}
+
+#[tokio::test]
+async fn domain_record_list_resolve() {
+    let server = MockServer::start().await;
+
+    mount_records(
+        &server,
+        json!([
+            {
+                "id": 1,
+                "fieldType": "A",
+                "subDomain": "www",
+                "ttl": 3600,
+                "zone": "example.com",
+                "target": "192.0.2.1"
+            },
+            {
+                "id": 2,
+                "fieldType": "TXT",
+                "subDomain": "",
+                "ttl": 0,
+                "zone": "example.com",
+                "target": "v=spf1 -all"
+            }
+        ]),
+    )
+    .await;
+
+    let resolver = nxdomain_resolver();
+    let output = ovhctl(
+        &server,
+        &[
+            "domain",
+            "record",
+            "list",
+            "example.com",
+            "--resolve",
+            "--resolver",
+            &resolver,
+            "-o",
+            "json",
+        ],
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    assert!(output.status.success(), "{:?}", output);
+    assert!(stdout.contains(r#""resolution": "NXDOMAIN""#), "{}", stdout);
+    assert!(stdout.contains(r#""resolution": null"#), "{}", stdout);
+}
+
+#[tokio::test]
+async fn domain_record_list_credential_flags() {
+    let server = MockServer::start().await;
+
+    Mock::given(method("GET"))
+        .and(path("/domain/zone/example.com/record"))
+        .and(header("X-Ovh-Application", "flag-application-key"))
+        .and(header("X-Ovh-Consumer", "flag-consumer-key"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!([])))
+        .mount(&server)
+        .await;
+
+    let output = ovhctl(
+        &server,
+        &[
+            "-vvvv",
+            "--application-key",
+            "flag-application-key",
+            "--application-secret",
+            "flag-application-secret",
+            "--consumer-key",
+            "flag-consumer-key",
+            "domain",
+            "record",
+            "list",
+            "example.com",
+        ],
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    assert!(output.status.success(), "{:?}", output);
+    assert!(!stdout.contains("flag-application-secret"), "{}", stdout);
+    assert!(!stdout.contains("flag-consumer-key"), "{}", stdout);
+}
+
+#[tokio::test]
+async fn domain_record_list_api_error() {
+    let server = MockServer::start().await;
+
+    Mock::given(method("GET"))
+        .and(path("/domain/zone/example.com/record"))
+        .respond_with(ResponseTemplate::new(404).set_body_json(json!({
+            "class": "Client::NotFound",
+            "message": "This service does not exist"
+        })))
+        .mount(&server)
+        .await;
+
+    let output = ovhctl(
+        &server,
+        &["--json-errors", "domain", "record", "list", "example.com"],
+    );
+    let stderr = String::from_utf8_lossy(&output.stderr);
+
+    assert!(!output.status.success(), "{:?}", output);
+    assert!(stderr.contains("\"status\":404"), "{}", stderr);
+    assert!(stderr.contains("Client::NotFound"), "{}", stderr);
+}
+
+#[tokio::test]
+async fn domain_record_list_maintenance() {
+    let server = MockServer::start().await;
+
+    Mock::given(method("GET"))
+        .and(path("/domain/zone/example.com/record"))
+        .respond_with(ResponseTemplate::new(503).set_body_json(json!({
+            "class": "Server::ServiceUnavailable::Maintenance",
+            "message": "The api is in maintenance"
+        })))
+        .mount(&server)
+        .await;
+
+    let output = ovhctl(&server, &["domain", "record", "list", "example.com"]);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+
+    assert_eq!(Some(4), output.status.code(), "{:?}", output);
+    assert!(stderr.contains("in maintenance, try later"), "{}", stderr);
+}
+
+#[tokio::test]
+async fn domain_record_list_invalid_credential() {
+    let server = MockServer::start().await;
+
+    Mock::given(method("GET"))
+        .and(path("/domain/zone/example.com/record"))
+        .respond_with(ResponseTemplate::new(403).set_body_json(json!({
+            "errorCode": "INVALID_CREDENTIAL",
+            "httpCode": "403 Forbidden",
+            "message": "This credential is not valid"
+        })))
+        .mount(&server)
+        .await;
+
+    let output = ovhctl(&server, &["domain", "record", "list", "example.com"]);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+
+    assert_eq!(Some(5), output.status.code(), "{:?}", output);
+    assert!(stderr.contains("connect --wait --save"), "{}", stderr);
+}
+
+#[tokio::test]
+async fn domain_record_list_endpoint_hint() {
+    let server = MockServer::start().await;
+
+    Mock::given(method("GET"))
+        .and(path("/domain/zone/example.com/record"))
+        .respond_with(ResponseTemplate::new(403).set_body_json(json!({
+            "class": "Client::Forbidden",
+            "message": "Invalid application key"
+        })))
+        .mount(&server)
+        .await;
+
+    let output = ovhctl(&server, &["domain", "record", "list", "example.com"]);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+
+    assert!(!output.status.success(), "{:?}", output);
+    assert!(
+        stderr.contains("may belong to a different endpoint"),
+        "{}",
+        stderr
+    );
+}
+
+#[tokio::test]
+async fn domain_record_list_retry_on() {
+    let server = MockServer::start().await;
+
+    Mock::given(method("GET"))
+        .and(path("/domain/zone/example.com/record"))
+        .respond_with(ResponseTemplate::new(503).set_body_json(json!({
+            "class": "Server::InternalServerError",
+            "message": "Service temporarily unavailable"
+        })))
+        .up_to_n_times(1)
+        .mount(&server)
+        .await;
+
+    Mock::given(method("GET"))
+        .and(path("/domain/zone/example.com/record"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!([])))
+        .mount(&server)
+        .await;
+
+    let output = ovhctl(
+        &server,
+        &[
+            "--retry-on",
+            "429,503",
+            "domain",
+            "record",
+            "list",
+            "example.com",
+        ],
+    );
+
+    assert!(output.status.success(), "{:?}", output);
+
+    let output = ovhctl(
+        &server,
+        &[
+            "--retry-on",
+            "42",
+            "domain",
+            "record",
+            "list",
+            "example.com",
+        ],
+    );
+
+    assert_eq!(Some(2), output.status.code(), "{:?}", output);
+}
+
+#[tokio::test]
+async fn domain_record_list_error_with_success_status() {
+    let server = MockServer::start().await;
+
+    Mock::given(method("GET"))
+        .and(path("/domain/zone/example.com/record"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "class": "Client::Forbidden",
+            "message": "This zone is locked"
+        })))
+        .mount(&server)
+        .await;
+
+    let output = ovhctl(
+        &server,
+        &["--json-errors", "domain", "record", "list", "example.com"],
+    );
+    let stderr = String::from_utf8_lossy(&output.stderr);
+
+    assert!(!output.status.success(), "{:?}", output);
+    assert!(stderr.contains("\"status\":200"), "{}", stderr);
+    assert!(stderr.contains("Client::Forbidden"), "{}", stderr);
+    assert!(stderr.contains("This zone is locked"), "{}", stderr);
+}
+
+#[tokio::test]
+async fn domain_record_list_queued_task() {
+    let server = MockServer::start().await;
+
+    Mock::given(method("GET"))
+        .and(path("/domain/zone/example.com/record"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "id": 42,
+            "function": "zoneImport",
+            "status": "todo"
+        })))
+        .mount(&server)
+        .await;
+
+    let output = ovhctl(
+        &server,
+        &["--json-errors", "domain", "record", "list", "example.com"],
+    );
+    let stderr = String::from_utf8_lossy(&output.stderr);
+
+    assert!(!output.status.success(), "{:?}", output);
+    assert!(stderr.contains("\"task\":42"), "{}", stderr);
+    assert!(stderr.contains("zoneImport"), "{}", stderr);
+}
+
+#[tokio::test]
+async fn domain_record_list_fail_on_empty() {
+    let server = MockServer::start().await;
+
+    Mock::given(method("GET"))
+        .and(path("/domain/zone/example.com/record"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!([])))
+        .mount(&server)
+        .await;
+
+    let output = ovhctl(&server, &["domain", "record", "list", "example.com"]);
+    assert!(output.status.success(), "{:?}", output);
+
+    let output = ovhctl(
+        &server,
+        &["--fail-on-empty", "domain", "record", "list", "example.com"],
+    );
+
+    assert_eq!(Some(3), output.status.code(), "{:?}", output);
+}
+
+#[tokio::test]
+async fn domain_record_list_endpoint_with_trailing_slash() {
+    let server = MockServer::start().await;
+
+    Mock::given(method("GET"))
+        .and(path("/1.0/domain/zone/example.com/record"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!([])))
+        .mount(&server)
+        .await;
+
+    for endpoint in ["1.0", "1.0/", "1.0//"] {
+        let output = Command::new(env!("CARGO_BIN_EXE_ovhctl"))
+            .args(["--endpoint-test", "--no-color"])
+            .args(["domain", "record", "list", "example.com"])
+            .env(
+                "OVHCTL_TEST_ENDPOINT",
+                format!("{}/{}", server.uri(), endpoint),
+            )
+            .output()
+            .expect("could not execute ovhctl");
+
+        assert!(output.status.success(), "{}: {:?}", endpoint, output);
+    }
+}
+
+#[tokio::test]
+async fn domain_record_list_unexpected_type() {
+    let server = MockServer::start().await;
+
+    Mock::given(method("GET"))
+        .and(path("/domain/zone/example.com/record"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!([1, "two"])))
+        .mount(&server)
+        .await;
+
+    let output = ovhctl(&server, &["domain", "record", "list", "example.com"]);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+
+    assert!(!output.status.success(), "{:?}", output);
+    assert!(stderr.contains("at '[1]'"), "{}", stderr);
+    assert!(stderr.contains("got '"), "{}", stderr);
+}
+
+#[tokio::test]
+async fn domain_record_list_gzip() {
+    let server = MockServer::start().await;
+
+    let mut encoder = GzEncoder::new(vec![], Compression::default());
+    encoder
+        .write_all(json!([1]).to_string().as_bytes())
+        .expect("could not compress payload");
+
+    Mock::given(method("GET"))
+        .and(path("/domain/zone/example.com/record"))
+        .and(header_exists("Accept-Encoding"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .insert_header("Content-Encoding", "gzip")
+                .set_body_raw(
+                    encoder.finish().expect("could not compress payload"),
+                    "application/json",
+                ),
+        )
+        .mount(&server)
+        .await;
+
+    Mock::given(method("GET"))
+        .and(path("/domain/zone/example.com/record/1"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!([
+            {
+                "key": "1",
+                "value": {
+                    "id": 1,
+                    "fieldType": "A",
+                    "subDomain": "www",
+                    "ttl": 3600,
+                    "zone": "example.com",
+                    "target": "192.0.2.1"
+                }
+            }
+        ])))
+        .mount(&server)
+        .await;
+
+    let output = ovhctl(&server, &["domain", "record", "list", "example.com"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    assert!(output.status.success(), "{:?}", output);
+    assert!(stdout.contains("192.0.2.1"), "{}", stdout);
+}
+
+#[tokio::test]
+async fn domain_record_list_ndjson() {
+    let server = MockServer::start().await;
+
+    mount_records(
+        &server,
+        json!([
+            {
+                "id": 1,
+                "fieldType": "A",
+                "subDomain": "www",
+                "ttl": 3600,
+                "zone": "example.com",
+                "target": "192.0.2.1"
+            },
+            {
+                "id": 2,
+                "fieldType": "CNAME",
+                "subDomain": "blog",
+                "ttl": 0,
+                "zone": "example.com",
+                "target": "www.example.com."
+            }
+        ]),
+    )
+    .await;
+
+    let output = ovhctl(
+        &server,
+        &["domain", "record", "list", "example.com", "-o", "ndjson"],
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    assert!(output.status.success(), "{:?}", output);
+
+    let lines: Vec<&str> = stdout.lines().collect();
+    assert_eq!(2, lines.len(), "{}", stdout);
+    for line in lines {
+        let record: serde_json::Value = serde_json::from_str(line).expect("not a json object");
+        assert!(record.is_object(), "{}", line);
+    }
+}
+
+#[tokio::test]
+async fn domain_record_list_select() {
+    let server = MockServer::start().await;
+
+    mount_records(
+        &server,
+        json!([
+            {
+                "id": 1,
+                "fieldType": "A",
+                "subDomain": "www",
+                "ttl": 3600,
+                "zone": "example.com",
+                "target": "192.0.2.1"
+            },
+            {
+                "id": 2,
+                "fieldType": "CNAME",
+                "subDomain": "blog",
+                "ttl": 0,
+                "zone": "example.com",
+                "target": "www.example.com."
+            }
+        ]),
+    )
+    .await;
+
+    let output = ovhctl(
+        &server,
+        &[
+            "domain",
+            "record",
+            "list",
+            "example.com",
+            "--select",
+            "$[?(@.fieldType == 'A')].target",
+        ],
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!("192.0.2.1", stdout.trim(), "{}", stdout);
+}
+
+#[tokio::test]
+async fn domain_record_list_select_table_output() {
+    let server = MockServer::start().await;
+
+    let output = ovhctl(
+        &server,
+        &[
+            "domain",
+            "record",
+            "list",
+            "example.com",
+            "--select",
+            "$[*].id",
+            "-o",
+            "short",
+        ],
+    );
+    let stderr = String::from_utf8_lossy(&output.stderr);
+
+    assert!(!output.status.success(), "{:?}", output);
+    assert!(stderr.contains("'--select'"), "{}", stderr);
+}
+
+#[tokio::test]
+async fn domain_zone_list_with_counts() {
+    let server = MockServer::start().await;
+
+    Mock::given(method("GET"))
+        .and(path("/domain/zone"))
+        .respond_with(
+            ResponseTemplate::new(200).set_body_json(json!(["example.com", "broken.com"])),
+        )
+        .mount(&server)
+        .await;
+
+    Mock::given(method("GET"))
+        .and(path("/domain/zone/example.com,broken.com"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!([
+            {
+                "key": "example.com",
+                "value": {
+                    "name": "example.com",
+                    "dnssecSupported": true,
+                    "hasDnsAnycast": false,
+                    "nameServers": ["dns1.ovh.net"]
+                }
+            },
+            {
+                "key": "broken.com",
+                "value": {
+                    "name": "broken.com",
+                    "dnssecSupported": true,
+                    "hasDnsAnycast": false,
+                    "nameServers": ["dns2.ovh.net"]
+                }
+            }
+        ])))
+        .mount(&server)
+        .await;
+
+    Mock::given(method("GET"))
+        .and(path("/domain/zone/example.com/record"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!([1, 2, 3])))
+        .mount(&server)
+        .await;
+
+    Mock::given(method("GET"))
+        .and(path("/domain/zone/example.com/soa"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "email": "tech@ovh.net",
+            "serial": 2026101601,
+            "ttl": 86400
+        })))
+        .mount(&server)
+        .await;
+
+    Mock::given(method("GET"))
+        .and(path("/domain/zone/broken.com/record"))
+        .respond_with(ResponseTemplate::new(400).set_body_json(json!({
+            "message": "zone is broken"
+        })))
+        .mount(&server)
+        .await;
+
+    let output = ovhctl(
+        &server,
+        &["domain", "zone", "list", "-o", "wide", "--with-counts"],
+    );
+    assert!(!output.status.success(), "{:?}", output);
+
+    let output = ovhctl(
+        &server,
+        &[
+            "--best-effort",
+            "domain",
+            "zone",
+            "list",
+            "-o",
+            "wide",
+            "--with-counts",
+        ],
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    assert!(output.status.success(), "{:?}", output);
+    assert!(stdout.contains("Records"), "{}", stdout);
+    assert!(stdout.contains("2026101601"), "{}", stdout);
+    assert!(stdout.contains("<error>"), "{}", stdout);
+}
+
+#[tokio::test]
+async fn domain_record_export_then_diff() {
+    let server = MockServer::start().await;
+
+    mount_records(
+        &server,
+        json!([
+            {
+                "id": 1,
+                "fieldType": "A",
+                "subDomain": "www",
+                "ttl": 3600,
+                "zone": "example.com",
+                "target": "192.0.2.1"
+            },
+            {
+                "id": 2,
+                "fieldType": "TXT",
+                "subDomain": "",
+                "ttl": 0,
+                "zone": "example.com",
+                "target": "\"v=spf1 -all\""
+            }
+        ]),
+    )
+    .await;
+
+    let output = ovhctl(&server, &["domain", "record", "export", "example.com"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    assert!(output.status.success(), "{:?}", output);
+    assert!(stdout.contains("zone: example.com"), "{}", stdout);
+
+    let path = std::env::temp_dir().join(format!("ovhctl-export-{}.yaml", std::process::id()));
+    std::fs::write(&path, stdout.as_bytes()).expect("could not write export");
+
+    let output = ovhctl(
+        &server,
+        &[
+            "domain",
+            "record",
+            "diff",
+            "example.com",
+            path.to_str().unwrap(),
+        ],
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    assert!(output.status.success(), "{:?}", output);
+    assert!(stdout.contains("No changes"), "{}", stdout);
+
+    std::fs::remove_file(path).expect("could not remove export");
+}
+
+#[tokio::test]
+async fn doctor() {
+    let server = MockServer::start().await;
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .expect("clock is before epoch")
+        .as_secs();
+
+    Mock::given(method("GET"))
+        .and(path("/auth/time"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!(now)))
+        .mount(&server)
+        .await;
+
+    Mock::given(method("GET"))
+        .and(path("/me"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "nichandle": "xx1234-ovh",
+            "email": "admin@example.com"
+        })))
+        .mount(&server)
+        .await;
+
+    Mock::given(method("GET"))
+        .and(path("/auth/currentCredential"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "credentialId": 42,
+            "status": "validated",
+            "applicationId": 7,
+            "creation": "2026-01-01T00:00:00+01:00",
+            "expiration": null,
+            "lastUse": null,
+            "rules": [
+                { "method": "GET", "path": "/*" },
+                { "method": "POST", "path": "/domain/*" }
+            ]
+        })))
+        .mount(&server)
+        .await;
+
+    let output = ovhctl(&server, &["doctor"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    assert!(output.status.success(), "{:?}", output);
+    assert!(
+        stdout.contains("[pass] credentials are valid for 'xx1234-ovh'"),
+        "{}",
+        stdout
+    );
+    assert!(!stdout.contains("[fail]"), "{}", stdout);
+}
+
+#[tokio::test]
+async fn domain_record_create_invalid_utf8_error() {
+    let server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/domain/zone/example.com/record"))
+        .respond_with(ResponseTemplate::new(400).set_body_bytes(vec![0x3c, 0xff, 0xfe, 0x3e]))
+        .mount(&server)
+        .await;
+
+    let output = ovhctl(
+        &server,
+        &[
+            "--json-errors",
+            "domain",
+            "record",
+            "create",
+            "example.com",
+            "a",
+            "www",
+            "-t",
+            "192.0.2.1",
+        ],
+    );
+    let stderr = String::from_utf8_lossy(&output.stderr);
+
+    assert!(!output.status.success(), "{:?}", output);
+    assert!(stderr.contains("\"status\":400"), "{}", stderr);
+}
+
+#[tokio::test]
+async fn domain_record_add_txt_escapes_value() {
+    let server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/domain/zone/example.com/record"))
+        .and(body_partial_json(json!({ "target": "\"a\\\\b\\\"c\"" })))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "id": 1,
+            "fieldType": "TXT",
+            "subDomain": "www",
+            "ttl": 0,
+            "zone": "example.com",
+            "target": "\"a\\\\b\\\"c\""
+        })))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    Mock::given(method("POST"))
+        .and(path("/domain/zone/example.com/refresh"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!(null)))
+        .mount(&server)
+        .await;
+
+    let output = ovhctl(
+        &server,
+        &[
+            "domain",
+            "record",
+            "add-txt",
+            "example.com",
+            "www",
+            "a\\b\"c",
+        ],
+    );
+
+    assert!(output.status.success(), "{:?}", output);
+}
+
+#[tokio::test]
+async fn domain_record_create_not_retried_with_idempotency_keys() {
+    let server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/domain/zone/example.com/record"))
+        .and(header_exists("Idempotency-Key"))
+        .respond_with(ResponseTemplate::new(502))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let config =
+        std::env::temp_dir().join(format!("ovhctl-idempotency-{}.toml", std::process::id()));
+    std::fs::write(
+        &config,
+        format!(
+            "[ovh]\nendpoint = \"{}\"\napplication-key = \"key\"\napplication-secret = \"secret\"\nconsumer-key = \"consumer\"\nidempotency-keys = true\n",
+            server.uri()
+        ),
+    )
+    .expect("could not write configuration");
+
+    let output = Command::new(env!("CARGO_BIN_EXE_ovhctl"))
+        .args(["--no-color", "-c", config.to_str().unwrap()])
+        .args(["domain", "record", "create", "example.com", "a", "www"])
+        .args(["-t", "192.0.2.1"])
+        .output()
+        .expect("could not execute ovhctl");
+
+    std::fs::remove_file(config).expect("could not remove configuration");
+
+    assert!(!output.status.success(), "{:?}", output);
+}
+
+#[tokio::test]
+async fn domain_record_export_output_default() {
+    let server = MockServer::start().await;
+
+    mount_records(
+        &server,
+        json!([
+            {
+                "id": 1,
+                "fieldType": "A",
+                "subDomain": "www",
+                "ttl": 3600,
+                "zone": "example.com",
+                "target": "192.0.2.1"
+            }
+        ]),
+    )
+    .await;
+
+    let config = std::env::temp_dir().join(format!("ovhctl-output-{}.toml", std::process::id()));
+    std::fs::write(
+        &config,
+        format!(
+            "[ovh]\nendpoint = \"{}\"\napplication-key = \"key\"\napplication-secret = \"secret\"\nconsumer-key = \"consumer\"\n\n[output]\ndefault = \"json\"\n",
+            server.uri()
+        ),
+    )
+    .expect("could not write configuration");
+
+    let output = Command::new(env!("CARGO_BIN_EXE_ovhctl"))
+        .args(["--no-color", "-c", config.to_str().unwrap()])
+        .args(["domain", "record", "export", "example.com"])
+        .output()
+        .expect("could not execute ovhctl");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    std::fs::remove_file(config).expect("could not remove configuration");
+
+    assert!(output.status.success(), "{:?}", output);
+
+    let records: serde_json::Value = serde_json::from_str(&stdout).expect("valid json");
+    assert_eq!(records[0]["target"], "192.0.2.1", "{}", stdout);
+}
+
+#[tokio::test]
+async fn domain_record_create_not_fully_qualified() {
+    let server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/domain/zone/example.com/record"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({})))
+        .expect(0)
+        .mount(&server)
+        .await;
+
+    let output = ovhctl(
+        &server,
+        &[
+            "domain",
+            "record",
+            "create",
+            "example.com",
+            "cname",
+            "www",
+            "-t",
+            " web.example.net ",
+        ],
+    );
+    let stderr = String::from_utf8_lossy(&output.stderr);
+
+    assert!(!output.status.success(), "{:?}", output);
+    assert!(
+        stderr.contains("target 'web.example.net' must be a fully qualified hostname"),
+        "{}",
+        stderr
+    );
+}
+
+#[tokio::test]
+async fn connect_all_endpoints() {
+    let server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/auth/credential"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "validationUrl": "https://eu.api.ovh.com/auth/?credentialToken=token",
+            "consumerKey": "new-consumer-key",
+            "state": "pendingValidation"
+        })))
+        .expect(2)
+        .mount(&server)
+        .await;
+
+    let config = std::env::temp_dir().join(format!("ovhctl-profiles-{}.toml", std::process::id()));
+    std::fs::write(
+        &config,
+        format!(
+            "[ovh]\nendpoint = \"{uri}\"\napplication-key = \"key\"\napplication-secret = \"secret\"\n\n\
+             [profiles.ca]\nendpoint = \"{uri}\"\napplication-key = \"ca-key\"\napplication-secret = \"ca-secret\"\n\n\
+             [profiles.down]\nendpoint = \"http://127.0.0.1:1\"\napplication-key = \"key\"\napplication-secret = \"secret\"\n",
+            uri = server.uri()
+        ),
+    )
+    .expect("could not write configuration");
+
+    let output = Command::new(env!("CARGO_BIN_EXE_ovhctl"))
+        .args(["--no-color", "-c", config.to_str().unwrap()])
+        .args(["connect", "--all-endpoints"])
+        .output()
+        .expect("could not execute ovhctl");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    std::fs::remove_file(config).expect("could not remove configuration");
+
+    assert!(!output.status.success(), "{:?}", output);
+    assert_eq!(stdout.matches("new-consumer-key").count(), 2, "{}", stdout);
+    assert!(
+        stdout.contains("could not connect profile 'down'"),
+        "{}",
+        stdout
+    );
+}
+
+#[tokio::test]
+async fn auth_refresh_save_refused() {
+    let server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/auth/credential"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "validationUrl": "https://eu.api.ovh.com/auth/?credentialToken=token",
+            "consumerKey": "new-consumer-key",
+            "state": "pendingValidation"
+        })))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    Mock::given(method("GET"))
+        .and(path("/auth/currentCredential"))
+        .and(header("X-Ovh-Consumer", "new-consumer-key"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "credentialId": 42,
+            "status": "refused",
+            "applicationId": 7,
+            "creation": "2026-01-01T00:00:00+01:00",
+            "expiration": null,
+            "lastUse": null,
+            "rules": [{ "method": "GET", "path": "/*" }]
+        })))
+        .mount(&server)
+        .await;
+
+    let config = std::env::temp_dir().join(format!("ovhctl-refresh-{}.toml", std::process::id()));
+    let content = format!(
+        "[ovh]\nendpoint = \"{}\"\napplication-key = \"key\"\napplication-secret = \"secret\"\nconsumer-key = \"consumer\"\n",
+        server.uri()
+    );
+    std::fs::write(&config, &content).expect("could not write configuration");
+
+    let output = Command::new(env!("CARGO_BIN_EXE_ovhctl"))
+        .args(["--no-color", "-c", config.to_str().unwrap()])
+        .args(["auth", "refresh", "--save"])
+        .output()
+        .expect("could not execute ovhctl");
+    let saved = std::fs::read_to_string(&config).expect("could not read configuration");
+
+    std::fs::remove_file(config).expect("could not remove configuration");
+
+    assert!(!output.status.success(), "{:?}", output);
+    assert_eq!(content, saved);
+}
+
+#[tokio::test]
+async fn connect_listen() {
+    let server = MockServer::start().await;
+    let address = TcpListener::bind("127.0.0.1:0")
+        .and_then(|listener| listener.local_addr())
+        .expect("could not find a free port");
+    let redirection = format!("http://{}/", address);
+
+    Mock::given(method("POST"))
+        .and(path("/auth/credential"))
+        .and(move |request: &wiremock::Request| {
+            serde_json::from_slice::<serde_json::Value>(&request.body)
+                .map(|body| body["redirection"] == redirection.as_str())
+                .unwrap_or(false)
+        })
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "validationUrl": "https://eu.api.ovh.com/auth/?credentialToken=token",
+            "consumerKey": "new-consumer-key",
+            "state": "pendingValidation"
+        })))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    Mock::given(method("GET"))
+        .and(path("/auth/currentCredential"))
+        .and(header("X-Ovh-Consumer", "new-consumer-key"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "credentialId": 42,
+            "status": "validated",
+            "applicationId": 7,
+            "creation": "2026-01-01T00:00:00+01:00",
+            "expiration": null,
+            "lastUse": null,
+            "rules": [{ "method": "GET", "path": "/*" }]
+        })))
+        .mount(&server)
+        .await;
+
+    let config = std::env::temp_dir().join(format!("ovhctl-listen-{}.toml", std::process::id()));
+    std::fs::write(
+        &config,
+        format!(
+            "[ovh]\nendpoint = \"{}\"\napplication-key = \"key\"\napplication-secret = \"secret\"\n",
+            server.uri()
+        ),
+    )
+    .expect("could not write configuration");
+
+    let child = Command::new(env!("CARGO_BIN_EXE_ovhctl"))
+        .args(["--no-color", "-c", config.to_str().unwrap()])
+        .args(["connect", "--listen", &address.to_string(), "--save"])
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("could not execute ovhctl");
+
+    // the browser is redirected to the callback server once the user validated
+    // the consumer key
+    let (page, output) = tokio::task::spawn_blocking(move || {
+        let mut page = String::new();
+        for _ in 0..100 {
+            if let Ok(mut stream) = TcpStream::connect(address) {
+                stream
+                    .write_all(b"GET /?credentialToken=token HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n")
+                    .expect("could not request callback server");
+                stream
+                    .read_to_string(&mut page)
+                    .expect("could not read callback server");
+                break;
+            }
+
+            thread::sleep(Duration::from_millis(100));
+        }
+
+        (page, child.wait_with_output().expect("could not wait ovhctl"))
+    })
+    .await
+    .expect("could not request callback server");
+
+    let saved = std::fs::read_to_string(&config).expect("could not read configuration");
+    std::fs::remove_file(config).expect("could not remove configuration");
+
+    assert!(output.status.success(), "{:?}", output);
+    assert!(page.contains("Consumer key has been validated"), "{}", page);
+    assert!(
+        saved.contains("consumer-key = \"new-consumer-key\""),
+        "{}",
+        saved
+    );
+}
+
+#[tokio::test]
+async fn connect_listen_port_in_use() {
+    let server = MockServer::start().await;
+    let listener = TcpListener::bind("127.0.0.1:0").expect("could not bind a port");
+    let address = listener.local_addr().expect("could not get address");
+
+    Mock::given(method("POST"))
+        .and(path("/auth/credential"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&server)
+        .await;
+
+    let output = ovhctl(
+        &server,
+        &["--json-errors", "connect", "--listen", &address.to_string()],
+    );
+    let stderr = String::from_utf8_lossy(&output.stderr);
+
+    assert!(!output.status.success(), "{:?}", output);
+    assert!(
+        stderr.contains("the port may already be in use"),
+        "{}",
+        stderr
+    );
+}
+
+#[tokio::test]
+async fn domain_record_list_limit_offset() {
+    let server = MockServer::start().await;
+
+    let record = |id: u64, target: &str| {
+        json!({
+            "id": id,
+            "fieldType": "A",
+            "subDomain": "www",
+            "ttl": 60,
+            "zone": "example.com",
+            "target": target
+        })
+    };
+
+    mount_records(
+        &server,
+        json!([
+            record(1, "192.0.2.1"),
+            record(2, "192.0.2.2"),
+            record(3, "192.0.2.3"),
+        ]),
+    )
+    .await;
+
+    let output = ovhctl(
+        &server,
+        &[
+            "domain",
+            "record",
+            "list",
+            "example.com",
+            "--limit",
+            "1",
+            "--offset",
+            "1",
+            "-o",
+            "json",
+        ],
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    assert!(output.status.success(), "{:?}", output);
+
+    let records: serde_json::Value = serde_json::from_str(&stdout).expect("valid json");
+    assert_eq!(records.as_array().map(Vec::len), Some(1), "{}", stdout);
+    assert_eq!(records[0]["target"], "192.0.2.2", "{}", stdout);
+}
+
+#[tokio::test]
+async fn domain_record_list_totals() {
+    let server = MockServer::start().await;
+
+    mount_records(
+        &server,
+        json!([
+            {
+                "id": 1,
+                "fieldType": "A",
+                "subDomain": "www",
+                "ttl": 3600,
+                "zone": "example.com",
+                "target": "192.0.2.1"
+            },
+            {
+                "id": 2,
+                "fieldType": "A",
+                "subDomain": "blog",
+                "ttl": 60,
+                "zone": "example.com",
+                "target": "192.0.2.2"
+            }
+        ]),
+    )
+    .await;
+
+    let output = ovhctl(
+        &server,
+        &[
+            "domain",
+            "record",
+            "list",
+            "example.com",
+            "--totals",
+            "-o",
+            "wide",
+        ],
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    assert!(output.status.success(), "{:?}", output);
+    assert!(stdout.contains("| Total "), "{}", stdout);
+    assert!(stdout.contains("| 3660 "), "{}", stdout);
+    assert!(!stdout.contains("| 3 "), "{}", stdout);
+}
+
+#[tokio::test]
+async fn domain_record_list_item_retry() {
+    let server = MockServer::start().await;
+
+    Mock::given(method("GET"))
+        .and(path("/domain/zone/example.com/record"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!([1, 2, 3])))
+        .mount(&server)
+        .await;
+
+    Mock::given(method("GET"))
+        .and(path("/domain/zone/example.com/record/1,2,3"))
+        .respond_with(ResponseTemplate::new(400).set_body_json(json!({
+            "message": "Batch requests are not supported"
+        })))
+        .mount(&server)
+        .await;
+
+    Mock::given(method("GET"))
+        .and(path("/domain/zone/example.com/record/2"))
+        .respond_with(ResponseTemplate::new(429).set_body_json(json!({
+            "message": "Too many requests"
+        })))
+        .up_to_n_times(1)
+        .with_priority(1)
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    for id in 1..=3 {
+        Mock::given(method("GET"))
+            .and(path(format!("/domain/zone/example.com/record/{}", id)))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "id": id,
+                "fieldType": "A",
+                "subDomain": format!("www{}", id),
+                "ttl": 60,
+                "zone": "example.com",
+                "target": format!("192.0.2.{}", id)
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+    }
+
+    let output = ovhctl(&server, &["domain", "record", "list", "example.com"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    assert!(output.status.success(), "{:?}", output);
+    for id in 1..=3 {
+        assert!(stdout.contains(&format!("192.0.2.{}", id)), "{}", stdout);
+    }
+}
+
+#[tokio::test]
+async fn domain_record_list_batch_forbidden() {
+    let server = MockServer::start().await;
+
+    Mock::given(method("GET"))
+        .and(path("/domain/zone/example.com/record"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!([1, 2])))
+        .mount(&server)
+        .await;
+
+    Mock::given(method("GET"))
+        .and(path("/domain/zone/example.com/record/1,2"))
+        .respond_with(ResponseTemplate::new(403).set_body_json(json!({
+            "message": "This call has not been granted"
+        })))
+        .mount(&server)
+        .await;
+
+    Mock::given(method("GET"))
+        .and(path("/domain/zone/example.com/record/1"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&server)
+        .await;
+
+    let output = ovhctl(&server, &["domain", "record", "list", "example.com"]);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+
+    assert!(!output.status.success(), "{:?}", output);
+    assert!(stderr.contains("has not been granted"), "{}", stderr);
+}
+
+#[tokio::test]
+async fn ping() {
+    let server = MockServer::start().await;
+
+    Mock::given(method("GET"))
+        .and(path("/auth/time"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!(1_700_000_000)))
+        .expect(3)
+        .mount(&server)
+        .await;
+
+    let output = ovhctl(&server, &["ping", "--count", "3", "-o", "json"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    assert!(output.status.success(), "{:?}", output);
+
+    let latency: serde_json::Value = serde_json::from_str(&stdout).expect("valid json");
+    assert_eq!(latency["sent"], 3, "{}", stdout);
+    assert_eq!(latency["received"], 3, "{}", stdout);
+    assert!(
+        latency["min"].as_f64() <= latency["max"].as_f64(),
+        "{}",
+        stdout
+    );
+}
+
+#[tokio::test]
+async fn ping_without_credentials() {
+    let server = MockServer::start().await;
+
+    Mock::given(method("GET"))
+        .and(path("/auth/time"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!(1_700_000_000)))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let config = std::env::temp_dir().join(format!("ovhctl-ping-{}.toml", std::process::id()));
+    std::fs::write(&config, format!("[ovh]\nendpoint = \"{}\"\n", server.uri()))
+        .expect("could not write configuration");
+
+    let output = Command::new(env!("CARGO_BIN_EXE_ovhctl"))
+        .args(["--no-color", "-c", config.to_str().unwrap()])
+        .args(["ping", "--count", "1"])
+        .output()
+        .expect("could not execute ovhctl");
+
+    std::fs::remove_file(config).expect("could not remove configuration");
+
+    assert!(output.status.success(), "{:?}", output);
+}
+
+#[tokio::test]
+async fn cloud_storage_list() {
+    let server = MockServer::start().await;
+
+    Mock::given(method("GET"))
+        .and(path("/cloud/project/tenant/storage"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!([
+            {
+                "id": "Y29udGFpbmVy",
+                "name": "backups",
+                "region": "GRA",
+                "storedObjects": 12,
+                "storedBytes": 1048576
+            }
+        ])))
+        .mount(&server)
+        .await;
+
+    Mock::given(method("GET"))
+        .and(path("/cloud/project/tenant/storage/Y29udGFpbmVy"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "name": "backups",
+            "region": "GRA",
+            "public": false
+        })))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let output = ovhctl(
+        &server,
+        &["cloud", "storage", "list", "tenant", "-o", "wide"],
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    assert!(output.status.success(), "{:?}", output);
+    assert!(stdout.contains("backups"), "{}", stdout);
+    assert!(stdout.contains("1048576"), "{}", stdout);
+    assert!(stdout.contains("private"), "{}", stdout);
+}
+
+#[tokio::test]
+async fn domain_record_sync_diff_only() {
+    let server = MockServer::start().await;
+
+    Mock::given(method("GET"))
+        .and(path("/cloud/project"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!(["tenant"])))
+        .mount(&server)
+        .await;
+
+    Mock::given(method("GET"))
+        .and(path("/cloud/project/tenant"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!([
+            {
+                "key": "tenant",
+                "value": {
+                    "project_id": "tenant",
+                    "description": "production",
+                    "planCode": "project.2018",
+                    "unleash": false,
+                    "status": "ok",
+                    "access": "full"
+                }
+            }
+        ])))
+        .mount(&server)
+        .await;
+
+    Mock::given(method("GET"))
+        .and(path("/cloud/project/tenant/instance"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!([
+            {
+                "id": "instance",
+                "name": "web.example.com",
+                "flavorId": "flavor",
+                "imageId": "image",
+                "planCode": "b2-7.consumption",
+                "region": "GRA11",
+                "status": "ACTIVE",
+                "created": null,
+                "ipAddresses": [
+                    { "ip": "192.0.2.10", "type": "public", "version": 4, "networkId": "public" },
+                    { "ip": "10.0.0.5", "type": "private", "version": 4, "networkId": "private" }
+                ]
+            }
+        ])))
+        .mount(&server)
+        .await;
+
+    mount_records(
+        &server,
+        json!([
+            {
+                "id": 1,
+                "fieldType": "A",
+                "subDomain": "web",
+                "ttl": 60,
+                "zone": "example.com",
+                "target": "10.0.0.5"
+            }
+        ]),
+    )
+    .await;
+
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&server)
+        .await;
+
+    Mock::given(method("DELETE"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&server)
+        .await;
+
+    let output = ovhctl(
+        &server,
+        &[
+            "domain",
+            "record",
+            "sync",
+            "example.com",
+            "--diff-only",
+            "-o",
+            "json",
+        ],
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    assert!(output.status.success(), "{:?}", output);
+
+    let plan: serde_json::Value = serde_json::from_str(&stdout).expect("valid json");
+    assert_eq!(plan["create"][0]["target"], "192.0.2.10", "{}", stdout);
+    assert_eq!(plan["create"][0]["subDomain"], "web", "{}", stdout);
+    assert_eq!(plan["delete"][0]["id"], 1, "{}", stdout);
+}
+
+#[tokio::test]
+async fn cloud_instance_list_ips() {
+    let server = MockServer::start().await;
+
+    Mock::given(method("GET"))
+        .and(path("/cloud/project/tenant/instance"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!([
+            {
+                "id": "instance",
+                "name": "web.example.com",
+                "flavorId": "flavor",
+                "imageId": "image",
+                "planCode": "b2-7.consumption",
+                "region": "GRA11",
+                "status": "ACTIVE",
+                "created": null,
+                "ipAddresses": [
+                    { "ip": "192.0.2.10", "type": "public", "version": 4, "networkId": "public" },
+                    { "ip": "2001:db8::10", "type": "public", "version": 6, "networkId": "public" },
+                    { "ip": "10.0.0.5", "type": "private", "version": 4, "networkId": "private" }
+                ]
+            }
+        ])))
+        .mount(&server)
+        .await;
+
+    let output = ovhctl(
+        &server,
+        &["cloud", "instance", "list", "tenant", "--ips", "-o", "json"],
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    assert!(output.status.success(), "{:?}", output);
+
+    let addresses: serde_json::Value = serde_json::from_str(&stdout).expect("valid json");
+    assert_eq!(addresses.as_array().map(Vec::len), Some(2), "{}", stdout);
+    assert_eq!(addresses[0]["ip"], "192.0.2.10", "{}", stdout);
+    assert_eq!(addresses[1]["ip"], "2001:db8::10", "{}", stdout);
+}
+
+#[tokio::test]
+async fn cloud_loadbalancer_create_named() {
+    let server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/cloud/project/tenant/loadbalancer"))
+        .and(body_json(json!({
+            "region": "GRA7",
+            "name": "front",
+            "description": "web frontends"
+        })))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "id": "loadbalancer",
+            "name": "front",
+            "description": "web frontends",
+            "region": "GRA7",
+            "status": "CREATING",
+            "address": { "ipv4": "192.0.2.20" },
+            "configuration": { "applied": 0, "latest": 0 }
+        })))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let output = ovhctl(
+        &server,
+        &[
+            "cloud",
+            "loadbalancer",
+            "create",
+            "tenant",
+            "GRA7",
+            "--name",
+            "front",
+            "--description",
+            "web frontends",
+        ],
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    assert!(output.status.success(), "{:?}", output);
+    assert!(stdout.contains("front"), "{}", stdout);
+}
+
+#[tokio::test]
+async fn ip_move_wait_task() {
+    let server = MockServer::start().await;
+
+    let task = |status: &str| {
+        json!({
+            "taskId": 42,
+            "function": "genericMoveFloatingIp",
+            "status": status,
+            "startDate": null,
+            "doneDate": null,
+            "comment": null
+        })
+    };
+
+    Mock::given(method("POST"))
+        .and(path("/ip/192.0.2.1%2F32/move"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(task("todo")))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    Mock::given(method("GET"))
+        .and(path("/ip/192.0.2.1%2F32/task/42"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(task("doing")))
+        .up_to_n_times(1)
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    Mock::given(method("GET"))
+        .and(path("/ip/192.0.2.1%2F32/task/42"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(task("done")))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let output = ovhctl(
+        &server,
+        &[
+            "ip",
+            "move",
+            "192.0.2.1/32",
+            "--to",
+            "ns1.example.net",
+            "-o",
+            "json",
+        ],
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    assert!(output.status.success(), "{:?}", output);
+    assert!(stdout.contains("\"done\""), "{}", stdout);
+}
+
+#[tokio::test]
+async fn domain_record_list_fields_file() {
+    let server = MockServer::start().await;
+
+    mount_records(
+        &server,
+        json!([
+            {
+                "id": 1,
+                "fieldType": "A",
+                "subDomain": "www",
+                "ttl": 3600,
+                "zone": "example.com",
+                "target": "192.0.2.1"
+            }
+        ]),
+    )
+    .await;
+
+    let fields = std::env::temp_dir().join(format!("ovhctl-fields-{}.txt", std::process::id()));
+    std::fs::write(&fields, "# team columns\ntarget\nsub domain\n")
+        .expect("could not write fields file");
+
+    let output = ovhctl(
+        &server,
+        &[
+            "domain",
+            "record",
+            "list",
+            "example.com",
+            "--fields-file",
+            fields.to_str().unwrap(),
+        ],
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    assert!(output.status.success(), "{:?}", output);
+    assert!(stdout.contains("192.0.2.1"), "{}", stdout);
+    assert!(stdout.contains("www"), "{}", stdout);
+    assert!(!stdout.contains("example.com"), "{}", stdout);
+
+    let output = ovhctl(
+        &server,
+        &[
+            "domain",
+            "record",
+            "list",
+            "example.com",
+            "--fields-file",
+            fields.to_str().unwrap(),
+            "--columns",
+            "ttl",
+        ],
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    assert!(output.status.success(), "{:?}", output);
+    assert!(stdout.contains("3600"), "{}", stdout);
+    assert!(!stdout.contains("192.0.2.1"), "{}", stdout);
+
+    std::fs::remove_file(fields).expect("could not remove fields file");
+}
+
+#[tokio::test]
+async fn domain_record_list_rate_limit() {
+    let server = MockServer::start().await;
+
+    Mock::given(method("GET"))
+        .and(path("/domain/zone/example.com/record"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .insert_header("X-RateLimit-Limit", "60")
+                .insert_header("X-RateLimit-Remaining", "0")
+                .insert_header("X-RateLimit-Reset", "1")
+                .set_body_json(json!([1])),
+        )
+        .mount(&server)
+        .await;
+
+    Mock::given(method("GET"))
+        .and(path("/domain/zone/example.com/record/1"))
+        .and(header_exists("X-Ovh-BatchSeparator"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!([{
+            "key": "1",
+            "value": {
+                "id": 1,
+                "fieldType": "A",
+                "subDomain": "www",
+                "ttl": 3600,
+                "zone": "example.com",
+                "target": "192.0.2.1"
+            }
+        }])))
+        .mount(&server)
+        .await;
+
+    let begin = std::time::Instant::now();
+    let output = ovhctl(&server, &["-v", "domain", "record", "list", "example.com"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    assert!(output.status.success(), "{:?}", output);
+    assert!(
+        stdout.contains("0 request(s) remaining out of 60"),
+        "{}",
+        stdout
+    );
+    assert!(
+        stdout.contains("rate limit of the api is exhausted"),
+        "{}",
+        stdout
+    );
+    assert!(stdout.contains("192.0.2.1"), "{}", stdout);
+    assert!(begin.elapsed() >= std::time::Duration::from_millis(500));
+}
+
+#[tokio::test]
+async fn cloud_instance_list_sort_by_ip() {
+    let server = MockServer::start().await;
+
+    let instance = |name: &str, ip: Option<&str>| {
+        let addresses = match ip {
+            Some(ip) => {
+                json!([{ "ip": ip, "type": "public", "version": 4, "networkId": "public" }])
+            }
+            None => json!([]),
+        };
+
+        json!({
+            "id": name,
+            "name": name,
+            "flavorId": "flavor",
+            "imageId": "image",
+            "planCode": "b2-7.consumption",
+            "region": "GRA11",
+            "status": "ACTIVE",
+            "created": null,
+            "ipAddresses": addresses
+        })
+    };
+
+    Mock::given(method("GET"))
+        .and(path("/cloud/project/tenant/instance"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!([
+            instance("private", None),
+            instance("ten", Some("192.0.2.10")),
+            instance("nine", Some("192.0.2.9")),
+        ])))
+        .mount(&server)
+        .await;
+
+    let output = ovhctl(
+        &server,
+        &[
+            "cloud",
+            "instance",
+            "list",
+            "tenant",
+            "--sort-by-ip",
+            "-o",
+            "json",
+        ],
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    assert!(output.status.success(), "{:?}", output);
+    let nine = stdout.find("\"nine\"").expect("missing instance 'nine'");
+    let ten = stdout.find("\"ten\"").expect("missing instance 'ten'");
+    let private = stdout
+        .find("\"private\"")
+        .expect("missing instance 'private'");
+    assert!(nine < ten && ten < private, "{}", stdout);
+}
+
+#[tokio::test]
+async fn cloud_instance_create_check_quota() {
+    let server = MockServer::start().await;
+
+    Mock::given(method("GET"))
+        .and(path("/cloud/project/tenant/quota"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!([
+            {
+                "region": "BHS5",
+                "instance": { "maxInstances": 20, "usedInstances": 1 }
+            },
+            {
+                "region": "GRA11",
+                "instance": { "maxInstances": 10, "usedInstances": 10 }
+            }
+        ])))
+        .mount(&server)
+        .await;
+
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&server)
+        .await;
+
+    let output = ovhctl(
+        &server,
+        &[
+            "--json-errors",
+            "cloud",
+            "instance",
+            "create",
+            "tenant",
+            "web",
+            "--flavor",
+            "flavor",
+            "--image",
+            "image",
+            "--region",
+            "GRA11",
+            "--check-quota",
+        ],
+    );
+    let stderr = String::from_utf8_lossy(&output.stderr);
+
+    assert!(!output.status.success(), "{:?}", output);
+    assert!(
+        stderr.contains("region 'GRA11' is at instance quota (10/10)"),
+        "{}",
+        stderr
+    );
+}
+
+#[tokio::test]
+async fn cloud_instance_rename_default_tenant() {
+    let server = MockServer::start().await;
+
+    Mock::given(method("PUT"))
+        .and(path("/cloud/project/tenant/instance/instance-1"))
+        .and(body_json(json!({ "instanceName": "web-2" })))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!(null)))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    Mock::given(method("GET"))
+        .and(path("/cloud/project/tenant/instance/instance-1"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "id": "instance-1",
+            "name": "web-2",
+            "flavorId": "flavor",
+            "imageId": "image",
+            "planCode": "b2-7.consumption",
+            "region": "GRA11",
+            "status": "ACTIVE",
+            "created": null,
+            "ipAddresses": []
+        })))
+        .mount(&server)
+        .await;
+
+    let output = ovhctl(
+        &server,
+        &[
+            "--tenant",
+            "tenant",
+            "cloud",
+            "instance",
+            "rename",
+            "instance-1",
+            "--name",
+            "web-2",
+        ],
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    assert!(output.status.success(), "{:?}", output);
+    assert!(stdout.contains("web-2"), "{}", stdout);
+}
+
+#[tokio::test]
+async fn domain_record_delete_confirm_destructive() {
+    let server = MockServer::start().await;
+
+    Mock::given(method("DELETE"))
+        .and(path("/domain/zone/example.com/record/1"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!(null)))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    // the standard input of the tests is not a terminal, so that nothing is asked
+    // by default
+    let output = ovhctl(&server, &["domain", "record", "delete", "example.com", "1"]);
+    assert!(output.status.success(), "{:?}", output);
+
+    let output = ovhctl(
+        &server,
+        &[
+            "--confirm-destructive",
+            "always",
+            "domain",
+            "record",
+            "delete",
+            "example.com",
+            "1",
+        ],
+    );
+    let stderr = String::from_utf8_lossy(&output.stderr);
+
+    assert!(!output.status.success(), "{:?}", output);
+    assert!(stderr.contains("has been aborted"), "{}", stderr);
+}
+
+#[tokio::test]
+async fn domain_record_list_yaml_multidoc() {
+    let server = MockServer::start().await;
+
+    mount_records(
+        &server,
+        json!([
+            {
+                "id": 1,
+                "fieldType": "A",
+                "subDomain": "www",
+                "ttl": 3600,
+                "zone": "example.com",
+                "target": "192.0.2.1"
+            },
+            {
+                "id": 2,
+                "fieldType": "A",
+                "subDomain": "blog",
+                "ttl": 60,
+                "zone": "example.com",
+                "target": "192.0.2.2"
+            }
+        ]),
+    )
+    .await;
+
+    let output = ovhctl(
+        &server,
+        &[
+            "domain",
+            "record",
+            "list",
+            "example.com",
+            "--yaml-multidoc",
+            "-o",
+            "yaml",
+        ],
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(2, stdout.matches("---\n").count(), "{}", stdout);
+    assert!(stdout.starts_with("---\n"), "{}", stdout);
+    assert!(!stdout.contains("- id:"), "{}", stdout);
+}
+
+#[tokio::test]
+async fn domain_record_list_with_meta() {
+    let server = MockServer::start().await;
+
+    mount_records(
+        &server,
+        json!([
+            {
+                "id": 1,
+                "fieldType": "A",
+                "subDomain": "www",
+                "ttl": 3600,
+                "zone": "example.com",
+                "target": "192.0.2.1"
+            }
+        ]),
+    )
+    .await;
+
+    let output = ovhctl(
+        &server,
+        &[
+            "domain",
+            "record",
+            "list",
+            "example.com",
+            "--with-meta",
+            "-o",
+            "json",
+        ],
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    assert!(output.status.success(), "{:?}", output);
+
+    let document: serde_json::Value = serde_json::from_str(&stdout).expect("valid json");
+    assert_eq!(document["data"][0]["target"], "192.0.2.1", "{}", stdout);
+    assert_eq!(document["meta"]["endpoint"], server.uri(), "{}", stdout);
+    assert!(
+        document["meta"]["requests"].as_u64() >= Some(2),
+        "{}",
+        stdout
+    );
+    assert!(document["meta"]["duration_ms"].is_u64(), "{}", stdout);
+}
+
+#[tokio::test]
+async fn self_update_check() {
+    let server = MockServer::start().await;
+
+    Mock::given(method("GET"))
+        .and(path("/releases/latest"))
+        .and(header_exists("User-Agent"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "tag_name": "v999.0.0",
+            "html_url": "https://github.com/FlorentinDUBOIS/ovhctl/releases/tag/v999.0.0"
+        })))
+        .mount(&server)
+        .await;
+
+    let self_update = |url: String| {
+        Command::new(env!("CARGO_BIN_EXE_ovhctl"))
+            .args(["--endpoint-test", "--no-color", "self-update", "-o", "json"])
+            .env("OVHCTL_TEST_ENDPOINT", server.uri())
+            .env("OVHCTL_RELEASES_URL", url)
+            .output()
+            .expect("could not execute ovhctl")
+    };
+
+    let output = self_update(format!("{}/releases/latest", server.uri()));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    assert!(output.status.success(), "{:?}", output);
+    assert!(stdout.contains("\"latest\": \"999.0.0\""), "{}", stdout);
+    assert!(stdout.contains("\"outdated\": true"), "{}", stdout);
+
+    // a version with a trailing zero is the same as the current one
+    let current = format!("{}.0", env!("CARGO_PKG_VERSION"));
+    Mock::given(method("GET"))
+        .and(path("/releases/current"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "tag_name": format!("v{}", current),
+            "html_url": format!("https://github.com/FlorentinDUBOIS/ovhctl/releases/tag/v{}", current)
+        })))
+        .mount(&server)
+        .await;
+
+    let output = self_update(format!("{}/releases/current", server.uri()));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    assert!(output.status.success(), "{:?}", output);
+    assert!(stdout.contains("\"outdated\": false"), "{}", stdout);
+
+    let output = self_update(String::from("http://127.0.0.1:1/releases/latest"));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+
+    assert!(!output.status.success(), "{:?}", output);
+    assert!(stderr.contains("check the network access"), "{}", stderr);
+}
+
+#[tokio::test]
+async fn domain_record_refresh_without_body() {
+    let server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/domain/zone/example.com/refresh"))
+        .and(|request: &wiremock::Request| {
+            request.body.is_empty()
+                && !request
+                    .headers
+                    .keys()
+                    .any(|name| name.as_str().eq_ignore_ascii_case("content-type"))
+        })
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!(null)))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let output = ovhctl(&server, &["domain", "record", "refresh", "example.com"]);
+    assert!(output.status.success(), "{:?}", output);
+}
+
+#[tokio::test]
+async fn cloud_instance_list_since_out_of_range() {
+    let server = MockServer::start().await;
+
+    for since in ["999999999999999d", "99999999999d"] {
+        let output = ovhctl(
+            &server,
+            &["cloud", "instance", "list", "tenant", "--since", since],
+        );
+        let stderr = String::from_utf8_lossy(&output.stderr);
+
+        assert_eq!(Some(2), output.status.code(), "{:?}", output);
+        assert!(stderr.contains("duration out of range"), "{}", stderr);
+    }
+}